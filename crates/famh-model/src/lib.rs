@@ -21,6 +21,15 @@ pub enum Numeric {
     Float(f64),
 }
 
+impl Numeric {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Self::Integer(value) => *value as f64,
+            Self::Float(value) => *value,
+        }
+    }
+}
+
 impl From<i64> for Numeric {
     fn from(value: i64) -> Self {
         Self::Integer(value)
@@ -291,4 +291,13 @@ mod tests {
         );
         assert_eq!(value["Data Evaluation"]["POI"][0]["Name"], json!("POI-1"));
     }
+
+    #[test]
+    fn vendor_sections_name_the_typed_tool_sections() {
+        for name in ToolSpecific::VENDOR_SECTIONS {
+            let tool: ToolSpecific = crate::from_value(json!({ *name: {} })).unwrap();
+            assert!(tool.extra.is_empty(), "{name} is not a typed section");
+            assert_eq!(crate::to_value(&tool).unwrap(), json!({ *name: {} }));
+        }
+    }
 }
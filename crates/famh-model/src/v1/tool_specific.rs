@@ -94,6 +94,22 @@ pub struct ToolSpecific {
 }
 
 impl ToolSpecific {
+    /// Names of the vendor sections, e.g. `ZEISS GeminiSEM 500`.
+    pub const VENDOR_SECTIONS: &'static [&'static str] = &[
+        "Tescan XEIA3",
+        "ZEISS GeminiSEM 500",
+        "ZEISS LEO Gemini 1550",
+        "ZEISS LEO Gemini 1560",
+        "ZEISS Gemini ULTRA 55",
+        "ZEISS Gemini SUPRA 55",
+        "HITACHI SU8000",
+        "HITACHI SU8200",
+        "FEI Magellan 400",
+        "Olympus DSX110",
+        "Olympus DSX500",
+        "Olympus DSX1000",
+    ];
+
     pub fn new() -> Self {
         Self::default()
     }
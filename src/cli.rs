@@ -1,6 +1,15 @@
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::schema::SchemaVersion;
+use crate::inputs::{DEFAULT_INCLUDE, InputOptions};
+use crate::lint::{Linter, Severity};
+use crate::report::OutputFormat;
+use crate::schema::{
+    AUTO_VERSION, DEFAULT_RETRIES, HttpConfig, SCHEMA_BASE_URL, SchemaConfig, SchemaVersion,
+    VersionSelection,
+};
+use crate::utils::parse_duration;
 
 #[derive(Parser)]
 #[command(name = "famdo")]
@@ -17,20 +26,151 @@ pub enum Commands {
     Extract(ExtractArgs),
     Edit(EditArgs),
     Delete(DeleteArgs),
+    /// Check FAMH headers for inconsistencies between fields
+    Lint(LintArgs),
+    /// Compare a FAMH header with the TIFF image it describes
+    Verify(VerifyArgs),
+    /// Inspect and manage the FAMH schema cache
+    Schema(SchemaCommandArgs),
 }
 
 #[derive(Args, Clone)]
 pub struct ValidateArgs {
-    pub path: String,
+    /// FAMH documents, directories or `@<filelist>`s with one input per line; `-` reads stdin
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Also validate files in subdirectories of the given directories
+    #[arg(short, long, default_value_t = false)]
+    pub recursive: bool,
+
+    /// Glob selecting the files to validate inside directories (repeatable)
+    #[arg(long, default_value = DEFAULT_INCLUDE)]
+    pub include: Vec<String>,
+
+    /// Glob of files or directories to skip inside directories (repeatable)
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Number of files validated in parallel [default: number of CPUs]
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
 
-    #[arg(short, long, value_enum, default_value_t = SchemaVersion::V1)]
-    pub version: SchemaVersion,
+    /// Schema version: `auto`, `v1`, `v2` or a version added by a schema manifest
+    #[arg(short, long, default_value = AUTO_VERSION)]
+    pub version: String,
 
     #[arg(short, long, default_value_t = false)]
     pub no_cache: bool,
 
     #[arg(long, default_value_t = false)]
     pub strict: bool,
+
+    /// Output format of the validation result
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Read every input as NDJSON, one document per line, and report each record
+    #[arg(long, default_value_t = false)]
+    pub ndjson: bool,
+
+    /// Apply safe repairs to the files, print the diff and validate the result
+    #[arg(long, default_value_t = false, conflicts_with = "ndjson")]
+    pub fix: bool,
+
+    /// With --fix, only list the repairs without changing any file
+    #[arg(long, default_value_t = false, requires = "fix")]
+    pub dry_run: bool,
+
+    /// Also check the requirements of a profile, by name or path to its JSON file (repeatable)
+    #[arg(long)]
+    pub profile: Vec<String>,
+
+    /// Directory of named profiles [default: famdo/profiles in the configuration directory]
+    #[arg(long, env = "FAMDO_PROFILE_DIR")]
+    pub profile_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+impl ValidateArgs {
+    pub fn input_options(&self) -> InputOptions {
+        InputOptions {
+            recursive: self.recursive,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+}
+
+// Options selecting where the FAMH schema is loaded from
+#[derive(Args, Clone)]
+pub struct SchemaArgs {
+    /// Load the schema from a local directory (e.g. a fa-metadata-schema checkout)
+    #[arg(long, env = "FAMDO_SCHEMA_DIR", conflicts_with_all = ["schema_url", "schema_ref"])]
+    pub schema_dir: Option<PathBuf>,
+
+    /// Base URL to download the schema from, followed by /<ref>/schema/<version>/<file>
+    #[arg(long, env = "FAMDO_SCHEMA_URL", default_value = SCHEMA_BASE_URL)]
+    pub schema_url: String,
+
+    /// Branch, tag or commit of the schema repository
+    #[arg(long, env = "FAMDO_SCHEMA_REF")]
+    pub schema_ref: Option<String>,
+
+    /// Revalidate cached schemas older than this, e.g. `30m`, `12h`, `7d` or `0` to always check
+    #[arg(long, env = "FAMDO_SCHEMA_TTL", default_value = "24h", value_parser = parse_duration)]
+    pub schema_ttl: Duration,
+
+    /// Directory holding the schema cache instead of the platform cache directory
+    #[arg(long, env = "FAMDO_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Manifest adding schema versions or replacing the built-in ones
+    #[arg(long, env = "FAMDO_SCHEMA_MANIFEST")]
+    pub schema_manifest: Option<PathBuf>,
+
+    /// Proxy for HTTPS schema downloads, overriding HTTPS_PROXY
+    #[arg(long, env = "FAMDO_HTTPS_PROXY")]
+    pub https_proxy: Option<String>,
+
+    /// PEM file with additional CA certificates to trust, e.g. a corporate root CA
+    #[arg(long, env = "FAMDO_CA_BUNDLE")]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Timeout for establishing a connection to the schema server
+    #[arg(long, env = "FAMDO_CONNECT_TIMEOUT", default_value = "10s", value_parser = parse_duration)]
+    pub connect_timeout: Duration,
+
+    /// Timeout for reading from the schema server
+    #[arg(long, env = "FAMDO_READ_TIMEOUT", default_value = "30s", value_parser = parse_duration)]
+    pub read_timeout: Duration,
+
+    /// Retries after connection errors and 5xx responses, with exponential backoff
+    #[arg(long, env = "FAMDO_RETRIES", default_value_t = DEFAULT_RETRIES)]
+    pub retries: u32,
+}
+
+impl SchemaArgs {
+    pub fn config(&self) -> SchemaConfig {
+        SchemaConfig {
+            base_url: self.schema_url.clone(),
+            git_ref: self.schema_ref.clone(),
+            dir: self.schema_dir.clone(),
+            ttl: self.schema_ttl,
+            cache_dir: self.cache_dir.clone(),
+            manifest: self.schema_manifest.clone(),
+            http: HttpConfig {
+                https_proxy: self.https_proxy.clone(),
+                ca_bundle: self.ca_bundle.clone(),
+                connect_timeout: self.connect_timeout,
+                read_timeout: self.read_timeout,
+                retries: self.retries,
+                ..HttpConfig::default()
+            },
+        }
+    }
 }
 
 #[derive(Args, Clone)]
@@ -39,6 +179,10 @@ pub struct ExtractArgs {
 
     #[arg(short, long, default_value = "extracted_metadata.json")]
     pub out: String,
+
+    /// Build a FAMH header of this version instead of dumping the raw tags
+    #[arg(long, value_enum, value_name = "VERSION")]
+    pub famh: Option<SchemaVersion>,
 }
 
 #[derive(Args, Clone)]
@@ -52,8 +196,8 @@ pub struct EditArgs {
     #[arg(short, long, default_value = "metadata_edited.json")]
     pub out: String,
 
-    #[arg(short, long, value_enum, default_value_t = SchemaVersion::V1)]
-    pub version: SchemaVersion,
+    #[arg(short, long, value_enum, default_value_t = VersionSelection::Auto)]
+    pub version: VersionSelection,
 }
 
 #[derive(Args, Clone)]
@@ -65,6 +209,154 @@ pub struct DeleteArgs {
     #[arg(short, long, default_value = "metadata_deleted.json")]
     pub out: String,
 
-    #[arg(short, long, value_enum, default_value_t = SchemaVersion::V1)]
-    pub version: SchemaVersion,
+    #[arg(short, long, value_enum, default_value_t = VersionSelection::Auto)]
+    pub version: VersionSelection,
+}
+
+#[derive(Args, Clone)]
+pub struct LintArgs {
+    #[arg(required_unless_present = "list_rules")]
+    pub path: Option<String>,
+
+    #[arg(short, long, value_enum, default_value_t = VersionSelection::Auto)]
+    pub version: VersionSelection,
+
+    /// Enable a rule that was disabled (repeatable)
+    #[arg(long, value_name = "RULE")]
+    pub enable: Vec<String>,
+
+    /// Disable a rule (repeatable)
+    #[arg(long, value_name = "RULE")]
+    pub disable: Vec<String>,
+
+    /// Change the severity of a rule, e.g. `pixel-size=warning` (repeatable)
+    #[arg(long, value_name = "RULE=SEVERITY", value_parser = parse_rule_severity)]
+    pub severity: Vec<(String, Severity)>,
+
+    /// List the available rules and exit
+    #[arg(long, default_value_t = false)]
+    pub list_rules: bool,
+}
+
+impl LintArgs {
+    /// A linter with the built-in rules, configured by the options.
+    pub fn linter(&self) -> Result<Linter, Box<dyn std::error::Error>> {
+        let mut linter = Linter::new();
+        for id in &self.enable {
+            linter.set_enabled(id, true)?;
+        }
+        for id in &self.disable {
+            linter.set_enabled(id, false)?;
+        }
+        for (id, severity) in &self.severity {
+            linter.set_severity(id, *severity)?;
+        }
+        Ok(linter)
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct VerifyArgs {
+    pub header: String,
+
+    /// TIFF image to compare against [default: the image named by the
+    /// header's file path or file name]
+    #[arg(short, long)]
+    pub image: Option<String>,
+
+    #[arg(short, long, value_enum, default_value_t = VersionSelection::Auto)]
+    pub version: VersionSelection,
+}
+
+fn parse_rule_severity(input: &str) -> Result<(String, Severity), String> {
+    let (rule, severity) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected RULE=SEVERITY, got '{input}'"))?;
+    Ok((rule.trim().to_string(), severity.trim().parse()?))
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaCommandArgs {
+    #[command(subcommand)]
+    pub command: SchemaCommands,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum SchemaCommands {
+    /// List all cached schemas
+    List(SchemaListArgs),
+    /// Print the cache directory of a schema version (or the cache root)
+    Path(SchemaCacheArgs),
+    /// Download the schema again and overwrite the cached copy
+    Refresh(SchemaCacheArgs),
+    /// Remove cached schemas
+    Clear(SchemaClearArgs),
+    /// Pretty-print a single schema fragment, e.g. `methodSpecific`
+    Show(SchemaShowArgs),
+    /// Describe a field, e.g. `generalSection.fileSize`, from the schema
+    Describe(SchemaDescribeArgs),
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaListArgs {
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaCacheArgs {
+    #[arg(short, long)]
+    pub version: Option<String>,
+
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaClearArgs {
+    #[arg(short, long)]
+    pub version: Option<String>,
+
+    /// Remove the cache of every source, ref and version
+    #[arg(long, default_value_t = false)]
+    pub all: bool,
+
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaShowArgs {
+    /// Section name, file name or label of the fragment
+    pub section: String,
+
+    /// Schema version, `auto` picks the first version with the section
+    #[arg(short, long, default_value = AUTO_VERSION)]
+    pub version: String,
+
+    #[arg(short, long, default_value_t = false)]
+    pub no_cache: bool,
+
+    #[command(flatten)]
+    pub schema: SchemaArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct SchemaDescribeArgs {
+    /// Field path in dot notation or as JSON pointer
+    pub field: String,
+
+    /// Print all nested fields as a tree, e.g. the whole structure of a section
+    #[arg(long, default_value_t = false)]
+    pub tree: bool,
+
+    /// Schema version, `auto` picks the version defining the field's section
+    #[arg(short, long, default_value = AUTO_VERSION)]
+    pub version: String,
+
+    #[arg(short, long, default_value_t = false)]
+    pub no_cache: bool,
+
+    #[command(flatten)]
+    pub schema: SchemaArgs,
 }
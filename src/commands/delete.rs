@@ -1,10 +1,9 @@
-use std::fs::File;
-use std::io::BufReader;
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 
-use crate::schema::SchemaVersion;
-use crate::utils::{field_to_json_pointer, write_bytes_atomically};
+use crate::schema::{SchemaVersion, VersionSelection};
+use crate::source::read_json;
+use crate::utils::{decode_pointer_token, field_to_json_pointer, write_bytes_atomically};
 use famh_model::{v1, v2};
 use serde_json::Value;
 
@@ -14,7 +13,7 @@ use serde_json::Value;
 /// `("/generalSection", "fileName")`.
 ///
 /// The returned child token is still JSON Pointer encoded. Call
-/// [`decode_reference_token`] before using it as an object key.
+/// [`decode_pointer_token`] before using it as an object key.
 pub fn extract_parent_pointer(pointer: &str) -> Result<(&str, &str), String> {
     if pointer.is_empty() {
         return Err("Invalid JSON pointer - cannot delete the document root.".to_string());
@@ -26,35 +25,6 @@ pub fn extract_parent_pointer(pointer: &str) -> Result<(&str, &str), String> {
     Ok((parent, child))
 }
 
-/// Decode a single RFC 6901 reference token into the real object key.
-///
-/// JSON Pointer escapes `/` as `~1` and `~` as `~0`. `serde_json` handles
-/// those escapes when traversing with `pointer()` and `pointer_mut()`, but
-/// once we have the parent container we must decode the final token ourselves
-/// before calling `Map::remove`.
-///
-/// For example, the final token in `/a~1b` is `a~1b`, but the actual JSON key
-/// is `a/b`.
-fn decode_reference_token(token: &str) -> Result<String, String> {
-    let mut decoded = String::with_capacity(token.len());
-    let mut chars = token.chars();
-
-    while let Some(ch) = chars.next() {
-        if ch != '~' {
-            decoded.push(ch);
-            continue;
-        }
-
-        match chars.next() {
-            Some('0') => decoded.push('~'),
-            Some('1') => decoded.push('/'),
-            _ => return Err(format!("Invalid JSON pointer token: {token}")),
-        }
-    }
-
-    Ok(decoded)
-}
-
 /// Remove the value addressed by `pointer` from its parent container.
 ///
 /// This differs from `pointer_mut(pointer)` on the full path: deletion must
@@ -62,7 +32,9 @@ fn decode_reference_token(token: &str) -> Result<String, String> {
 /// entirely instead of being replaced with `null`.
 fn remove_at_pointer(schema_doc: &mut Value, pointer: &str) -> Result<(), String> {
     let (parent_pointer, child_pointer) = extract_parent_pointer(pointer)?;
-    let child_pointer = decode_reference_token(child_pointer)?;
+    // `pointer_mut` decodes the parent's tokens; the child's is ours to decode,
+    // e.g. `a~1b` for the key `a/b`.
+    let child_pointer = decode_pointer_token(child_pointer);
 
     let parent = schema_doc
         .pointer_mut(parent_pointer)
@@ -113,22 +85,19 @@ pub fn delete_metadata_field(
     file_path: impl AsRef<Path>,
     field: String,
     out_path: impl AsRef<Path>,
-    version: SchemaVersion,
+    version: impl Into<VersionSelection>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file_path_ref = file_path.as_ref();
-
-    let file = File::open(file_path_ref)?;
-    let reader = BufReader::new(file);
+    let path = file_path.as_ref().to_string_lossy();
+    let (document, source) = read_json(&path)?;
+    let version = version.into().resolve(&document)?;
 
     let mut schema_doc = match version {
-        SchemaVersion::V1 => {
-            let schema: v1::FaMetadataHeader = v1::FaMetadataHeader::from_reader(reader)?;
-            schema.to_value()?
-        }
-        SchemaVersion::V2 => {
-            let schema: v2::FaMetadataHeader = v2::FaMetadataHeader::from_reader(reader)?;
-            schema.to_value()?
-        }
+        SchemaVersion::V1 => source
+            .deserialize::<v1::FaMetadataHeader>(&path)?
+            .to_value()?,
+        SchemaVersion::V2 => source
+            .deserialize::<v2::FaMetadataHeader>(&path)?
+            .to_value()?,
     };
 
     let pointer = field_to_json_pointer(&field);
@@ -209,6 +178,36 @@ mod tests {
         assert!(!out_path.exists());
     }
 
+    #[test]
+    fn test_delete_metadata_field_locates_type_errors() {
+        let source = fs::read_to_string("tests/fixtures/v2/minimal_example_optical.json").unwrap();
+        let mut source_file = NamedTempFile::new().unwrap();
+        write!(
+            source_file,
+            "{}",
+            source.replace("\"optical_defect_overview.jpg\"", "42")
+        )
+        .unwrap();
+        let out_dir = TempDir::new().unwrap();
+        let out_path = out_dir.path().join("type_error.json");
+
+        let err = delete_metadata_field(
+            source_file.path(),
+            "generalSection.toolName".to_string(),
+            &out_path,
+            SchemaVersion::V2,
+        )
+        .unwrap_err()
+        .to_string();
+
+        let path = source_file.path().display();
+        assert!(
+            err.starts_with(&format!("{path}:3:17: invalid type: integer `42`")),
+            "{err}"
+        );
+        assert!(!out_path.exists());
+    }
+
     #[test]
     fn test_delete_metadata_field_removes_top_level_v1_section() {
         let source_file = write_fixture_to_temp("tests/fixtures/v1/minimal_example_optical.json");
@@ -267,6 +266,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_delete_metadata_field_detects_version_automatically() {
+        let out_dir = TempDir::new().unwrap();
+        for (fixture, field) in [
+            (
+                "tests/fixtures/v1/minimal_example_optical.json",
+                "General Section.File Name",
+            ),
+            (
+                "tests/fixtures/v2/minimal_example_optical.json",
+                "generalSection.fileName",
+            ),
+        ] {
+            let source_file = write_fixture_to_temp(fixture);
+            let out_path = out_dir.path().join("deleted_auto.json");
+
+            delete_metadata_field(
+                source_file.path(),
+                field.to_string(),
+                &out_path,
+                VersionSelection::Auto,
+            )
+            .unwrap();
+
+            let updated: Value =
+                serde_json::from_str(&fs::read_to_string(&out_path).unwrap()).unwrap();
+            assert!(updated.pointer(&field_to_json_pointer(field)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_delete_metadata_field_rejects_mixed_document_in_auto_mode() {
+        let mut source_file = NamedTempFile::new().unwrap();
+        source_file
+            .write_all(br#"{"General Section": {}, "methodSpecific": {}}"#)
+            .unwrap();
+        let out_dir = TempDir::new().unwrap();
+        let out_path = out_dir.path().join("mixed.json");
+
+        let err = delete_metadata_field(
+            source_file.path(),
+            "methodSpecific".to_string(),
+            &out_path,
+            VersionSelection::Auto,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("mixes v1 sections"));
+        assert!(!out_path.exists());
+    }
+
     #[test]
     fn test_extract_parent_pointer_handles_root_and_nested_fields() {
         assert_eq!(
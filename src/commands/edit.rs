@@ -2,12 +2,10 @@ use famh_model::{v1, v2};
 use serde_json::Value;
 use std::path::Path;
 
-use std::{
-    fs::File,
-    io::{BufReader, BufWriter},
-};
+use std::{fs::File, io::BufWriter};
 
-use crate::schema::SchemaVersion;
+use crate::schema::{SchemaVersion, VersionSelection};
+use crate::source::read_json;
 use crate::utils::field_to_json_pointer;
 
 /// Parse the value from the user
@@ -20,22 +18,19 @@ pub fn edit_famh_file(
     field: String,
     value: String,
     out_path: impl AsRef<Path>,
-    version: SchemaVersion,
+    version: impl Into<VersionSelection>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file_path_ref = file_path.as_ref();
-
-    let file = File::open(file_path_ref)?;
-    let reader = BufReader::new(file);
+    let path = file_path.as_ref().to_string_lossy();
+    let (document, source) = read_json(&path)?;
+    let version = version.into().resolve(&document)?;
 
     let mut schema_doc = match version {
-        SchemaVersion::V1 => {
-            let schema: v1::FaMetadataHeader = v1::FaMetadataHeader::from_reader(reader)?;
-            schema.to_value()?
-        }
-        SchemaVersion::V2 => {
-            let schema: v2::FaMetadataHeader = v2::FaMetadataHeader::from_reader(reader)?;
-            schema.to_value()?
-        }
+        SchemaVersion::V1 => source
+            .deserialize::<v1::FaMetadataHeader>(&path)?
+            .to_value()?,
+        SchemaVersion::V2 => source
+            .deserialize::<v2::FaMetadataHeader>(&path)?
+            .to_value()?,
     };
 
     let pointer = field_to_json_pointer(&field);
@@ -1,13 +1,93 @@
+use serde::Serialize;
 use serde_json::{Value, json};
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 use tiff::decoder::Decoder;
 use tiff::decoder::ifd;
+use tiff::tags::{CompressionMethod, Tag};
+
+mod acquisition;
+mod famh;
+mod fei;
+mod ini;
+mod raw_tags;
+mod tescan;
+mod zeiss;
+
+pub use acquisition::{Acquisition, Beam, Quantity, StagePosition};
+pub use famh::{ImageMetadata, extract_and_save_famh_header, read_image_metadata};
+
+/// Properties of the first image of a TIFF file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TiffInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Bits of each sample (channel) of a pixel.
+    pub bits_per_sample: Vec<u16>,
+    /// Name of the compression scheme, e.g. `None` or `LZW`.
+    pub compression: String,
+    /// Bytes of (compressed) image data, summed over all strips or tiles.
+    pub data_size: u64,
+    pub file_size: u64,
+}
+
+impl TiffInfo {
+    /// Average number of bits per pixel as stored in the file.
+    pub fn compressed_bits_per_pixel(&self) -> f64 {
+        let pixels = u64::from(self.width) * u64::from(self.height);
+        if pixels == 0 {
+            return 0.0;
+        }
+        (self.data_size * 8) as f64 / pixels as f64
+    }
+}
+
+fn open_tiff(image_path: &Path) -> Result<Decoder<BufReader<File>>, Box<dyn std::error::Error>> {
+    let file = File::open(image_path)
+        .map_err(|e| format!("Failed to open image {}: {}", image_path.display(), e))?;
+    Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Cannot read {} as TIFF: {}", image_path.display(), e).into())
+}
+
+/// Read dimensions, sample depth and compression of a TIFF file.
+pub fn read_tiff_info(
+    image_path: impl AsRef<Path>,
+) -> Result<TiffInfo, Box<dyn std::error::Error>> {
+    let image_path = image_path.as_ref();
+    let mut decoder = open_tiff(image_path)?;
+    let (width, height) = decoder.dimensions()?;
+    let bits_per_sample = decoder
+        .find_tag_unsigned_vec::<u16>(Tag::BitsPerSample)?
+        .unwrap_or_else(|| vec![1]);
+    let compression = decoder
+        .find_tag_unsigned::<u16>(Tag::Compression)?
+        .unwrap_or(1);
+    let compression = match CompressionMethod::from_u16(compression) {
+        Some(method) => format!("{method:?}"),
+        None => format!("Unknown({compression})"),
+    };
+    let byte_counts = match decoder.find_tag_unsigned_vec::<u64>(Tag::StripByteCounts)? {
+        Some(counts) => counts,
+        None => decoder
+            .find_tag_unsigned_vec::<u64>(Tag::TileByteCounts)?
+            .unwrap_or_default(),
+    };
+
+    Ok(TiffInfo {
+        width,
+        height,
+        bits_per_sample,
+        compression,
+        data_size: byte_counts.iter().sum(),
+        file_size: std::fs::metadata(image_path)?.len(),
+    })
+}
 
 /// Extract metadata from a TIFF file and save it to a JSON file.
 pub fn extract_metadata(image_path: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let file = File::open(image_path)?;
-    let mut decoder = Decoder::new(BufReader::new(file))?;
+    let mut decoder = open_tiff(Path::new(image_path))?;
     let (width, height) = decoder.dimensions()?;
     println!("Dimensions: {} x {}", width, height);
 
@@ -155,4 +235,14 @@ mod tests {
         assert_eq!(dims["width"], json!(640));
         assert_eq!(dims["height"], json!(480));
     }
+
+    #[test]
+    fn test_read_tiff_info() {
+        let info = read_tiff_info("tests/fixtures/images/example_image.tif").unwrap();
+        assert_eq!((info.width, info.height), (640, 480));
+        assert_eq!(info.bits_per_sample, vec![8]);
+        assert_eq!(info.compression, "PackBits");
+        assert_eq!(info.file_size, 310202);
+        assert!((info.compressed_bits_per_pixel() - 8.062).abs() < 1e-3);
+    }
 }
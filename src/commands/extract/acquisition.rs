@@ -0,0 +1,309 @@
+//! Acquisition parameters decoded from the private TIFF tags of tool vendors,
+//! and how they fill a FAMH header.
+
+use famh_model::{JsonMap, NumberArrayWithUnit, NumberWithUnit, Numeric, v1, v2};
+use serde_json::{Value, json};
+
+/// A number with its unit as written by the tool, e.g. `5.1 mm`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: String,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: impl Into<String>) -> Self {
+        Self {
+            value,
+            unit: normalize_unit(&unit.into()),
+        }
+    }
+
+    /// A value in SI units (V, m, A, rad) converted to `unit`, e.g. `0.0051`
+    /// to `5.1 mm`. Units other than the ones used by FAMH are kept in SI.
+    pub fn from_si(value: f64, unit: &str) -> Self {
+        let factor = match unit {
+            "kV" | "km" => 1e-3,
+            "mm" => 1e3,
+            "µm" | "µA" => 1e6,
+            "nm" | "nA" => 1e9,
+            "pA" => 1e12,
+            "deg" => 180.0 / std::f64::consts::PI,
+            _ => return Self::new(value, ""),
+        };
+        // Round away the noise of the conversion, e.g. 4.119999999999999 mm.
+        let value = format!("{:.11e}", value * factor).parse().unwrap_or(value);
+        Self::new(value, unit)
+    }
+
+    /// Parse `5.1 mm`; a bare number gets an empty unit.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (number, unit) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        Some(Self::new(number.parse().ok()?, unit.trim()))
+    }
+
+    fn v1(&self) -> v1::LegacyNumberWithUnit {
+        v1::LegacyNumberWithUnit::new(number(self.value), &self.unit)
+    }
+
+    fn v2(&self) -> NumberWithUnit {
+        NumberWithUnit::new(number(self.value), &self.unit)
+    }
+}
+
+/// The stage position at the time of the acquisition.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StagePosition {
+    pub x: Option<Quantity>,
+    pub y: Option<Quantity>,
+    pub z: Option<Quantity>,
+    /// Rotation around the Z axis.
+    pub rotation: Option<Quantity>,
+    /// Tilt around the X axis.
+    pub tilt: Option<Quantity>,
+}
+
+impl StagePosition {
+    /// X, Y and Z in their common unit. Missing coordinates are `None`.
+    fn position(&self) -> Option<(Vec<Option<Numeric>>, String)> {
+        let coordinates = [&self.x, &self.y, &self.z];
+        let unit = &coordinates.iter().find_map(|c| c.as_ref())?.unit;
+        if coordinates
+            .iter()
+            .copied()
+            .flatten()
+            .any(|c| &c.unit != unit)
+        {
+            return None;
+        }
+        let values = coordinates
+            .iter()
+            .map(|c| c.as_ref().map(|c| number(c.value)))
+            .collect();
+        Some((values, unit.clone()))
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The beam that recorded an image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Beam {
+    #[default]
+    Electron,
+    Ion,
+}
+
+/// What a vendor's metadata block tells about an SEM or FIB image.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Acquisition {
+    pub manufacturer: Option<String>,
+    pub tool_name: Option<String>,
+    /// The v1 tool specific section of the block, whatever the tool, e.g.
+    /// `Tescan XEIA3`.
+    pub vendor_section: Option<&'static str>,
+    /// Electron images fill the SEM section, ion images the FIB section.
+    pub beam: Beam,
+    pub accelerating_voltage: Option<Quantity>,
+    pub working_distance: Option<Quantity>,
+    /// The beam current, written as `ionCurrent` for ion images in v2.
+    pub probe_current: Option<Quantity>,
+    pub emission_current: Option<Quantity>,
+    /// As displayed by the tool, e.g. `50000x`.
+    pub magnification: Option<String>,
+    pub detectors: Vec<String>,
+    pub signal_types: Vec<String>,
+    pub signal_mixing: Option<bool>,
+    pub aperture_size: Option<Quantity>,
+    pub scan_rotation: Option<Quantity>,
+    pub scan_rotation_mode: Option<bool>,
+    pub pixel_width: Option<Quantity>,
+    pub pixel_height: Option<Quantity>,
+    pub fib_sem_intersection_point: Option<Quantity>,
+    pub fib_tilt_angle: Option<Quantity>,
+    pub stage: StagePosition,
+    /// Every parameter of the block, by its name in the block.
+    pub parameters: JsonMap,
+}
+
+impl Acquisition {
+    /// Name of the method of the image, `SEM` or `FIB`.
+    pub fn method(&self) -> &'static str {
+        match self.beam {
+            Beam::Electron => "SEM",
+            Beam::Ion => "FIB",
+        }
+    }
+
+    /// Fill the general and the SEM or FIB sections of a v2 header and add
+    /// the parameters to `toolSpecific.customParameters`.
+    pub fn apply_v2(&self, header: &mut v2::FaMetadataHeader) {
+        let general = header.general_section.get_or_insert_default();
+        fill(&mut general.manufacturer, &self.manufacturer);
+        fill(&mut general.tool_name, &self.tool_name);
+        general.method = Some(self.method().to_string());
+        general.pixel_width = self.pixel_width.as_ref().map(Quantity::v2);
+        general.pixel_height = self.pixel_height.as_ref().map(Quantity::v2);
+        if !self.stage.is_empty() {
+            let coordinates = general.coordinates.get_or_insert_default();
+            coordinates.stage_position = self
+                .stage
+                .position()
+                .map(|(values, unit)| NumberArrayWithUnit::new(values, unit));
+            coordinates.stage_rotation_rx = self.stage.tilt.as_ref().map(Quantity::v2);
+            coordinates.stage_rotation_rz = self.stage.rotation.as_ref().map(Quantity::v2);
+        }
+
+        let method = header.method_specific.get_or_insert_default();
+        if self.beam == Beam::Ion {
+            let fib = method.focused_ion_beam.get_or_insert_default();
+            fib.accelerating_voltage = self.accelerating_voltage.as_ref().map(Quantity::v2);
+            fib.working_distance = self.working_distance.as_ref().map(Quantity::v2);
+            fib.ion_current = self.probe_current.as_ref().map(Quantity::v2);
+            fib.magnification = self.magnification.clone();
+            fib.detectors = non_empty(&self.detectors);
+            fib.signal_types = non_empty(&self.signal_types);
+            fib.signal_mixing = self.signal_mixing;
+            fib.fib_sem_intersection_point =
+                self.fib_sem_intersection_point.as_ref().map(Quantity::v2);
+            fib.fib_tilt_angle = self.fib_tilt_angle.as_ref().map(Quantity::v2);
+        } else {
+            let sem = method.scanning_electron_microscopy.get_or_insert_default();
+            sem.accelerating_voltage = self.accelerating_voltage.as_ref().map(Quantity::v2);
+            sem.working_distance = self.working_distance.as_ref().map(Quantity::v2);
+            sem.probe_current = self.probe_current.as_ref().map(Quantity::v2);
+            sem.emission_current = self.emission_current.as_ref().map(Quantity::v2);
+            sem.magnification = self.magnification.clone();
+            sem.detectors = non_empty(&self.detectors);
+            sem.signal_types = non_empty(&self.signal_types);
+            sem.signal_mixing = self.signal_mixing;
+            sem.aperture_size = self.aperture_size.as_ref().map(Quantity::v2);
+            sem.scan_rotation = self.scan_rotation.as_ref().map(Quantity::v2);
+            sem.scan_rotation_mode = self.scan_rotation_mode;
+        }
+
+        let tool = header.tool_specific.get_or_insert_default();
+        fill(&mut tool.vendor_name, &self.manufacturer);
+        tool.custom_parameters
+            .get_or_insert_default()
+            .extend(self.parameters.clone());
+    }
+
+    /// Fill the general and the SEM or FIB sections of a v1 header and add
+    /// the parameters to the tool specific section `vendor_section`.
+    pub fn apply_v1(&self, header: &mut v1::FaMetadataHeader, vendor_section: &str) {
+        let general = &mut header.general_section;
+        fill(&mut general.manufacturer, &self.manufacturer);
+        fill(&mut general.tool_name, &self.tool_name);
+        general.method = Some(self.method().to_string());
+        general.pixel_width = self.pixel_width.as_ref().map(Quantity::v1);
+        general.pixel_height = self.pixel_height.as_ref().map(Quantity::v1);
+        if !self.stage.is_empty() {
+            let coordinates = general.coordinates_sub_section.get_or_insert_default();
+            coordinates.stage_coordinates_x_y_z = self
+                .stage
+                .position()
+                .map(|(values, unit)| v1::LegacyNumberArrayWithUnit::new(values, unit));
+            coordinates.stage_rotation_rx = self.stage.tilt.as_ref().map(Quantity::v1);
+            coordinates.stage_rotation_rz = self.stage.rotation.as_ref().map(Quantity::v1);
+        }
+
+        let method = &mut header.method_specific;
+        if self.beam == Beam::Ion {
+            let fib = method.focused_ion_beam.get_or_insert_default();
+            fib.accelerating_voltage = self.accelerating_voltage.as_ref().map(Quantity::v1);
+            fib.working_distance = self.working_distance.as_ref().map(Quantity::v1);
+            fib.probe_current = self.probe_current.as_ref().map(Quantity::v1);
+            fib.emission_current = self.emission_current.as_ref().map(Quantity::v1);
+            fib.magnification = self.magnification.clone();
+            fib.detectors = non_empty(&self.detectors);
+            fib.signal_types = non_empty(&self.signal_types);
+            fib.signal_mixing = self.signal_mixing;
+            fib.aperture_size = self.aperture_size.as_ref().map(Quantity::v1);
+            fib.scan_rotation = self.scan_rotation.as_ref().map(Quantity::v1);
+            fib.scan_rotation_mode = self.scan_rotation_mode;
+            fib.fib_sem_intersection_point =
+                self.fib_sem_intersection_point.as_ref().map(Quantity::v1);
+            fib.fib_tilt_angle = self.fib_tilt_angle.as_ref().map(Quantity::v1);
+        } else {
+            let sem = method.scanning_electron_microscopy.get_or_insert_default();
+            sem.accelerating_voltage = self.accelerating_voltage.as_ref().map(Quantity::v1);
+            sem.working_distance = self.working_distance.as_ref().map(Quantity::v1);
+            sem.probe_current = self.probe_current.as_ref().map(Quantity::v1);
+            sem.emission_current = self.emission_current.as_ref().map(Quantity::v1);
+            sem.magnification = self.magnification.clone();
+            sem.detectors = non_empty(&self.detectors);
+            sem.signal_types = non_empty(&self.signal_types);
+            sem.signal_mixing = self.signal_mixing;
+            sem.aperture_size = self.aperture_size.as_ref().map(Quantity::v1);
+            sem.scan_rotation = self.scan_rotation.as_ref().map(Quantity::v1);
+            sem.scan_rotation_mode = self.scan_rotation_mode;
+        }
+
+        // The vendor sections are typed, so go through JSON to reach the one
+        // of the tool by name.
+        let mut tool = header
+            .tool_specific
+            .take()
+            .and_then(|tool| famh_model::to_value(&tool).ok())
+            .unwrap_or_else(|| json!({}));
+        let section = tool
+            .as_object_mut()
+            .and_then(|tool| {
+                tool.entry(vendor_section)
+                    .or_insert_with(|| json!({}))
+                    .as_object_mut()
+            })
+            .expect("tool specific sections are objects");
+        section.extend(self.parameters.clone());
+        header.tool_specific = famh_model::from_value(tool).ok();
+    }
+}
+
+/// A parameter of a vendor block as stored in the tool specific section.
+pub fn parameter(label: &str, value: &Value, unit: Option<&str>) -> Value {
+    let mut parameter = json!({ "label": label, "value": value });
+    if let Some(unit) = unit {
+        parameter["unit"] = json!(unit);
+    }
+    parameter
+}
+
+/// A magnification as FAMH writes it, e.g. `50000x`.
+pub fn format_magnification(factor: f64) -> String {
+    if factor.fract() == 0.0 {
+        format!("{}x", factor as i64)
+    } else {
+        format!("{factor}x")
+    }
+}
+
+/// Units as FAMH writes them: `deg` instead of `°`.
+fn normalize_unit(unit: &str) -> String {
+    match unit {
+        "°" | "deg." => "deg".to_string(),
+        unit => unit.to_string(),
+    }
+}
+
+/// Integral values as integers, so that `5.0 kV` is written as `5`.
+fn number(value: f64) -> Numeric {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        Numeric::Integer(value as i64)
+    } else {
+        Numeric::Float(value)
+    }
+}
+
+fn fill(field: &mut Option<String>, value: &Option<String>) {
+    if field.is_none() {
+        field.clone_from(value);
+    }
+}
+
+fn non_empty(values: &[String]) -> Option<Vec<String>> {
+    (!values.is_empty()).then(|| values.to_vec())
+}
@@ -0,0 +1,515 @@
+//! FAMH headers built from the tags of a TIFF file.
+
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone};
+use famh_model::{IntegerWithUnit, JsonMap, NumberWithUnit, Numeric, v1, v2};
+use serde_json::{Value, json};
+use std::path::Path;
+use tiff::tags::Tag;
+
+use super::acquisition::Acquisition;
+use super::raw_tags::{decode_text, read_tag_bytes};
+use super::{extract_tiff_metadata_tags, fei, open_tiff, tescan, zeiss};
+use crate::schema::SchemaVersion;
+
+/// Name of the raw tag dump in the tool specific section.
+const TIFF_TAGS: &str = "tiffTags";
+
+/// What a TIFF file tells about the image and the tool that recorded it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageMetadata {
+    pub file_name: String,
+    /// Extension of the file, e.g. `.tif`.
+    pub file_format: String,
+    pub file_size: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Bits of the first sample of a pixel.
+    pub bit_depth: Option<u16>,
+    /// Derived from PhotometricInterpretation, e.g. `Grayscale` or `RGB`.
+    pub color_mode: Option<String>,
+    /// The Make tag.
+    pub manufacturer: Option<String>,
+    /// The Model tag.
+    pub tool_name: Option<String>,
+    /// The DateTime tag in RFC 3339 format.
+    pub time_stamp: Option<String>,
+    /// All tags as dumped by `famdo extract`.
+    pub tags: Value,
+    /// Decoded from the metadata block of the tool vendor, if there is one.
+    pub acquisition: Option<Acquisition>,
+}
+
+/// Read the metadata of the first image of a TIFF file.
+pub fn read_image_metadata(
+    image_path: impl AsRef<Path>,
+) -> Result<ImageMetadata, Box<dyn std::error::Error>> {
+    let image_path = image_path.as_ref();
+    let mut decoder = open_tiff(image_path)?;
+    let (width, height) = decoder.dimensions()?;
+    let bit_depth = decoder
+        .find_tag_unsigned_vec::<u16>(Tag::BitsPerSample)?
+        .and_then(|bits| bits.first().copied());
+    let color_mode = decoder
+        .find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?
+        .map(color_mode);
+    let mut ascii = |tag: Tag| -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(value) = decoder.find_tag(tag)? else {
+            return Ok(None);
+        };
+        let text = value.into_string()?;
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        Ok((!text.is_empty()).then(|| text.to_string()))
+    };
+    let manufacturer = ascii(Tag::Make)?;
+    let tool_name = ascii(Tag::Model)?;
+    let time_stamp = ascii(Tag::DateTime)?.and_then(|text| normalize_date_time(&text, &Local));
+    let tags = extract_tiff_metadata_tags(&mut decoder)?;
+    let acquisition = read_acquisition(image_path)?;
+
+    let file_format = image_path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy().to_ascii_lowercase()))
+        .unwrap_or_else(|| ".tiff".to_string());
+    Ok(ImageMetadata {
+        file_name: image_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        file_format,
+        file_size: std::fs::metadata(image_path)?.len(),
+        width,
+        height,
+        bit_depth,
+        color_mode,
+        manufacturer,
+        tool_name,
+        time_stamp,
+        tags,
+        acquisition,
+    })
+}
+
+/// Decode the metadata block of the tool vendor, if the image has one.
+fn read_acquisition(image_path: &Path) -> Result<Option<Acquisition>, Box<dyn std::error::Error>> {
+    if let Some(bytes) = read_tag_bytes(image_path, zeiss::CZ_SEM)? {
+        return Ok(zeiss::decode(&decode_text(&bytes)));
+    }
+    if let Some(bytes) = read_tag_bytes(image_path, fei::FEI_METADATA)? {
+        return Ok(fei::decode(&decode_text(&bytes)));
+    }
+    if let Some(bytes) = read_tag_bytes(image_path, tescan::TESCAN_METADATA)? {
+        return Ok(tescan::decode(&decode_text(&bytes)));
+    }
+    Ok(None)
+}
+
+impl ImageMetadata {
+    pub fn to_v1_header(&self) -> v1::FaMetadataHeader {
+        let mut general = v1::GeneralSection::new()
+            .file_name(&self.file_name)
+            .file_format(&self.file_format)
+            .file_size(v1::LegacyIntegerWithUnit::new(
+                self.file_size as i64,
+                "bytes",
+            ))
+            .image_width(v1::LegacyIntegerWithUnit::new(i64::from(self.width), "px"))
+            .image_height(v1::LegacyIntegerWithUnit::new(i64::from(self.height), "px"));
+        general.bit_depth = self.bit_depth.map(i64::from);
+        general.color_mode = self.color_mode.clone();
+        general.manufacturer = self.manufacturer.clone();
+        general.tool_name = self.tool_name.clone();
+        general.time_stamp = self.time_stamp.clone();
+
+        // v1 only has sections for some tools, others get a section of their
+        // own, which validation reports as unknown.
+        let section = self
+            .v1_vendor_section()
+            .unwrap_or_else(|| "Custom Parameters".to_string());
+        let tool = serde_json::from_value(json!({ &section: { TIFF_TAGS: self.tags } }))
+            .unwrap_or_default();
+        let mut header = v1::FaMetadataHeader::new()
+            .general_section(general)
+            .tool_specific(tool);
+        if let Some(acquisition) = &self.acquisition {
+            acquisition.apply_v1(&mut header, &section);
+        }
+        header
+    }
+
+    pub fn to_v2_header(&self) -> v2::FaMetadataHeader {
+        let mut general = v2::GeneralSection::new()
+            .file_name(&self.file_name)
+            .file_format(&self.file_format)
+            .file_size(NumberWithUnit::new(
+                Numeric::Integer(self.file_size as i64),
+                "bytes",
+            ))
+            .image_width(IntegerWithUnit::new(i64::from(self.width), "px"))
+            .image_height(IntegerWithUnit::new(i64::from(self.height), "px"));
+        general.bit_depth = self.bit_depth.map(i64::from);
+        general.color_mode = self.color_mode.clone();
+        general.manufacturer = self.manufacturer.clone();
+        general.tool_name = self.tool_name.clone();
+        general.time_stamp = self.time_stamp.clone();
+
+        let mut parameters = JsonMap::new();
+        parameters.insert(TIFF_TAGS.to_string(), self.tags.clone());
+        let mut tool = v2::ToolSpecific::new().custom_parameters(parameters);
+        tool.vendor_name = self.manufacturer.clone();
+        let mut header = v2::FaMetadataHeader::new()
+            .general_section(general)
+            .tool_specific(tool);
+        if let Some(acquisition) = &self.acquisition {
+            acquisition.apply_v2(&mut header);
+        }
+        header
+    }
+
+    /// Name of the v1 tool specific section of the tool, e.g. `ZEISS
+    /// GeminiSEM 500` for the Make `Carl Zeiss` and the Model `GeminiSEM500`,
+    /// or `FEI Magellan 400` for the tool `Magellan 400 XHR`.
+    pub fn v1_vendor_section(&self) -> Option<String> {
+        let acquisition = self.acquisition.as_ref();
+        if let Some(section) = acquisition.and_then(|a| a.vendor_section) {
+            return Some(section.to_string());
+        }
+        let manufacturer = self
+            .manufacturer
+            .as_ref()
+            .or(acquisition.and_then(|a| a.manufacturer.as_ref()))?;
+        let tool_name = self
+            .tool_name
+            .as_ref()
+            .or(acquisition.and_then(|a| a.tool_name.as_ref()))?;
+        let (manufacturer, tool_name) = (simplify(manufacturer), simplify(tool_name));
+        if tool_name.is_empty() {
+            return None;
+        }
+        v1::ToolSpecific::VENDOR_SECTIONS
+            .iter()
+            .find(|section| {
+                let (vendor, tool) = section.split_once(' ').unwrap_or((section, ""));
+                let tool = simplify(tool);
+                manufacturer.contains(&simplify(vendor))
+                    && (tool.ends_with(&tool_name) || tool_name.starts_with(&tool))
+            })
+            .map(|section| section.to_string())
+    }
+
+    /// The header of `version` as JSON.
+    pub fn to_header(&self, version: SchemaVersion) -> Result<Value, serde_json::Error> {
+        match version {
+            SchemaVersion::V1 => famh_model::to_value(&self.to_v1_header()),
+            SchemaVersion::V2 => famh_model::to_value(&self.to_v2_header()),
+        }
+    }
+}
+
+/// Build a FAMH header of `version` from a TIFF file and save it to `out_path`.
+pub fn extract_and_save_famh_header(
+    image_path: &str,
+    out_path: &str,
+    version: SchemaVersion,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let header = read_image_metadata(image_path)?.to_header(version)?;
+    let outfile = std::fs::File::create(out_path)
+        .map_err(|e| format!("Failed to create {}: {}", out_path, e))?;
+    serde_json::to_writer_pretty(outfile, &header)?;
+    Ok(())
+}
+
+/// Lower case letters and digits of a name, to compare names of tools.
+fn simplify(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Color mode of a PhotometricInterpretation value.
+fn color_mode(photometric_interpretation: u16) -> String {
+    match photometric_interpretation {
+        0 | 1 => "Grayscale",
+        2 => "RGB",
+        3 => "Palette",
+        4 => "Transparency Mask",
+        5 => "CMYK",
+        6 => "YCbCr",
+        8..=10 => "CIELab",
+        other => return format!("Unknown({other})"),
+    }
+    .to_string()
+}
+
+/// Turn a TIFF DateTime (`2025:11:13 14:25:30`) into RFC 3339. TIFF date
+/// times carry no time zone, they are taken as local times of `time_zone`.
+fn normalize_date_time<Tz: TimeZone>(text: &str, time_zone: &Tz) -> Option<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+        return Some(date_time.to_rfc3339_opts(SecondsFormat::AutoSi, false));
+    }
+    let naive = [
+        "%Y:%m:%d %H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())?;
+    let date_time = time_zone.from_local_datetime(&naive).earliest()?;
+    Some(date_time.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use std::fs::File;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    fn tiff_with_tags(
+        dir: &Path,
+        tags: &[(Tag, &str)],
+        private_tags: &[(u16, &[u8])],
+    ) -> std::path::PathBuf {
+        let path = dir.join("scan_001.TIF");
+        let mut encoder = TiffEncoder::new(File::create(&path).unwrap()).unwrap();
+        let mut image = encoder.new_image::<colortype::Gray16>(4, 3).unwrap();
+        for (tag, value) in tags {
+            image.encoder().write_tag(*tag, *value).unwrap();
+        }
+        for (tag, bytes) in private_tags {
+            image
+                .encoder()
+                .write_tag(Tag::Unknown(*tag), *bytes)
+                .unwrap();
+        }
+        image.write_data(&[0u16; 12]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_headers_map_the_tiff_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tiff_with_tags(
+            dir.path(),
+            &[
+                (Tag::Make, "ZEISS"),
+                (Tag::Model, "GeminiSEM 500"),
+                (Tag::DateTime, "2025:11:13 14:25:30"),
+            ],
+            &[],
+        );
+
+        let metadata = read_image_metadata(&path).unwrap();
+        assert_eq!(metadata.file_name, "scan_001.TIF");
+        assert_eq!(metadata.file_format, ".tif");
+        assert_eq!((metadata.width, metadata.height), (4, 3));
+        assert_eq!(metadata.bit_depth, Some(16));
+        assert_eq!(metadata.color_mode.as_deref(), Some("Grayscale"));
+        assert!(
+            metadata
+                .time_stamp
+                .unwrap()
+                .starts_with("2025-11-13T14:25:30")
+        );
+
+        let metadata = ImageMetadata {
+            time_stamp: Some("2025-11-13T14:25:30+01:00".to_string()),
+            ..read_image_metadata(&path).unwrap()
+        };
+        let header = metadata.to_header(SchemaVersion::V2).unwrap();
+        let general = &header["generalSection"];
+        assert_eq!(general["fileName"], "scan_001.TIF");
+        assert_eq!(
+            general["fileSize"],
+            json!({ "value": metadata.file_size, "unit": "bytes" })
+        );
+        assert_eq!(general["imageWidth"], json!({ "value": 4, "unit": "px" }));
+        assert_eq!(general["imageHeight"], json!({ "value": 3, "unit": "px" }));
+        assert_eq!(general["bitDepth"], 16);
+        assert_eq!(general["colorMode"], "Grayscale");
+        assert_eq!(general["manufacturer"], "ZEISS");
+        assert_eq!(general["toolName"], "GeminiSEM 500");
+        assert_eq!(general["timeStamp"], "2025-11-13T14:25:30+01:00");
+        let tags = &header["toolSpecific"]["customParameters"]["tiffTags"];
+        assert!(
+            tags.as_array()
+                .unwrap()
+                .iter()
+                .any(|tag| tag["tag"] == "Model")
+        );
+
+        let header = metadata.to_header(SchemaVersion::V1).unwrap();
+        let general = &header["General Section"];
+        assert_eq!(general["Image Width"], json!({ "Value": 4, "Unit": "px" }));
+        assert_eq!(general["Tool Name"], "GeminiSEM 500");
+        assert_eq!(
+            header["Tool Specific"]["ZEISS GeminiSEM 500"]["tiffTags"],
+            *tags
+        );
+
+        let metadata = ImageMetadata {
+            tool_name: Some("Sigma 300".to_string()),
+            ..metadata
+        };
+        assert_eq!(metadata.v1_vendor_section(), None);
+        let header = metadata.to_header(SchemaVersion::V1).unwrap();
+        assert!(header["Tool Specific"]["Custom Parameters"]["tiffTags"].is_array());
+    }
+
+    #[test]
+    fn test_headers_map_the_zeiss_cz_sem_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let cz_sem = b"0\r\nAP_ACTUALKV\r\nEHT = 3.00 kV\r\n\
+            AP_WD\r\nWD = 5.1 mm\r\n\
+            AP_APERTURESIZE\r\nAperture Size = 30.00 \xb5m\r\n\
+            DP_DETECTOR_CHANNEL\r\nSignal A = InLens\r\n\
+            AP_STAGE_AT_X\r\nStage at X = 12.5 mm\r\n\
+            AP_STAGE_AT_Y\r\nStage at Y = 67.8 mm\r\n\
+            AP_STAGE_AT_T\r\nStage at T = 10.0 \xb0\r\n\0";
+        let path = tiff_with_tags(
+            dir.path(),
+            &[(Tag::Make, "Carl Zeiss"), (Tag::Model, "GeminiSEM500")],
+            &[(zeiss::CZ_SEM, cz_sem)],
+        );
+        let metadata = read_image_metadata(&path).unwrap();
+        assert_eq!(
+            metadata.v1_vendor_section().as_deref(),
+            Some("ZEISS GeminiSEM 500")
+        );
+
+        let header = metadata.to_header(SchemaVersion::V2).unwrap();
+        assert_eq!(header["generalSection"]["method"], "SEM");
+        assert_eq!(
+            header["generalSection"]["coordinates"]["stagePosition"],
+            json!({ "value": [12.5, 67.8, null], "unit": "mm" })
+        );
+        assert_eq!(
+            header["generalSection"]["coordinates"]["stageRotationRx"],
+            json!({ "value": 10, "unit": "deg" })
+        );
+        let sem = &header["methodSpecific"]["scanningElectronMicroscopy"];
+        assert_eq!(
+            sem["acceleratingVoltage"],
+            json!({ "value": 3, "unit": "kV" })
+        );
+        assert_eq!(
+            sem["workingDistance"],
+            json!({ "value": 5.1, "unit": "mm" })
+        );
+        assert_eq!(sem["apertureSize"], json!({ "value": 30, "unit": "µm" }));
+        assert_eq!(sem["detectors"], json!(["InLens"]));
+        let parameters = &header["toolSpecific"]["customParameters"];
+        assert_eq!(parameters["AP_WD"]["label"], "WD");
+        assert!(parameters["tiffTags"].is_array());
+
+        let header = metadata.to_header(SchemaVersion::V1).unwrap();
+        let sem = &header["Method Specific"]["Scanning Electron Microscopy"];
+        assert_eq!(
+            sem["Accelerating Voltage"],
+            json!({ "Value": 3, "Unit": "kV" })
+        );
+        let section = &header["Tool Specific"]["ZEISS GeminiSEM 500"];
+        assert_eq!(section["AP_STAGE_AT_T"]["unit"], "deg");
+        assert!(section["tiffTags"].is_array());
+    }
+
+    #[test]
+    fn test_headers_map_the_fei_block_of_ion_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let block = b"[System]\r\nSystemType=Magellan 400 XHR\r\nEucWD=0.004\r\n\
+            [Beam]\r\nBeam=IBeam\r\n\
+            [IBeam]\r\nHV=30000\r\nBeamCurrent=9.7e-011\r\nSpot=3\r\n\
+            [Stage]\r\nStageX=0.0123\r\nStageY=0.0045\r\nStageZ=0.0041\r\n\
+            StageT=0.907571211037051\r\n\0";
+        let path = tiff_with_tags(dir.path(), &[], &[(fei::FEI_METADATA, block)]);
+        let metadata = read_image_metadata(&path).unwrap();
+        assert_eq!(
+            metadata.v1_vendor_section().as_deref(),
+            Some("FEI Magellan 400")
+        );
+
+        let header = metadata.to_header(SchemaVersion::V2).unwrap();
+        assert_eq!(header["generalSection"]["method"], "FIB");
+        assert_eq!(header["generalSection"]["manufacturer"], "FEI");
+        assert_eq!(
+            header["generalSection"]["coordinates"]["stagePosition"],
+            json!({ "value": [12.3, 4.5, 4.1], "unit": "mm" })
+        );
+        let fib = &header["methodSpecific"]["focusedIonBeam"];
+        assert_eq!(fib["ionCurrent"], json!({ "value": 97, "unit": "pA" }));
+        assert_eq!(
+            fib["fibSemIntersectionPoint"],
+            json!({ "value": 4, "unit": "mm" })
+        );
+        assert_eq!(fib["fibTiltAngle"], json!({ "value": 52, "unit": "deg" }));
+        assert_eq!(
+            header["toolSpecific"]["customParameters"]["IBeam"]["Spot"],
+            3
+        );
+
+        let header = metadata.to_header(SchemaVersion::V1).unwrap();
+        let fib = &header["Method Specific"]["Focused Ion Beam"];
+        assert_eq!(fib["Probe Current"], json!({ "Value": 97, "Unit": "pA" }));
+        assert_eq!(
+            header["Tool Specific"]["FEI Magellan 400"]["IBeam"]["Spot"],
+            3
+        );
+    }
+
+    #[test]
+    fn test_headers_map_the_tescan_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let block = b"\x01\0TESCAN\0[MAIN]\r\nDevice=MIRA3 LMH\r\nMagnification=25000\r\n\
+            [SEM]\r\nHV=10000\r\nSpotSize=3.1e-009\r\nStageX=0.0123\r\nStageTilt=55\r\n\0";
+        let path = tiff_with_tags(dir.path(), &[], &[(tescan::TESCAN_METADATA, block)]);
+        let metadata = read_image_metadata(&path).unwrap();
+        assert_eq!(
+            metadata.v1_vendor_section().as_deref(),
+            Some("Tescan XEIA3")
+        );
+
+        let header = metadata.to_header(SchemaVersion::V1).unwrap();
+        assert_eq!(header["General Section"]["Tool Name"], "MIRA3 LMH");
+        assert_eq!(
+            header["Method Specific"]["Scanning Electron Microscopy"]["Magnification"],
+            "25000x"
+        );
+        let section = &header["Tool Specific"]["Tescan XEIA3"];
+        assert_eq!(section["SEM"]["SpotSize"], "3.1e-009");
+        assert!(section["tiffTags"].is_array());
+
+        let header = metadata.to_header(SchemaVersion::V2).unwrap();
+        assert_eq!(
+            header["generalSection"]["coordinates"]["stagePosition"],
+            json!({ "value": [12.3, null, null], "unit": "mm" })
+        );
+        assert_eq!(
+            header["toolSpecific"]["customParameters"]["SEM"]["SpotSize"],
+            "3.1e-009"
+        );
+    }
+
+    #[test]
+    fn test_date_times_are_normalized_to_rfc3339() {
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(
+            normalize_date_time("2025:11:13 14:25:30", &cet).as_deref(),
+            Some("2025-11-13T14:25:30+01:00")
+        );
+        assert_eq!(
+            normalize_date_time("2025-11-13T14:25:30Z", &cet).as_deref(),
+            Some("2025-11-13T14:25:30+00:00")
+        );
+        assert_eq!(normalize_date_time("13.11.2025", &cet), None);
+    }
+
+    #[test]
+    fn test_color_mode_from_photometric_interpretation() {
+        assert_eq!(color_mode(1), "Grayscale");
+        assert_eq!(color_mode(2), "RGB");
+        assert_eq!(color_mode(32844), "Unknown(32844)");
+    }
+}
@@ -0,0 +1,217 @@
+//! The metadata block of FEI and Thermo Fisher images (tag 34682).
+//!
+//! The block is INI text in SI units, with angles in radians:
+//!
+//! ```text
+//! [Beam]
+//! HV=5000
+//! Beam=EBeam
+//! [EBeam]
+//! WD=0.00412
+//! BeamCurrent=1e-010
+//! ```
+//!
+//! `[Beam] Beam` names the section of the beam that recorded the image,
+//! `EBeam` or `IBeam`.
+
+use serde_json::{Value, json};
+
+use super::acquisition::{Acquisition, Beam, StagePosition, format_magnification};
+use super::ini::Ini;
+
+/// The private TIFF tag of the block.
+pub const FEI_METADATA: u16 = 34682;
+
+/// The acquisition parameters of an FEI block, `None` if it has no beam or
+/// scan sections.
+pub fn decode(text: &str) -> Option<Acquisition> {
+    let mut ini = Ini::parse(text);
+    if !["Beam", "EBeam", "IBeam", "Scan"]
+        .iter()
+        .any(|section| ini.has_section(section))
+    {
+        return None;
+    }
+    let section = match ini.get("Beam", "Beam") {
+        Some(beam @ ("EBeam" | "IBeam")) => beam.to_string(),
+        _ if !ini.has_section("EBeam") && ini.has_section("IBeam") => "IBeam".to_string(),
+        _ => "EBeam".to_string(),
+    };
+    let beam = if section == "IBeam" {
+        Beam::Ion
+    } else {
+        Beam::Electron
+    };
+    let s = section.as_str();
+
+    // The magnification on a display of DisplayWidth for a horizontal field
+    // width of HFW.
+    let magnification = ini.number("System", "DisplayWidth").and_then(|display| {
+        let field = ini
+            .number(s, "HFW")
+            .or_else(|| ini.number("Scan", "HorFieldsize"))?;
+        (field > 0.0).then(|| format_magnification((display / field).round()))
+    });
+
+    let mut acquisition = Acquisition {
+        manufacturer: Some("FEI".to_string()),
+        tool_name: ini.take("System", "SystemType"),
+        beam,
+        accelerating_voltage: ini.take_quantity(&[(s, "HV"), ("Beam", "HV")], "kV"),
+        working_distance: ini.take_quantity(&[(s, "WD"), ("Stage", "WorkingDistance")], "mm"),
+        probe_current: ini.take_quantity(&[(s, "BeamCurrent")], "pA"),
+        magnification,
+        detectors: ini.take("Detectors", "Name").into_iter().collect(),
+        signal_types: ini.take("Detectors", "Mode").into_iter().collect(),
+        pixel_width: ini.take_quantity(&[("Scan", "PixelWidth")], "nm"),
+        pixel_height: ini.take_quantity(&[("Scan", "PixelHeight")], "nm"),
+        stage: StagePosition {
+            x: ini.take_quantity(&[("Stage", "StageX")], "mm"),
+            y: ini.take_quantity(&[("Stage", "StageY")], "mm"),
+            z: ini.take_quantity(&[("Stage", "StageZ")], "mm"),
+            rotation: ini.take_quantity(&[("Stage", "StageR")], "deg"),
+            tilt: ini.take_quantity(&[("Stage", "StageT")], "deg"),
+        },
+        ..Acquisition::default()
+    };
+    match beam {
+        Beam::Electron => {
+            acquisition.emission_current = ini.take_quantity(&[(s, "EmissionCurrent")], "µA");
+            acquisition.aperture_size = ini.take_quantity(&[(s, "ApertureDiameter")], "µm");
+            acquisition.scan_rotation =
+                ini.take_quantity(&[(s, "ScanRotation"), ("Beam", "ScanRotation")], "deg");
+        }
+        Beam::Ion => {
+            acquisition.fib_sem_intersection_point =
+                ini.take_quantity(&[("System", "EucWD"), ("EBeam", "EucWD")], "mm");
+            acquisition.fib_tilt_angle = acquisition.stage.tilt.clone();
+        }
+    }
+    acquisition.parameters = ini.into_parameters(typed_value);
+    Some(acquisition)
+}
+
+/// Numbers as JSON numbers, everything else as written.
+fn typed_value(value: String) -> Value {
+    if let Ok(integer) = value.parse::<i64>() {
+        return json!(integer);
+    }
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => json!(number),
+        _ => Value::String(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::extract::acquisition::Quantity;
+
+    const BLOCK: &str = "[User]\r\n\
+        Date=11/13/2025\r\n\
+        User=fa-lab\r\n\
+        [System]\r\n\
+        Type=DualBeam\r\n\
+        SystemType=Helios NanoLab 660\r\n\
+        DisplayWidth=0.5\r\n\
+        EucWD=0.004\r\n\
+        [Beam]\r\n\
+        HV=5000\r\n\
+        Beam=EBeam\r\n\
+        Scan=EScan\r\n\
+        [EBeam]\r\n\
+        HV=5000\r\n\
+        HFW=1e-005\r\n\
+        WD=0.00412\r\n\
+        BeamCurrent=1e-010\r\n\
+        EmissionCurrent=\r\n\
+        ApertureDiameter=3.2e-005\r\n\
+        ScanRotation=0\r\n\
+        LensMode=Immersion\r\n\
+        [IBeam]\r\n\
+        HV=30000\r\n\
+        HFW=2e-005\r\n\
+        WD=0.0165\r\n\
+        BeamCurrent=9.7e-011\r\n\
+        [Stage]\r\n\
+        StageX=0.0123\r\n\
+        StageY=-0.0045\r\n\
+        StageZ=0.0041\r\n\
+        StageR=3.14159265358979\r\n\
+        StageT=0.907571211037051\r\n\
+        [Scan]\r\n\
+        PixelWidth=2.5e-009\r\n\
+        PixelHeight=2.5e-009\r\n\
+        Dwelltime=1e-006\r\n\
+        [Detectors]\r\n\
+        Name=TLD\r\n\
+        Mode=SE\r\n";
+
+    #[test]
+    fn test_electron_images_fill_the_sem_fields() {
+        let acquisition = decode(BLOCK).unwrap();
+        assert_eq!(acquisition.beam, Beam::Electron);
+        assert_eq!(acquisition.tool_name.as_deref(), Some("Helios NanoLab 660"));
+        assert_eq!(
+            acquisition.accelerating_voltage,
+            Some(Quantity::new(5.0, "kV"))
+        );
+        assert_eq!(
+            acquisition.working_distance,
+            Some(Quantity::new(4.12, "mm"))
+        );
+        assert_eq!(acquisition.probe_current, Some(Quantity::new(100.0, "pA")));
+        assert_eq!(acquisition.emission_current, None);
+        assert_eq!(acquisition.aperture_size, Some(Quantity::new(32.0, "µm")));
+        assert_eq!(acquisition.magnification.as_deref(), Some("50000x"));
+        assert_eq!(acquisition.detectors, ["TLD"]);
+        assert_eq!(acquisition.signal_types, ["SE"]);
+        assert_eq!(acquisition.pixel_width, Some(Quantity::new(2.5, "nm")));
+        assert_eq!(acquisition.stage.x, Some(Quantity::new(12.3, "mm")));
+        assert_eq!(acquisition.stage.y, Some(Quantity::new(-4.5, "mm")));
+        assert_eq!(
+            acquisition.stage.rotation,
+            Some(Quantity::new(180.0, "deg"))
+        );
+        assert_eq!(acquisition.stage.tilt, Some(Quantity::new(52.0, "deg")));
+        assert_eq!(acquisition.fib_sem_intersection_point, None);
+
+        let parameters = json!(acquisition.parameters);
+        assert_eq!(parameters["EBeam"]["LensMode"], "Immersion");
+        assert_eq!(parameters["EBeam"]["EmissionCurrent"], "");
+        assert_eq!(parameters["EBeam"].get("WD"), None);
+        assert_eq!(parameters["Beam"]["HV"], 5000);
+        assert_eq!(parameters["IBeam"]["HV"], 30000);
+        assert_eq!(parameters["Scan"]["Dwelltime"], 1e-6);
+        assert_eq!(parameters["User"]["Date"], "11/13/2025");
+        assert_eq!(parameters.get("Stage"), None);
+    }
+
+    #[test]
+    fn test_ion_images_fill_the_fib_fields() {
+        let acquisition = decode(&BLOCK.replace("Beam=EBeam", "Beam=IBeam")).unwrap();
+        assert_eq!(acquisition.beam, Beam::Ion);
+        assert_eq!(
+            acquisition.accelerating_voltage,
+            Some(Quantity::new(30.0, "kV"))
+        );
+        assert_eq!(
+            acquisition.working_distance,
+            Some(Quantity::new(16.5, "mm"))
+        );
+        assert_eq!(acquisition.probe_current, Some(Quantity::new(97.0, "pA")));
+        assert_eq!(acquisition.magnification.as_deref(), Some("25000x"));
+        assert_eq!(
+            acquisition.fib_sem_intersection_point,
+            Some(Quantity::new(4.0, "mm"))
+        );
+        assert_eq!(acquisition.fib_tilt_angle, Some(Quantity::new(52.0, "deg")));
+        assert_eq!(acquisition.aperture_size, None);
+        assert_eq!(
+            acquisition.parameters["EBeam"]["ApertureDiameter"],
+            json!(3.2e-5)
+        );
+
+        assert_eq!(decode("[User]\r\nUser=fa-lab\r\n"), None);
+    }
+}
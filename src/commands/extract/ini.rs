@@ -0,0 +1,85 @@
+//! INI text as written by FEI and Tescan tools:
+//!
+//! ```text
+//! [SEM]
+//! HV=5000
+//! WD=0.0051
+//! ```
+
+use famh_model::JsonMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::acquisition::Quantity;
+
+/// Values by key by section.
+pub(super) struct Ini(BTreeMap<String, BTreeMap<String, String>>);
+
+impl Ini {
+    pub(super) fn parse(text: &str) -> Self {
+        let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut section = String::new();
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                sections.entry(section.clone()).or_default();
+            } else if let Some((key, value)) = line.split_once('=') {
+                sections
+                    .entry(section.clone())
+                    .or_default()
+                    .insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        Self(sections)
+    }
+
+    pub(super) fn has_section(&self, section: &str) -> bool {
+        self.0.contains_key(section)
+    }
+
+    pub(super) fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.0.get(section)?.get(key).map(String::as_str)
+    }
+
+    pub(super) fn number(&self, section: &str, key: &str) -> Option<f64> {
+        self.get(section, key)?.parse().ok()
+    }
+
+    /// Remove a value that is mapped to a header field, so that it is not
+    /// kept as a vendor parameter as well.
+    pub(super) fn take(&mut self, section: &str, key: &str) -> Option<String> {
+        let value = self.0.get_mut(section)?.remove(key)?;
+        Some(value).filter(|value| !value.is_empty())
+    }
+
+    /// Take the first of `keys` that is a number.
+    pub(super) fn take_number(&mut self, keys: &[(&str, &str)]) -> Option<f64> {
+        keys.iter().find_map(|(section, key)| {
+            let value = self.number(section, key)?;
+            self.take(section, key);
+            Some(value)
+        })
+    }
+
+    /// Take the first of `keys` that is a number in SI units, as a quantity
+    /// in `unit`.
+    pub(super) fn take_quantity(&mut self, keys: &[(&str, &str)], unit: &str) -> Option<Quantity> {
+        self.take_number(keys)
+            .map(|value| Quantity::from_si(value, unit))
+    }
+
+    /// The remaining values by key by section, as JSON by `value`.
+    pub(super) fn into_parameters(self, value: impl Fn(String) -> Value) -> JsonMap {
+        self.0
+            .into_iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(section, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(key, text)| (key, value(text)))
+                    .collect();
+                (section, Value::Object(values))
+            })
+            .collect()
+    }
+}
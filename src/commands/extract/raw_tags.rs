@@ -0,0 +1,197 @@
+//! Raw bytes of the tags of the first image of a TIFF file.
+//!
+//! Vendors store their metadata as text in private tags, often in Latin-1
+//! (`µm`, `°`). The `tiff` crate rejects ASCII tags that are not UTF-8, so
+//! these tags are read here directly from the first image file directory.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes of `tag` in the first image of the TIFF file at `path`, or `None`
+/// if the image has no such tag.
+pub(super) fn read_tag_bytes(
+    path: &Path,
+    tag: u16,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open image {}: {}", path.display(), e))?;
+    let invalid = || format!("Cannot read {} as TIFF", path.display());
+
+    let mut header = [0u8; 16];
+    file.read_exact(&mut header[..8]).map_err(|_| invalid())?;
+    let reader = match &header[..2] {
+        b"II" => Reader {
+            little_endian: true,
+        },
+        b"MM" => Reader {
+            little_endian: false,
+        },
+        _ => return Err(invalid().into()),
+    };
+    let big = match reader.u16(&header[2..4]) {
+        42 => false,
+        43 => {
+            file.read_exact(&mut header[8..]).map_err(|_| invalid())?;
+            true
+        }
+        _ => return Err(invalid().into()),
+    };
+    let (ifd, entry_size, count_size) = if big {
+        (reader.u64(&header[8..16]), 20, 8)
+    } else {
+        (u64::from(reader.u32(&header[4..8])), 12, 2)
+    };
+
+    file.seek(SeekFrom::Start(ifd))?;
+    let mut count = [0u8; 8];
+    file.read_exact(&mut count[..count_size])?;
+    let entries = if big {
+        reader.u64(&count)
+    } else {
+        u64::from(reader.u16(&count[..2]))
+    };
+    let mut entry = vec![0u8; entry_size];
+    for _ in 0..entries {
+        file.read_exact(&mut entry)?;
+        if reader.u16(&entry[..2]) != tag {
+            continue;
+        }
+        let size = type_size(reader.u16(&entry[2..4]));
+        let (count, value) = if big {
+            (reader.u64(&entry[4..12]), &entry[12..20])
+        } else {
+            (u64::from(reader.u32(&entry[4..8])), &entry[8..12])
+        };
+        let length = count.checked_mul(size).ok_or_else(invalid)?;
+        if length <= value.len() as u64 {
+            return Ok(Some(value[..length as usize].to_vec()));
+        }
+        let offset = if big {
+            reader.u64(value)
+        } else {
+            u64::from(reader.u32(value))
+        };
+        if offset.checked_add(length).ok_or_else(invalid)? > file.metadata()?.len() {
+            return Err(
+                format!("{}: tag {} points past the end of the file", invalid(), tag).into(),
+            );
+        }
+        let mut bytes = vec![0u8; length as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut bytes)?;
+        return Ok(Some(bytes));
+    }
+    Ok(None)
+}
+
+/// Text of a tag: UTF-8 if it is valid UTF-8, Latin-1 otherwise, without
+/// trailing NULs.
+pub(super) fn decode_text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |last| last + 1);
+    let bytes = &bytes[..end];
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+struct Reader {
+    little_endian: bool,
+}
+
+impl Reader {
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    fn u64(&self, bytes: &[u8]) -> u64 {
+        let mut array = [0u8; 8];
+        array.copy_from_slice(&bytes[..8]);
+        if self.little_endian {
+            u64::from_le_bytes(array)
+        } else {
+            u64::from_be_bytes(array)
+        }
+    }
+}
+
+/// Bytes of one value of a TIFF field type.
+fn type_size(field_type: u16) -> u64 {
+    match field_type {
+        3 | 8 => 2,
+        4 | 9 | 11 | 13 => 4,
+        5 | 10 | 12 | 16..=18 => 8,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{TiffEncoder, colortype};
+    use tiff::tags::Tag;
+
+    #[test]
+    fn test_reads_latin1_text_of_private_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.tif");
+        let mut encoder = TiffEncoder::new(File::create(&path).unwrap()).unwrap();
+        let mut image = encoder.new_image::<colortype::Gray8>(2, 2).unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Unknown(34118), b"WD = 5.1 \xb5m\0".as_slice())
+            .unwrap();
+        image
+            .encoder()
+            .write_tag(Tag::Unknown(34682), "HV=5000")
+            .unwrap();
+        image.write_data(&[0u8; 4]).unwrap();
+
+        let bytes = read_tag_bytes(&path, 34118).unwrap().unwrap();
+        assert_eq!(decode_text(&bytes), "WD = 5.1 µm");
+        let bytes = read_tag_bytes(&path, 34682).unwrap().unwrap();
+        assert_eq!(decode_text(&bytes), "HV=5000");
+        assert_eq!(read_tag_bytes(&path, 50431).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rejects_offsets_that_overflow() {
+        // A BigTIFF whose only tag points 16 bytes before the end of the
+        // addressable range.
+        let mut bytes = b"II\x2b\x00\x08\x00\x00\x00".to_vec();
+        bytes.extend(16u64.to_le_bytes());
+        bytes.extend(1u64.to_le_bytes());
+        bytes.extend(34118u16.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend(32u64.to_le_bytes());
+        bytes.extend((u64::MAX - 16).to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.tif");
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = read_tag_bytes(&path, 34118).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Cannot read {} as TIFF", path.display())
+        );
+    }
+}
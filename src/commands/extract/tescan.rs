@@ -0,0 +1,161 @@
+//! The metadata block of Tescan images (tag 50431).
+//!
+//! The tag holds a binary header followed by INI text with a `[MAIN]`
+//! section and a section for each column, `[SEM]` and `[FIB]`:
+//!
+//! ```text
+//! [MAIN]
+//! Device=MIRA3 LMH
+//! PixelSizeX=2.5e-009
+//! [SEM]
+//! HV=5000
+//! StageTilt=0
+//! ```
+//!
+//! Lengths, voltages and currents are in SI units, angles in degrees.
+//!
+//! The FAMH model has no field for the spot size, so `SpotSize` is kept with
+//! the other unmapped values in the vendor parameters.
+
+use serde_json::Value;
+
+use super::acquisition::{Acquisition, Beam, Quantity, StagePosition, format_magnification};
+use super::ini::Ini;
+
+/// The private TIFF tag of the block.
+pub const TESCAN_METADATA: u16 = 50431;
+
+/// The v1 tool specific section of Tescan tools.
+const VENDOR_SECTION: &str = "Tescan XEIA3";
+
+/// The acquisition parameters of a Tescan block, `None` if it has no
+/// `[MAIN]` section.
+pub fn decode(text: &str) -> Option<Acquisition> {
+    let mut ini = Ini::parse(&text[text.find("[MAIN]")?..]);
+    // Images of dual beam tools have an `[SEM]` section if the electron beam
+    // recorded them.
+    let (section, beam) = if !ini.has_section("SEM") && ini.has_section("FIB") {
+        ("FIB", Beam::Ion)
+    } else {
+        ("SEM", Beam::Electron)
+    };
+    let keys = |key: &'static str| [(section, key), ("MAIN", key)];
+    let degrees = |value: f64| Quantity::new(value, "deg");
+
+    let mut acquisition = Acquisition {
+        manufacturer: Some("TESCAN".to_string()),
+        tool_name: ini.take("MAIN", "Device"),
+        vendor_section: Some(VENDOR_SECTION),
+        beam,
+        accelerating_voltage: ini.take_quantity(&keys("HV"), "kV"),
+        working_distance: ini.take_quantity(&keys("WD"), "mm"),
+        magnification: ini
+            .take_number(&keys("Magnification"))
+            .map(format_magnification),
+        detectors: ini
+            .take(section, "Detector")
+            .or_else(|| ini.take("MAIN", "Detector"))
+            .into_iter()
+            .collect(),
+        scan_rotation: ini.take_number(&keys("ScanRotation")).map(degrees),
+        pixel_width: ini.take_quantity(&keys("PixelSizeX"), "nm"),
+        pixel_height: ini.take_quantity(&keys("PixelSizeY"), "nm"),
+        stage: StagePosition {
+            x: ini.take_quantity(&keys("StageX"), "mm"),
+            y: ini.take_quantity(&keys("StageY"), "mm"),
+            z: ini.take_quantity(&keys("StageZ"), "mm"),
+            rotation: ini.take_number(&keys("StageRotation")).map(degrees),
+            tilt: ini.take_number(&keys("StageTilt")).map(degrees),
+        },
+        ..Acquisition::default()
+    };
+    match beam {
+        Beam::Electron => {
+            acquisition.emission_current = ini.take_quantity(&keys("EmissionCurrent"), "µA");
+        }
+        Beam::Ion => acquisition.fib_tilt_angle = acquisition.stage.tilt.clone(),
+    }
+    acquisition.parameters = ini.into_parameters(Value::String);
+    Some(acquisition)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BLOCK: &str = "\u{1}\u{0}TESCAN\u{0}[MAIN]\r\n\
+        Date=2025-11-13\r\n\
+        Time=14:25:30\r\n\
+        Device=XEIA3\r\n\
+        SerialNumber=118-0042\r\n\
+        Magnification=25000.0\r\n\
+        PixelSizeX=5.5e-009\r\n\
+        PixelSizeY=5.5e-009\r\n\
+        [SEM]\r\n\
+        HV=10000\r\n\
+        WD=0.0061\r\n\
+        SpotSize=3.1e-009\r\n\
+        Detector=SE\r\n\
+        ScanRotation=12.5\r\n\
+        StageX=0.0123\r\n\
+        StageY=0.0045\r\n\
+        StageZ=0.0302\r\n\
+        StageRotation=30\r\n\
+        StageTilt=55\r\n\
+        EmissionCurrent=0.00021\r\n\
+        [FIB]\r\n\
+        HV=30000\r\n";
+
+    #[test]
+    fn test_sem_section_is_read_before_fib_with_main_as_fallback() {
+        let acquisition = decode(BLOCK).unwrap();
+        assert_eq!(acquisition.beam, Beam::Electron);
+        assert_eq!(acquisition.tool_name.as_deref(), Some("XEIA3"));
+        assert_eq!(
+            acquisition.accelerating_voltage,
+            Some(Quantity::new(10.0, "kV"))
+        );
+        assert_eq!(acquisition.working_distance, Some(Quantity::new(6.1, "mm")));
+        assert_eq!(
+            acquisition.emission_current,
+            Some(Quantity::new(210.0, "µA"))
+        );
+        assert_eq!(acquisition.magnification.as_deref(), Some("25000x"));
+        assert_eq!(acquisition.detectors, ["SE"]);
+        assert_eq!(acquisition.scan_rotation, Some(Quantity::new(12.5, "deg")));
+        assert_eq!(acquisition.pixel_height, Some(Quantity::new(5.5, "nm")));
+        assert_eq!(acquisition.stage.z, Some(Quantity::new(30.2, "mm")));
+        assert_eq!(acquisition.stage.rotation, Some(Quantity::new(30.0, "deg")));
+        assert_eq!(acquisition.stage.tilt, Some(Quantity::new(55.0, "deg")));
+        assert_eq!(acquisition.fib_tilt_angle, None);
+
+        let parameters = json!(acquisition.parameters);
+        assert_eq!(parameters["SEM"]["SpotSize"], "3.1e-009");
+        assert_eq!(parameters["SEM"].get("EmissionCurrent"), None);
+        assert_eq!(parameters["MAIN"]["SerialNumber"], "118-0042");
+        assert_eq!(parameters["FIB"]["HV"], "30000");
+        assert_eq!(parameters["SEM"].get("HV"), None);
+    }
+
+    #[test]
+    fn test_fib_only_blocks_are_ion_images_with_a_fib_tilt() {
+        let block = BLOCK
+            .replace("[SEM]", "[FIB]")
+            .replace("[FIB]\r\nHV=30000", "");
+        let acquisition = decode(&block).unwrap();
+        assert_eq!(acquisition.beam, Beam::Ion);
+        assert_eq!(
+            acquisition.accelerating_voltage,
+            Some(Quantity::new(10.0, "kV"))
+        );
+        assert_eq!(acquisition.fib_tilt_angle, Some(Quantity::new(55.0, "deg")));
+        assert_eq!(acquisition.emission_current, None);
+        assert_eq!(
+            json!(acquisition.parameters)["FIB"]["EmissionCurrent"],
+            "0.00021"
+        );
+
+        assert_eq!(decode("[SEM]\r\nHV=10000\r\n"), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! The CZ_SEM block of ZEISS SmartSEM images (tag 34118).
+//!
+//! The block is text with one parameter per pair of lines, its name and then
+//! its label and value:
+//!
+//! ```text
+//! AP_WD
+//! WD = 5.1 mm
+//! AP_STAGE_AT_T
+//! Stage at T = 0.0 °
+//! ```
+
+use famh_model::JsonMap;
+use serde_json::{Value, json};
+
+use super::acquisition::{Acquisition, Quantity, StagePosition, format_magnification, parameter};
+
+/// The private TIFF tag of the block.
+pub const CZ_SEM: u16 = 34118;
+
+/// A parameter of the block.
+struct Parameter {
+    label: String,
+    text: String,
+}
+
+impl Parameter {
+    fn quantity(&self) -> Option<Quantity> {
+        Quantity::parse(&self.text)
+    }
+
+    fn switch(&self) -> Option<bool> {
+        switch(&self.text)
+    }
+}
+
+/// The acquisition parameters of a CZ_SEM block, `None` if it has none.
+pub fn decode(text: &str) -> Option<Acquisition> {
+    let parameters = parse(text);
+    if parameters.is_empty() {
+        return None;
+    }
+    let get = |name: &str| parameters.iter().find(|(n, _)| n == name).map(|(_, p)| p);
+    let quantity = |names: &[&str]| names.iter().find_map(|name| get(name)?.quantity());
+
+    let signals: Vec<String> = parameters
+        .iter()
+        .filter(|(_, p)| matches!(p.label.as_str(), "Signal A" | "Signal B"))
+        .map(|(_, p)| p.text.clone())
+        .fold(Vec::new(), |mut signals, signal| {
+            if !signals.contains(&signal) {
+                signals.push(signal);
+            }
+            signals
+        });
+    let detectors = match get("DP_DETECTOR_TYPE") {
+        Some(detector) => vec![detector.text.clone()],
+        None => signals.clone(),
+    };
+    let mut signal_types = Vec::new();
+    for signal_type in signals.iter().filter_map(|signal| signal_type(signal)) {
+        if !signal_types.iter().any(|t| t == signal_type) {
+            signal_types.push(signal_type.to_string());
+        }
+    }
+
+    let mut values = JsonMap::new();
+    for (name, p) in &parameters {
+        let (value, unit) = typed_value(&p.text);
+        values.insert(name.clone(), parameter(&p.label, &value, unit.as_deref()));
+    }
+
+    let pixel_size = quantity(&["AP_PIXEL_SIZE"]);
+    Some(Acquisition {
+        manufacturer: Some("ZEISS".to_string()),
+        accelerating_voltage: quantity(&["AP_ACTUALKV", "AP_MANUALKV"]),
+        working_distance: quantity(&["AP_WD"]),
+        probe_current: quantity(&["AP_IPROBE"]),
+        emission_current: quantity(&["AP_EMISSION_CURRENT", "AP_BEAM_CURRENT"]),
+        magnification: get("AP_MAG").and_then(|mag| magnification(&mag.text)),
+        detectors,
+        signal_types,
+        signal_mixing: get("DP_MIXING").and_then(Parameter::switch),
+        aperture_size: quantity(&["AP_APERTURESIZE"]),
+        scan_rotation: quantity(&["AP_SCANROTATION"]),
+        scan_rotation_mode: get("DP_SCAN_ROT").and_then(Parameter::switch),
+        pixel_width: pixel_size.clone(),
+        pixel_height: pixel_size,
+        stage: StagePosition {
+            x: quantity(&["AP_STAGE_AT_X"]),
+            y: quantity(&["AP_STAGE_AT_Y"]),
+            z: quantity(&["AP_STAGE_AT_Z"]),
+            rotation: quantity(&["AP_STAGE_AT_R"]),
+            tilt: quantity(&["AP_STAGE_AT_T"]),
+        },
+        parameters: values,
+        ..Acquisition::default()
+    })
+}
+
+/// Parameters by name, in the order of the block. A value line without a
+/// name line before it is named by its label.
+fn parse(text: &str) -> Vec<(String, Parameter)> {
+    let mut parameters = Vec::new();
+    let mut name: Option<&str> = None;
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if is_name(line) {
+            name = Some(line);
+            continue;
+        }
+        let Some((label, value)) = line.split_once(['=', ':']) else {
+            name = None;
+            continue;
+        };
+        let label = label.trim();
+        let name = name.take().unwrap_or(label);
+        if label.is_empty() || parameters.iter().any(|(n, _)| n == name) {
+            continue;
+        }
+        parameters.push((
+            name.to_string(),
+            Parameter {
+                label: label.to_string(),
+                text: value.trim().to_string(),
+            },
+        ));
+    }
+    parameters
+}
+
+/// Names are upper case words joined by underscores, e.g. `AP_WD`.
+fn is_name(line: &str) -> bool {
+    line.contains('_')
+        && line
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// A value as JSON: a number with its unit, a switch or the text.
+fn typed_value(text: &str) -> (Value, Option<String>) {
+    if let Some(quantity) = Quantity::parse(text) {
+        let unit = (!quantity.unit.is_empty()).then_some(quantity.unit);
+        return (json!(quantity.value), unit);
+    }
+    match switch(text) {
+        Some(on) => (json!(on), None),
+        None => (json!(text), None),
+    }
+}
+
+fn switch(text: &str) -> Option<bool> {
+    match text {
+        "On" | "Yes" => Some(true),
+        "Off" | "No" => Some(false),
+        _ => None,
+    }
+}
+
+/// Magnification as `50000x` from `50.00 K X`.
+fn magnification(text: &str) -> Option<String> {
+    let quantity = Quantity::parse(text)?;
+    let factor = match quantity.unit.as_str() {
+        "X" | "x" => 1.0,
+        "K X" | "KX" | "k X" | "kX" => 1e3,
+        "M X" | "MX" => 1e6,
+        _ => return None,
+    };
+    Some(format_magnification(quantity.value * factor))
+}
+
+/// Signal type of a ZEISS detector: backscattered electron detectors are
+/// `BSE`, the others `SE`.
+fn signal_type(detector: &str) -> Option<&'static str> {
+    let detector = detector.to_ascii_uppercase();
+    if ["BSD", "ASB", "ESB", "BSE"]
+        .iter()
+        .any(|bse| detector.contains(bse))
+    {
+        Some("BSE")
+    } else if detector.contains("SE") || detector.contains("INLENS") {
+        Some("SE")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str = "0\r\n\
+        AP_ACTUALKV\r\nEHT = 3.00 kV\r\n\
+        AP_WD\r\nWD = 5.1 mm\r\n\
+        AP_IPROBE\r\nI Probe = 100.0 pA\r\n\
+        AP_MAG\r\nMag = 50.00 K X\r\n\
+        AP_APERTURESIZE\r\nAperture Size = 30.00 µm\r\n\
+        AP_PIXEL_SIZE\r\nPixel Size = 2.233 nm\r\n\
+        AP_SCANROTATION\r\nScan Rotation = 90.0 °\r\n\
+        DP_SCAN_ROT\r\nScan Rot = On\r\n\
+        DP_DETECTOR_CHANNEL\r\nSignal A = InLens\r\n\
+        DP_DETECTOR_CHANNEL_B\r\nSignal B = SE2\r\n\
+        DP_MIXING\r\nMixing = Off\r\n\
+        AP_STAGE_AT_X\r\nStage at X = 12.345 mm\r\n\
+        AP_STAGE_AT_Y\r\nStage at Y = 67.8 mm\r\n\
+        AP_STAGE_AT_Z\r\nStage at Z = 25.0 mm\r\n\
+        AP_STAGE_AT_R\r\nStage at R = 45.0 °\r\n\
+        AP_STAGE_AT_T\r\nStage at T = 0.0 °\r\n\
+        SV_USER_NAME\r\nUser Name = FA Lab\r\n";
+
+    #[test]
+    fn test_block_is_decoded() {
+        let acquisition = decode(BLOCK).unwrap();
+        assert_eq!(
+            acquisition.accelerating_voltage,
+            Some(Quantity::new(3.0, "kV"))
+        );
+        assert_eq!(acquisition.working_distance, Some(Quantity::new(5.1, "mm")));
+        assert_eq!(acquisition.probe_current, Some(Quantity::new(100.0, "pA")));
+        assert_eq!(acquisition.magnification.as_deref(), Some("50000x"));
+        assert_eq!(acquisition.aperture_size, Some(Quantity::new(30.0, "µm")));
+        assert_eq!(acquisition.scan_rotation, Some(Quantity::new(90.0, "deg")));
+        assert_eq!(acquisition.scan_rotation_mode, Some(true));
+        assert_eq!(acquisition.detectors, ["InLens", "SE2"]);
+        assert_eq!(acquisition.signal_types, ["SE"]);
+        assert_eq!(acquisition.signal_mixing, Some(false));
+        assert_eq!(acquisition.stage.x, Some(Quantity::new(12.345, "mm")));
+        assert_eq!(acquisition.stage.rotation, Some(Quantity::new(45.0, "deg")));
+        assert_eq!(
+            acquisition.parameters["AP_WD"],
+            json!({ "label": "WD", "value": 5.1, "unit": "mm" })
+        );
+        assert_eq!(
+            acquisition.parameters["SV_USER_NAME"],
+            json!({ "label": "User Name", "value": "FA Lab" })
+        );
+        assert_eq!(acquisition.parameters.len(), 17);
+
+        assert_eq!(decode("0\r\n"), None);
+    }
+
+    #[test]
+    fn test_magnification_is_written_as_a_factor() {
+        assert_eq!(magnification("50.00 K X").as_deref(), Some("50000x"));
+        assert_eq!(magnification("1.25 K X").as_deref(), Some("1250x"));
+        assert_eq!(magnification("250 X").as_deref(), Some("250x"));
+        assert_eq!(magnification("wide"), None);
+    }
+}
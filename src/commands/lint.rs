@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use famh_model::{v1, v2};
+
+use crate::lint::{LintReport, Linter};
+use crate::schema::{SchemaVersion, VersionSelection};
+use crate::source::read_json;
+
+/// Lint the FAMH document at `path` with the rules enabled in `linter`.
+pub fn lint_famh_file(
+    path: &str,
+    version: impl Into<VersionSelection>,
+    linter: &Linter,
+) -> Result<LintReport, Box<dyn std::error::Error>> {
+    let (document, source) = read_json(path)?;
+    let version = version.into().resolve(&document)?;
+    let unreadable = |e: String| format!("Cannot read {path} as a FAMH {version} header: {e}");
+    let findings = match version {
+        SchemaVersion::V1 => linter.lint(
+            &source
+                .deserialize::<v1::FaMetadataHeader>(path)
+                .map_err(unreadable)?,
+        ),
+        SchemaVersion::V2 => linter.lint(
+            &source
+                .deserialize::<v2::FaMetadataHeader>(path)
+                .map_err(unreadable)?,
+        ),
+    };
+    let mut report = LintReport {
+        path: path.to_string(),
+        version,
+        findings,
+        snippets: BTreeMap::new(),
+    };
+    report.locate(&source);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::Severity;
+    use serde_json::Value;
+
+    #[test]
+    fn test_lint_famh_file_detects_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.json");
+        let mut document: Value = serde_json::from_str(
+            &std::fs::read_to_string("tests/fixtures/v1/minimal_example_sem.json").unwrap(),
+        )
+        .unwrap();
+        document["General Section"]["Time Stamp"] = "2999-01-01T00:00:00Z".into();
+        std::fs::write(&path, document.to_string()).unwrap();
+
+        let report = lint_famh_file(
+            &path.to_string_lossy(),
+            VersionSelection::Auto,
+            &Linter::new(),
+        )
+        .unwrap();
+
+        assert_eq!(report.version.as_str(), "v1");
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].rule, "timestamp-in-future");
+        assert_eq!(
+            report.snippets["/General Section/Time Stamp"].width,
+            "\"2999-01-01T00:00:00Z\"".len()
+        );
+        assert_eq!(report.count(Severity::Warning), 1);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_deserialization_errors_are_located() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("header.json");
+        std::fs::write(
+            &path,
+            "{\n  \"generalSection\": {\n    \"bitDepth\": \"8\"\n  }\n}\n",
+        )
+        .unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let err = lint_famh_file(&path, VersionSelection::Auto, &Linter::new()).unwrap_err();
+
+        assert!(
+            err.to_string().starts_with(&format!(
+                "Cannot read {path} as a FAMH v2 header: {path}:3:17: invalid type"
+            )),
+            "{err}"
+        );
+    }
+}
@@ -1,4 +1,7 @@
 pub mod delete;
 pub mod edit;
 pub mod extract;
+pub mod lint;
+pub mod schema;
 pub mod validate;
+pub mod verify;
@@ -0,0 +1,340 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde_json::Value;
+
+use crate::schema::{
+    AUTO_VERSION, FieldDescription, HttpClient, SchemaCache, SchemaConfig, SchemaRegistry,
+    SectionSpec, VersionSpec, decode_cache_key, get_cache_dir, get_cache_root,
+};
+use crate::utils::{decode_pointer_token, field_to_json_pointer};
+
+/// A schema version stored in the on-disk cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedSchema {
+    pub source: String,
+    pub git_ref: String,
+    pub version: String,
+    pub path: PathBuf,
+    pub fragments: usize,
+    /// Date the newest fragment was downloaded.
+    pub modified: Option<NaiveDate>,
+    /// Date the fragments were last revalidated with the server.
+    pub checked: Option<NaiveDate>,
+}
+
+fn versions_or_all(
+    registry: &SchemaRegistry,
+    version: Option<&str>,
+) -> Result<Vec<VersionSpec>, Box<dyn std::error::Error>> {
+    match version {
+        Some(version) => Ok(vec![registry.get(version)?.clone()]),
+        None => Ok(registry.versions().to_vec()),
+    }
+}
+
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect()
+}
+
+fn latest_modification(paths: &[PathBuf]) -> Option<NaiveDate> {
+    paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+        .map(|time| DateTime::<Local>::from(time).date_naive())
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// List all cached schemas below `cache_root`.
+///
+/// The cache is laid out as `<source>/<ref>/<version>/<fragment>.json`, each
+/// fragment accompanied by a `<fragment>.json.meta` freshness record.
+pub fn list_cached_schemas_in(cache_root: &Path) -> Vec<CachedSchema> {
+    let mut cached = Vec::new();
+    for source_dir in sub_dirs(cache_root) {
+        for ref_dir in sub_dirs(&source_dir) {
+            for version_dir in sub_dirs(&ref_dir) {
+                let fragments = files_with_extension(&version_dir, "json");
+                if fragments.is_empty() {
+                    continue;
+                }
+                let metadata = files_with_extension(&version_dir, "meta");
+
+                cached.push(CachedSchema {
+                    source: decode_cache_key(&file_name_of(&source_dir)),
+                    git_ref: decode_cache_key(&file_name_of(&ref_dir)),
+                    version: file_name_of(&version_dir),
+                    path: version_dir.clone(),
+                    fragments: fragments.len(),
+                    modified: latest_modification(&fragments),
+                    checked: latest_modification(&metadata),
+                });
+            }
+        }
+    }
+    cached
+}
+
+pub fn list_cached_schemas(
+    config: &SchemaConfig,
+) -> Result<Vec<CachedSchema>, Box<dyn std::error::Error>> {
+    Ok(list_cached_schemas_in(&get_cache_root(config)?))
+}
+
+/// Cache directory for the given source and version, or the cache root if no
+/// version is given.
+pub fn schema_cache_path(
+    config: &SchemaConfig,
+    version: Option<&str>,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    match version {
+        Some(version) => get_cache_dir(config, SchemaRegistry::load(config)?.get(version)?),
+        None => get_cache_root(config),
+    }
+}
+
+/// Re-download the schema and overwrite the cached copy.
+pub async fn refresh_schema_cache(
+    config: &SchemaConfig,
+    version: Option<&str>,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let registry = SchemaRegistry::load(config)?;
+    let client = HttpClient::new(&config.http)?;
+    let mut refreshed = Vec::new();
+    for version in versions_or_all(&registry, version)? {
+        refreshed.push(SchemaCache::refresh(config, &client, &version.name).await?);
+    }
+    Ok(refreshed)
+}
+
+/// Remove cached schemas and return the directories that were deleted.
+///
+/// With `all` the whole cache is removed, otherwise only the cache of the
+/// configured source and ref.
+pub fn clear_schema_cache(
+    config: &SchemaConfig,
+    version: Option<&str>,
+    all: bool,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let dirs = if all {
+        vec![get_cache_root(config)?]
+    } else {
+        versions_or_all(&SchemaRegistry::load(config)?, version)?
+            .iter()
+            .map(|version| get_cache_dir(config, version))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut removed = Vec::new();
+    for dir in dirs {
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+            removed.push(dir);
+        }
+    }
+    Ok(removed)
+}
+
+/// Load the schema and return a single fragment, e.g. `methodSpecific`.
+///
+/// With `version` set to `auto` the version is the first one with the
+/// section.
+pub async fn show_schema_fragment(
+    config: &SchemaConfig,
+    version: &str,
+    section: &str,
+    no_cache: bool,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let version = if version.eq_ignore_ascii_case(AUTO_VERSION) {
+        first_version_with(&SchemaRegistry::load(config)?, section, |spec| {
+            spec.matches(section)
+        })?
+    } else {
+        version.to_string()
+    };
+
+    let schema_cache = SchemaCache::download_all(config, &version, !no_cache).await?;
+    let version = &schema_cache.version().name;
+    let sections: Vec<&str> = schema_cache
+        .all_sections()
+        .iter()
+        .map(|(section_name, _)| *section_name)
+        .collect();
+
+    schema_cache.find_fragment(section).cloned().ok_or_else(|| {
+        format!(
+            "Unknown {} schema section '{}'. Known sections: {}",
+            version,
+            section,
+            sections.join(", ")
+        )
+        .into()
+    })
+}
+
+/// Load the schema and describe the field at `field`, given in dot or
+/// pointer syntax, e.g. `generalSection.fileSize`.
+///
+/// With `version` set to `auto` the version is chosen by the section the
+/// field belongs to.
+pub async fn describe_schema_field(
+    config: &SchemaConfig,
+    version: &str,
+    field: &str,
+    no_cache: bool,
+) -> Result<FieldDescription, Box<dyn std::error::Error>> {
+    let pointer = field_to_json_pointer(field);
+    let version = if version.eq_ignore_ascii_case(AUTO_VERSION) {
+        version_defining_section(&SchemaRegistry::load(config)?, &pointer)?
+    } else {
+        version.to_string()
+    };
+
+    let schema_cache = SchemaCache::download_all(config, &version, !no_cache).await?;
+    schema_cache.describe_field(&pointer)
+}
+
+/// Name of the first version with a section named by the first token of
+/// `pointer`.
+fn version_defining_section(
+    registry: &SchemaRegistry,
+    pointer: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let section = decode_pointer_token(
+        pointer
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default(),
+    );
+    first_version_with(registry, &section, |spec| spec.name == section)
+}
+
+/// Name of the first version with a section accepted by `matches`.
+fn first_version_with(
+    registry: &SchemaRegistry,
+    section: &str,
+    matches: impl Fn(&SectionSpec) -> bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    registry
+        .versions()
+        .iter()
+        .find(|version| version.sections.iter().any(&matches))
+        .map(|version| version.name.clone())
+        .ok_or_else(|| {
+            format!("No schema version has a section '{section}'. Pass --version explicitly").into()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_list_cached_schemas_in_reads_source_ref_and_version() {
+        let root = TempDir::new().unwrap();
+        let version_dir = root
+            .path()
+            .join("example.com_2Ffork")
+            .join("feature_2Funits")
+            .join("v2");
+        fs::create_dir_all(&version_dir).unwrap();
+        fs::write(version_dir.join("generalSection.json"), "{}").unwrap();
+        fs::write(version_dir.join("methodSpecific.json"), "{}").unwrap();
+        fs::write(version_dir.join("methodSpecific.json.meta"), "{}").unwrap();
+        fs::create_dir_all(root.path().join("empty").join("master").join("v1")).unwrap();
+
+        let cached = list_cached_schemas_in(root.path());
+
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].source, "example.com/fork");
+        assert_eq!(cached[0].git_ref, "feature/units");
+        assert_eq!(cached[0].version, "v2");
+        assert_eq!(cached[0].fragments, 2);
+        assert!(cached[0].modified.is_some());
+        assert!(cached[0].checked.is_some());
+    }
+
+    #[test]
+    fn test_list_cached_schemas_in_missing_root_is_empty() {
+        let root = TempDir::new().unwrap();
+        assert!(list_cached_schemas_in(&root.path().join("missing")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_show_schema_fragment_returns_single_fragment() {
+        let config = SchemaConfig {
+            dir: Some(PathBuf::from("tests/schemas")),
+            ..SchemaConfig::default()
+        };
+
+        let fragment = show_schema_fragment(&config, "v2", "methodSpecific.json", false)
+            .await
+            .unwrap();
+        assert!(fragment["properties"].get("methodSpecific").is_some());
+
+        let result = show_schema_fragment(&config, "v2", "nope", false).await;
+        assert!(result.unwrap_err().to_string().contains("methodSpecific"));
+
+        let fragment = show_schema_fragment(&config, "auto", "methodSpecific", false)
+            .await
+            .unwrap();
+        assert!(fragment["properties"].get("methodSpecific").is_some());
+        let fragment = show_schema_fragment(&config, "auto", "General Section", false)
+            .await
+            .unwrap();
+        assert!(fragment["properties"].get("General Section").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_describe_schema_field_picks_version_by_section() {
+        let config = SchemaConfig {
+            dir: Some(PathBuf::from("tests/schemas")),
+            ..SchemaConfig::default()
+        };
+
+        let v1 = describe_schema_field(&config, "auto", "General Section.File Name", false)
+            .await
+            .unwrap();
+        assert_eq!(v1.pointer, "/General Section/File Name");
+        let v2 = describe_schema_field(&config, "auto", "/generalSection/fileName", false)
+            .await
+            .unwrap();
+        assert_eq!(v2.types, "string");
+
+        let err = describe_schema_field(&config, "auto", "nope.field", false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No schema version has a section 'nope'. Pass --version explicitly"
+        );
+    }
+}
@@ -1,147 +1,410 @@
-use crate::schema::{SchemaCache, SchemaVersion};
-use crate::utils::load_json;
-use colored::Colorize;
-use jsonschema;
-use serde_json::{Map, Value};
-use std::collections::HashSet;
+use crate::profile::Profile;
+use crate::report::{BatchReport, FileResult, ValidationReport};
+use crate::schema::{
+    AUTO_VERSION, Change, Fix, HttpClient, SchemaCache, SchemaConfig, SchemaOrigin,
+    resolve_version_name,
+};
+use crate::source::{SourceMap, located_error, read_json};
+use crate::utils::{
+    STDIN_PATH, default_jobs, display_path, parallel_map, read_input, write_bytes_atomically,
+};
+use crate::validator::FamhValidator;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
 
+/// Validate the FAMH document at `json_file_path`, or on standard input for
+/// `-`. Nothing is printed; the returned report holds every issue found.
 pub async fn validate_json(
     json_file_path: &str,
-    version: SchemaVersion,
+    version: impl AsRef<str>,
+    schema_config: &SchemaConfig,
     no_cache: bool,
     strict: bool,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let schema_cache = SchemaCache::download_all(version, !no_cache).await?;
-    let json_file = load_json(json_file_path)?;
+) -> Result<ValidationReport, Box<dyn std::error::Error>> {
+    let (json_file, source) = read_json(json_file_path)?;
+    let requested = version.as_ref();
+    let version = resolve_version_name(requested, &json_file)?;
+    let schema_cache = SchemaCache::download_all(schema_config, &version, !no_cache).await?;
 
-    validate_json_content(&json_file, &schema_cache, strict)
+    let mut report = validate_json_content(
+        display_path(json_file_path),
+        &json_file,
+        &schema_cache,
+        strict,
+    )?;
+    report.detected = requested.eq_ignore_ascii_case(AUTO_VERSION);
+    report.locate(&source);
+    Ok(report)
 }
 
-fn validate_json_content(
-    json_file: &Value,
-    schema_cache: &SchemaCache,
-    strict: bool,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let Some(top_level) = json_file.as_object() else {
-        return Err("Input JSON must be an object at top level".into());
+/// How a batch of documents is validated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchOptions {
+    pub no_cache: bool,
+    pub strict: bool,
+    /// Read every input as a stream of one document per line.
+    pub ndjson: bool,
+    /// Number of documents validated in parallel.
+    pub jobs: usize,
+    /// Profiles checked on top of the schema.
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            no_cache: false,
+            strict: false,
+            ndjson: false,
+            jobs: default_jobs(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// Validate many FAMH documents on `options.jobs` threads. Each schema
+/// version is loaded once. Files that cannot be read or validated are
+/// reported in the batch instead of aborting it; only failing to load a
+/// schema or to apply a profile to it is an error.
+///
+/// The path `-` reads standard input. With `options.ndjson`, every input is a
+/// stream of one document per line and each record is reported on its own.
+pub async fn validate_files(
+    paths: &[PathBuf],
+    version: impl AsRef<str>,
+    schema_config: &SchemaConfig,
+    options: &BatchOptions,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let requested = version.as_ref();
+    let detected = requested.eq_ignore_ascii_case(AUTO_VERSION);
+    let BatchOptions {
+        no_cache,
+        strict,
+        ndjson,
+        jobs,
+        ..
+    } = *options;
+    let documents = documents(paths, ndjson);
+
+    // Detect the version of every document first, so that each schema is
+    // loaded before validation starts. Files are read again when validating
+    // to keep memory bounded for large batches.
+    let versions: Vec<Result<String, String>> = if detected {
+        parallel_map(&documents, jobs, |document| {
+            let (value, _) = document.read()?;
+            resolve_version_name(requested, &value).map_err(|e| e.to_string())
+        })
+    } else {
+        vec![Ok(requested.to_string()); documents.len()]
     };
 
-    let mut json_valid: bool = true;
+    let client = HttpClient::new(&schema_config.http)?;
+    let mut validators: BTreeMap<&str, (FamhValidator, SchemaOrigin, Vec<String>)> =
+        BTreeMap::new();
+    for version in versions.iter().flatten() {
+        if !validators.contains_key(version.as_str()) {
+            let schema_cache =
+                SchemaCache::download_all_with(schema_config, &client, version, !no_cache).await?;
+            let mut validator = FamhValidator::new(&schema_cache)?;
+            for profile in &options.profiles {
+                validator = validator.with_profile(profile)?;
+            }
+            validators.insert(
+                version,
+                (
+                    validator,
+                    schema_cache.origin().clone(),
+                    schema_cache.warnings().to_vec(),
+                ),
+            );
+        }
+    }
 
-    let known_sections = schema_cache.all_sections();
-    let allowed_section_names: HashSet<&str> = known_sections
-        .iter()
-        .map(|(section_name, _)| *section_name)
-        .collect();
-
-    let mut unknown_sections: Vec<&str> = top_level
-        .keys()
-        .map(String::as_str)
-        .filter(|section_name| !allowed_section_names.contains(section_name))
-        .collect();
-    unknown_sections.sort_unstable();
-
-    if !unknown_sections.is_empty() {
-        println!(
-            "Unknown root-level sections: {}",
-            unknown_sections.join(", ").yellow()
-        );
-        if strict {
-            json_valid = false;
+    let inputs: Vec<(&Document, &Result<String, String>)> =
+        documents.iter().zip(&versions).collect();
+    let files = parallel_map(&inputs, jobs, |(document, version)| {
+        let validated = (*version).clone().and_then(|version| {
+            let (validator, origin, warnings) = &validators[version.as_str()];
+            let (value, source) = document.read()?;
+            let outcome = validator.validate(&value).map_err(|e| e.to_string())?;
+            let mut report = ValidationReport {
+                path: document.path.clone(),
+                record: document.record,
+                version: validator.version().name.clone(),
+                origin: origin.clone(),
+                detected,
+                strict,
+                outcome,
+                snippets: BTreeMap::new(),
+                warnings: warnings.clone(),
+            };
+            report.locate(&source);
+            Ok(report)
+        });
+        match validated {
+            Ok(report) => FileResult::Validated(report),
+            Err(error) => FileResult::Failed {
+                path: document.name(),
+                error,
+            },
+        }
+    });
+
+    Ok(BatchReport { files })
+}
+
+/// A document of a batch: a file, standard input, or a record of an NDJSON
+/// stream.
+struct Document {
+    /// Path shown in reports, `<stdin>` for standard input.
+    path: String,
+    /// Line of the record in an NDJSON stream.
+    record: Option<usize>,
+    source: DocumentSource,
+}
+
+enum DocumentSource {
+    /// A file, read when the document is.
+    File,
+    /// Text read from standard input or an NDJSON stream.
+    Text(String),
+    /// The input could not be read.
+    Unreadable(String),
+}
+
+impl Document {
+    fn name(&self) -> String {
+        match self.record {
+            Some(line) => format!("{}:{}", self.path, line),
+            None => self.path.clone(),
         }
     }
 
-    if !verify_required_sections(schema_cache, top_level) {
-        json_valid = false;
+    fn read(&self) -> Result<(Value, SourceMap), String> {
+        let text = match &self.source {
+            DocumentSource::File => {
+                read_input(&self.path).map_err(|e| format!("{}: {e}", self.path))?
+            }
+            DocumentSource::Text(text) => text.clone(),
+            DocumentSource::Unreadable(error) => return Err(error.clone()),
+        };
+        // Locations of records are lines of the stream.
+        let source = SourceMap::new(text).with_first_line(self.record.unwrap_or(1));
+        let value = serde_json::from_str(source.text())
+            .map_err(|e| source.located_error(&self.path, &e))?;
+        Ok((value, source))
     }
+}
 
-    for (section_name, schema) in known_sections {
-        let section_is_valid = validate_schema_section(top_level, schema, section_name)?;
-        if !section_is_valid {
-            json_valid = false;
+/// The documents of `paths`. Standard input and NDJSON streams are read here,
+/// files only when their documents are.
+fn documents(paths: &[PathBuf], ndjson: bool) -> Vec<Document> {
+    let mut documents = Vec::new();
+    for path in paths {
+        let path = path.to_string_lossy();
+        let name = display_path(&path).to_string();
+        if !ndjson && path != STDIN_PATH {
+            documents.push(Document {
+                path: name,
+                record: None,
+                source: DocumentSource::File,
+            });
+            continue;
+        }
+        let text = match read_input(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                documents.push(Document {
+                    source: DocumentSource::Unreadable(e.to_string()),
+                    path: name,
+                    record: None,
+                });
+                continue;
+            }
+        };
+        if !ndjson {
+            documents.push(Document {
+                path: name,
+                record: None,
+                source: DocumentSource::Text(text),
+            });
+            continue;
+        }
+        for (index, line) in text.lines().enumerate() {
+            if !line.trim().is_empty() {
+                documents.push(Document {
+                    path: name.clone(),
+                    record: Some(index + 1),
+                    source: DocumentSource::Text(line.to_string()),
+                });
+            }
         }
     }
+    documents
+}
 
-    Ok(json_valid)
+/// The repairs of one file and its text before and after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixReport {
+    pub path: String,
+    /// Name of the schema version the repairs were derived from.
+    pub version: String,
+    pub fixes: Vec<Fix>,
+    pub original: String,
+    pub fixed: String,
 }
-/// Verify that all required root-level sections exist in the FA Header
-fn verify_required_sections(
-    schema_cache: &SchemaCache,
-    top_level_schema: &Map<String, Value>,
-) -> bool {
-    let mut all_required_sections_exist = true;
-    let mut missing_required: Vec<&str> = schema_cache
-        .required_sections()
-        .iter()
-        .copied()
-        .filter(|required| !top_level_schema.contains_key(*required))
-        .collect();
-    missing_required.sort_unstable();
-
-    if !missing_required.is_empty() {
-        println!(
-            "Missing required sections: {}",
-            missing_required.join(", ").bold()
-        );
-        all_required_sections_exist = false;
+
+impl FixReport {
+    /// Unified diff of the repaired file. Every repair stays on its line, so
+    /// the diff has a one-line hunk per changed line.
+    pub fn diff(&self) -> String {
+        let mut out = String::new();
+        if self.fixes.is_empty() {
+            return out;
+        }
+        let _ = writeln!(out, "--- {}", self.path);
+        let _ = writeln!(out, "+++ {}", self.path);
+        for (index, (before, after)) in self.original.lines().zip(self.fixed.lines()).enumerate() {
+            if before != after {
+                let _ = writeln!(out, "@@ -{0} +{0} @@\n-{before}\n+{after}", index + 1);
+            }
+        }
+        out
     }
-    all_required_sections_exist
 }
 
-fn validate_schema_section(
-    top_level_schema: &Map<String, Value>,
-    schema: &Value,
-    section_name: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let mut section_is_valid = true;
-    if let Some(section_data) = top_level_schema.get(section_name) {
-        let section_schema = get_section_validation_schema(section_name, schema)?;
-        let validator = jsonschema::validator_for(section_schema)?;
-        let errors: Vec<jsonschema::ValidationError<'_>> =
-            validator.iter_errors(section_data).collect();
-
-        if errors.is_empty() {
-            println!("{} {}", section_name, "section is valid".green());
-        } else {
-            section_is_valid = false;
-            println!(
-                "{} section - {} validation error(s):",
-                section_name,
-                errors.len()
-            );
-            for err in errors {
-                let full_error_path = format!("/{section_name}{}", err.instance_path.as_str());
-                println!("{}: {}", full_error_path.red(), err);
+/// Find the safe repairs of every file in `paths` and, unless `dry_run` is
+/// set, write the repaired files atomically. Files without repairs are left
+/// untouched. Each schema version is loaded once; files that cannot be read,
+/// repaired or written are returned as errors naming the file.
+pub async fn fix_files(
+    paths: &[PathBuf],
+    version: impl AsRef<str>,
+    schema_config: &SchemaConfig,
+    no_cache: bool,
+    dry_run: bool,
+) -> Result<Vec<Result<FixReport, String>>, Box<dyn std::error::Error>> {
+    let client = HttpClient::new(&schema_config.http)?;
+    let mut schemas: BTreeMap<String, SchemaCache> = BTreeMap::new();
+    let mut reports = Vec::new();
+    for path in paths {
+        let path = path.to_string_lossy().to_string();
+        if path == STDIN_PATH && !dry_run {
+            reports.push(Err(
+                "Cannot write repairs to standard input, use --dry-run".to_string()
+            ));
+            continue;
+        }
+        let source = read_input(&path);
+        let path = display_path(&path).to_string();
+        let source = match source {
+            Ok(text) => SourceMap::new(text),
+            Err(e) => {
+                reports.push(Err(format!("{path}: {e}")));
+                continue;
+            }
+        };
+        let document: Value = match serde_json::from_str(source.text()) {
+            Ok(document) => document,
+            Err(e) => {
+                reports.push(Err(located_error(&path, &e)));
+                continue;
             }
+        };
+        let version = match resolve_version_name(version.as_ref(), &document) {
+            Ok(version) => version,
+            Err(e) => {
+                reports.push(Err(format!("{path}: {e}")));
+                continue;
+            }
+        };
+        if !schemas.contains_key(&version) {
+            let schema_cache =
+                SchemaCache::download_all_with(schema_config, &client, &version, !no_cache).await?;
+            schemas.insert(version.clone(), schema_cache);
+        }
+        let report = fix_source(&path, &document, &source, &schemas[&version]);
+        if let Ok(report) = &report
+            && !dry_run
+            && !report.fixes.is_empty()
+            && let Err(e) = write_bytes_atomically(&path, report.fixed.as_bytes())
+        {
+            reports.push(Err(format!("Cannot write {path}: {e}")));
+            continue;
         }
+        reports.push(report);
     }
-    Ok(section_is_valid)
+    Ok(reports)
 }
 
-fn get_section_validation_schema<'a>(
-    section_name: &str,
-    schema: &'a Value,
-) -> Result<&'a Value, Box<dyn std::error::Error>> {
-    if let Some(section_schema) = schema.get("properties").and_then(|p| p.get(section_name)) {
-        return Ok(section_schema);
-    }
+fn fix_source(
+    path: &str,
+    document: &Value,
+    source: &SourceMap,
+    schema_cache: &SchemaCache,
+) -> Result<FixReport, String> {
+    let fixes = schema_cache.fixes(document);
+    let mut edits = fixes
+        .iter()
+        .map(|fix| {
+            let range = match &fix.change {
+                Change::RenameKey(_) => source.key_range(&fix.instance_path),
+                Change::ReplaceValue(_) => source.value_range(&fix.instance_path),
+            };
+            let range =
+                range.ok_or_else(|| format!("{path}: cannot locate {}", fix.instance_path))?;
+            Ok((range, fix.change.to_json()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
 
-    if let Some(section_schema) = schema.get(section_name) {
-        return Ok(section_schema);
+    let mut fixed = source.text().to_string();
+    for (range, text) in edits {
+        fixed.replace_range(range, &text);
     }
+    serde_json::from_str::<Value>(&fixed)
+        .map_err(|e| format!("{path}: the repaired document is not valid JSON: {e}"))?;
+    Ok(FixReport {
+        path: path.to_string(),
+        version: schema_cache.version().name.clone(),
+        fixes,
+        original: source.text().to_string(),
+        fixed,
+    })
+}
 
-    Err(format!("Could not find schema definition for section '{section_name}'").into())
+fn validate_json_content(
+    json_file_path: &str,
+    json_file: &Value,
+    schema_cache: &SchemaCache,
+    strict: bool,
+) -> Result<ValidationReport, Box<dyn std::error::Error>> {
+    let validator = FamhValidator::new(schema_cache)?;
+    Ok(ValidationReport {
+        path: json_file_path.to_string(),
+        record: None,
+        version: schema_cache.version().name.clone(),
+        origin: schema_cache.origin().clone(),
+        detected: false,
+        strict,
+        outcome: validator.validate(json_file)?,
+        snippets: BTreeMap::new(),
+        warnings: schema_cache.warnings().to_vec(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::V2SchemaCache;
+    use crate::schema::{SchemaOrigin, SchemaRegistry, VersionSpec};
     use serde_json::json;
 
     fn v2_test_cache() -> SchemaCache {
-        SchemaCache::V2(V2SchemaCache {
-            general: json!({
+        let version = SchemaRegistry::built_in().get("v2").unwrap().clone();
+        let fragments = vec![
+            json!({
                 "type": "object",
                 "properties": {
                     "generalSection": {
@@ -153,19 +416,19 @@ mod tests {
                     }
                 }
             }),
-            customer: json!({
+            json!({
                 "type": "object",
                 "properties": {
                     "customerSpecific": { "type": "object" }
                 }
             }),
-            tool: json!({
+            json!({
                 "type": "object",
                 "properties": {
                     "toolSpecific": { "type": "object" }
                 }
             }),
-            method: json!({
+            json!({
                 "type": "object",
                 "properties": {
                     "methodSpecific": {
@@ -177,19 +440,29 @@ mod tests {
                     }
                 }
             }),
-            data_evaluation: json!({
+            json!({
                 "type": "object",
                 "properties": {
                     "dataEvaluation": { "type": "object" }
                 }
             }),
-            history: json!({
+            json!({
                 "type": "object",
                 "properties": {
                     "history": { "type": "object" }
                 }
             }),
-        })
+        ];
+        SchemaCache::new(version, fragments, SchemaOrigin::downloaded()).unwrap()
+    }
+
+    fn general_section_cache(schema: Value) -> SchemaCache {
+        let version: VersionSpec = serde_json::from_value(json!({
+            "name": "test",
+            "sections": [{ "name": "generalSection", "file": "generalSection.json" }]
+        }))
+        .unwrap();
+        SchemaCache::new(version, vec![schema], SchemaOrigin::downloaded()).unwrap()
     }
 
     #[test]
@@ -198,7 +471,9 @@ mod tests {
             "generalSection": { "fileName": "sample.tif" }
         });
 
-        let is_valid = validate_json_content(&input, &v2_test_cache(), true).unwrap();
+        let is_valid = validate_json_content("input.json", &input, &v2_test_cache(), true)
+            .unwrap()
+            .is_valid();
         assert!(!is_valid);
     }
 
@@ -210,7 +485,9 @@ mod tests {
             "General": {}
         });
 
-        let is_valid = validate_json_content(&input, &v2_test_cache(), true).unwrap();
+        let is_valid = validate_json_content("input.json", &input, &v2_test_cache(), true)
+            .unwrap()
+            .is_valid();
         assert!(!is_valid);
     }
 
@@ -221,19 +498,20 @@ mod tests {
             "methodSpecific": { "method": "SEM" }
         });
 
-        let is_valid = validate_json_content(&input, &v2_test_cache(), false).unwrap();
+        let is_valid = validate_json_content("input.json", &input, &v2_test_cache(), false)
+            .unwrap()
+            .is_valid();
         assert!(!is_valid);
     }
 
     #[test]
-    fn validate_schema_section_accepts_valid_section() {
+    fn section_validation_accepts_valid_section() {
         let input = json!({
             "generalSection": {
                 "fileName": "sample.tif",
                 "method": "SEM"
             }
         });
-        let top_level = input.as_object().unwrap();
         let schema = json!({
             "properties": {
                 "generalSection": {
@@ -247,19 +525,21 @@ mod tests {
             }
         });
 
-        let is_valid = validate_schema_section(top_level, &schema, "generalSection").unwrap();
+        let schema_cache = general_section_cache(schema);
+        let is_valid = validate_json_content("input.json", &input, &schema_cache, true)
+            .unwrap()
+            .is_valid();
         assert!(is_valid);
     }
 
     #[test]
-    fn validate_schema_section_reports_multiple_failures() {
+    fn section_validation_reports_multiple_failures() {
         let input = json!({
             "generalSection": {
                 "fileName": 123,
                 "method": 456
             }
         });
-        let top_level = input.as_object().unwrap();
         let schema = json!({
             "properties": {
                 "generalSection": {
@@ -273,18 +553,110 @@ mod tests {
             }
         });
 
-        let section_data = top_level.get("generalSection").unwrap();
-        let section_schema = get_section_validation_schema("generalSection", &schema).unwrap();
-        let validator = jsonschema::validator_for(section_schema).unwrap();
-        let errors: Vec<_> = validator.iter_errors(section_data).collect();
+        let schema_cache = general_section_cache(schema);
+        let outcome = FamhValidator::new(&schema_cache)
+            .unwrap()
+            .validate(&input)
+            .unwrap();
 
         assert!(
-            errors.len() >= 2,
+            outcome.error_count() >= 2,
             "expected multiple errors, got {}",
-            errors.len()
+            outcome.error_count()
         );
 
-        let is_valid = validate_schema_section(top_level, &schema, "generalSection").unwrap();
+        let is_valid = validate_json_content("input.json", &input, &schema_cache, false)
+            .unwrap()
+            .is_valid();
         assert!(!is_valid);
     }
+
+    #[test]
+    fn section_validation_resolves_refs_into_other_fragments() {
+        let mut schema_cache = v2_test_cache();
+        let mut fragments: Vec<Value> = schema_cache
+            .all_sections()
+            .into_iter()
+            .map(|(_, fragment)| fragment.clone())
+            .collect();
+        fragments[0]["properties"]["generalSection"]["properties"]["fileName"] =
+            json!({ "$ref": "toolSpecific.json#/$defs/fileName" });
+        fragments[2]["$defs"] = json!({ "fileName": { "type": "string", "pattern": "\\.tiff?$" } });
+        schema_cache = SchemaCache::new(
+            schema_cache.version().clone(),
+            fragments,
+            SchemaOrigin::downloaded(),
+        )
+        .unwrap();
+
+        let valid = json!({
+            "generalSection": { "fileName": "sample.tif" },
+            "methodSpecific": { "method": "SEM" }
+        });
+        let invalid = json!({
+            "generalSection": { "fileName": "sample.png" },
+            "methodSpecific": { "method": "SEM" }
+        });
+        assert!(
+            validate_json_content("input.json", &valid, &schema_cache, false)
+                .unwrap()
+                .is_valid()
+        );
+        assert!(
+            !validate_json_content("input.json", &invalid, &schema_cache, false)
+                .unwrap()
+                .is_valid()
+        );
+    }
+
+    #[test]
+    fn fixes_keep_the_formatting_and_repair_the_document() {
+        let schema_cache = SchemaCache::fixture("v2");
+        let text = "{\n  \"generalSection\": {\n    \"fileName\": \"a.tif\",\n    \"imageWidth\": \"1024 px\",\n    \"imageHeight\": { \"Value\": \"768\", \"Unit\": \"px\" }\n  }\n}\n";
+        let source = SourceMap::new(text);
+        let document: Value = serde_json::from_str(text).unwrap();
+
+        let report = fix_source("a.json", &document, &source, &schema_cache).unwrap();
+
+        assert_eq!(report.fixes.len(), 4);
+        assert_eq!(
+            report.fixed,
+            "{\n  \"generalSection\": {\n    \"fileName\": \"a.tif\",\n    \"imageWidth\": { \"value\": 1024, \"unit\": \"px\" },\n    \"imageHeight\": { \"value\": 768, \"unit\": \"px\" }\n  }\n}\n"
+        );
+        assert_eq!(
+            report.diff(),
+            "--- a.json\n+++ a.json\n@@ -4 +4 @@\n-    \"imageWidth\": \"1024 px\",\n+    \"imageWidth\": { \"value\": 1024, \"unit\": \"px\" },\n@@ -5 +5 @@\n-    \"imageHeight\": { \"Value\": \"768\", \"Unit\": \"px\" }\n+    \"imageHeight\": { \"value\": 768, \"unit\": \"px\" }\n"
+        );
+        let validator = FamhValidator::new(&schema_cache).unwrap();
+        let image_errors = |document: &Value| {
+            validator.validate(document).unwrap().sections[0]
+                .errors
+                .iter()
+                .filter(|error| error.instance_path.starts_with("/generalSection/image"))
+                .count()
+        };
+        assert!(image_errors(&document) > 0);
+        assert_eq!(
+            image_errors(&serde_json::from_str(&report.fixed).unwrap()),
+            0
+        );
+    }
+
+    #[test]
+    fn section_validation_reports_unresolvable_refs() {
+        let schema = json!({
+            "properties": {
+                "generalSection": { "$ref": "common.json#/$defs/section" }
+            }
+        });
+        let input = json!({ "generalSection": {} });
+
+        let err =
+            validate_json_content("input.json", &input, &general_section_cache(schema), false)
+                .unwrap_err()
+                .to_string();
+
+        assert!(err.contains("'common.json#/$defs/section' at /properties/generalSection/$ref"));
+        assert!(err.contains("generalSection schema fragment (generalSection.json)"));
+    }
 }
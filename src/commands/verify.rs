@@ -0,0 +1,516 @@
+use std::path::{Path, PathBuf};
+
+use famh_model::{Numeric, v1, v2};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::commands::extract::{TiffInfo, read_tiff_info};
+use crate::schema::{SchemaVersion, VersionSelection};
+use crate::source::{SourceMap, read_json};
+use crate::utils::encode_pointer_token;
+
+/// Allowed deviation of `compressedBitsPerPixel` from the image, which covers
+/// values rounded to one decimal.
+const BITS_PER_PIXEL_TOLERANCE: f64 = 0.05;
+
+/// A header field that disagrees with the image.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    /// The compared property, e.g. `imageWidth` or `compression`.
+    pub check: &'static str,
+    /// JSON pointer of the header field.
+    pub pointer: String,
+    pub header: Value,
+    pub image: Value,
+    pub message: String,
+}
+
+/// Result of comparing a header with its image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+    pub header: String,
+    pub image: PathBuf,
+    pub version: SchemaVersion,
+    pub image_info: TiffInfo,
+    /// Pointers of the header fields compared with the image.
+    pub compared: Vec<String>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Compare the FAMH header at `header_path` with the TIFF image it describes.
+///
+/// Without `image`, the image is looked up from `filePath` and `fileName`,
+/// relative to the directory of the header.
+pub fn verify_famh_file(
+    header_path: &str,
+    image: Option<&str>,
+    version: impl Into<VersionSelection>,
+) -> Result<VerifyReport, Box<dyn std::error::Error>> {
+    let (document, source) = read_json(header_path)?;
+    let version = version.into().resolve(&document)?;
+    let facts = HeaderFacts::read(&source, header_path, version)
+        .map_err(|e| format!("Cannot read {header_path} as a FAMH {version} header: {e}"))?;
+    let image = match image {
+        Some(image) => PathBuf::from(image),
+        None => facts.resolve_image(header_path)?,
+    };
+    let image_info = read_tiff_info(&image)?;
+    let mut verification = Verification::default();
+    facts.compare(&image_info, &mut verification);
+
+    Ok(VerifyReport {
+        header: header_path.to_string(),
+        image,
+        version,
+        image_info,
+        compared: verification.compared,
+        mismatches: verification.mismatches,
+    })
+}
+
+/// Field names that differ between v1 and v2.
+struct FieldNames {
+    general: &'static str,
+    file_path: &'static str,
+    file_name: &'static str,
+    file_format: &'static str,
+    file_size: &'static str,
+    image_width: &'static str,
+    image_height: &'static str,
+    bit_depth: &'static str,
+    compressed_bits_per_pixel: &'static str,
+}
+
+const V1_NAMES: FieldNames = FieldNames {
+    general: "General Section",
+    file_path: "File Path",
+    file_name: "File Name",
+    file_format: "File Format",
+    file_size: "File Size",
+    image_width: "Image Width",
+    image_height: "Image Height",
+    bit_depth: "Bit Depth",
+    compressed_bits_per_pixel: "Compressed Bits/Pixel",
+};
+
+const V2_NAMES: FieldNames = FieldNames {
+    general: "generalSection",
+    file_path: "filePath",
+    file_name: "fileName",
+    file_format: "fileFormat",
+    file_size: "fileSize",
+    image_width: "imageWidth",
+    image_height: "imageHeight",
+    bit_depth: "bitDepth",
+    compressed_bits_per_pixel: "compressedBitsPerPixel",
+};
+
+/// The general section fields that describe the image file.
+struct HeaderFacts {
+    names: &'static FieldNames,
+    file_path: Option<String>,
+    file_name: Option<String>,
+    file_format: Option<String>,
+    file_size: Option<(f64, Option<String>)>,
+    image_width: Option<i64>,
+    image_height: Option<i64>,
+    bit_depth: Option<i64>,
+    compressed_bits_per_pixel: Option<f64>,
+}
+
+impl HeaderFacts {
+    fn read(source: &SourceMap, path: &str, version: SchemaVersion) -> Result<Self, String> {
+        Ok(match version {
+            SchemaVersion::V1 => {
+                let general = source
+                    .deserialize::<v1::FaMetadataHeader>(path)?
+                    .general_section;
+                Self {
+                    names: &V1_NAMES,
+                    file_path: general.file_path,
+                    file_name: general.file_name,
+                    file_format: general.file_format,
+                    file_size: general
+                        .file_size
+                        .and_then(|size| Some((size.value? as f64, size.unit))),
+                    image_width: general.image_width.and_then(|width| width.value),
+                    image_height: general.image_height.and_then(|height| height.value),
+                    bit_depth: general.bit_depth,
+                    compressed_bits_per_pixel: general
+                        .compressed_bits_per_pixel
+                        .as_ref()
+                        .map(Numeric::as_f64),
+                }
+            }
+            SchemaVersion::V2 => {
+                let general = source
+                    .deserialize::<v2::FaMetadataHeader>(path)?
+                    .general_section
+                    .unwrap_or_default();
+                Self {
+                    names: &V2_NAMES,
+                    file_path: general.file_path,
+                    file_name: general.file_name,
+                    file_format: general.file_format,
+                    file_size: general
+                        .file_size
+                        .and_then(|size| Some((size.value?.as_f64(), size.unit))),
+                    image_width: general.image_width.and_then(|width| width.value),
+                    image_height: general.image_height.and_then(|height| height.value),
+                    bit_depth: general.bit_depth,
+                    compressed_bits_per_pixel: general
+                        .compressed_bits_per_pixel
+                        .as_ref()
+                        .map(Numeric::as_f64),
+                }
+            }
+        })
+    }
+
+    fn pointer(&self, field: &str) -> String {
+        format!(
+            "/{}/{}",
+            encode_pointer_token(self.names.general),
+            encode_pointer_token(field)
+        )
+    }
+
+    /// The first existing file among `filePath`, `fileName` and the file name
+    /// of `filePath`, with relative paths resolved against the header's
+    /// directory.
+    fn resolve_image(&self, header_path: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let base = Path::new(header_path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let mut candidates = Vec::new();
+        if let Some(file_path) = &self.file_path {
+            let path = base.join(file_path);
+            if path.is_dir()
+                && let Some(file_name) = &self.file_name
+            {
+                candidates.push(path.join(file_name));
+            }
+            candidates.push(path);
+        }
+        if let Some(file_name) = &self.file_name {
+            candidates.push(base.join(file_name));
+        }
+        if let Some(file_name) = self
+            .file_path
+            .as_deref()
+            .and_then(|path| Path::new(path).file_name())
+        {
+            candidates.push(base.join(file_name));
+        }
+        let mut unique: Vec<PathBuf> = Vec::new();
+        for candidate in candidates {
+            if !unique.contains(&candidate) {
+                unique.push(candidate);
+            }
+        }
+        let candidates = unique;
+
+        if candidates.is_empty() {
+            return Err(format!(
+                "{} names no image in {} or {}, pass it with --image",
+                header_path, self.names.file_path, self.names.file_name
+            )
+            .into());
+        }
+        if let Some(image) = candidates.iter().find(|path| path.is_file()) {
+            return Ok(image.clone());
+        }
+        let tried: Vec<String> = candidates
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        Err(format!(
+            "Cannot find the image described by {} (tried {}), pass it with --image",
+            header_path,
+            tried.join(", ")
+        )
+        .into())
+    }
+
+    fn compare(&self, image: &TiffInfo, verification: &mut Verification) {
+        let names = self.names;
+        for (check, name, claimed, actual) in [
+            (
+                "imageWidth",
+                names.image_width,
+                self.image_width,
+                image.width,
+            ),
+            (
+                "imageHeight",
+                names.image_height,
+                self.image_height,
+                image.height,
+            ),
+        ] {
+            let Some(claimed) = claimed else { continue };
+            let pointer = self.pointer(name);
+            if claimed != i64::from(actual) {
+                verification.mismatches.push(Mismatch {
+                    check,
+                    message: format!("{name} is {claimed} px but the image has {actual} px"),
+                    pointer: pointer.clone(),
+                    header: json!(claimed),
+                    image: json!(actual),
+                });
+            }
+            verification.compared.push(pointer);
+        }
+
+        if let Some(bit_depth) = self.bit_depth {
+            let pointer = self.pointer(names.bit_depth);
+            let samples = &image.bits_per_sample;
+            if samples.iter().any(|bits| i64::from(*bits) != bit_depth) {
+                let actual = match samples.as_slice() {
+                    [first, rest @ ..] if rest.iter().all(|bits| bits == first) => json!(first),
+                    _ => json!(samples),
+                };
+                verification.mismatches.push(Mismatch {
+                    check: "bitDepth",
+                    message: format!(
+                        "{} is {} but the image has {} bits per sample",
+                        names.bit_depth, bit_depth, actual
+                    ),
+                    pointer: pointer.clone(),
+                    header: json!(bit_depth),
+                    image: actual,
+                });
+            }
+            verification.compared.push(pointer);
+        }
+
+        if let Some((size, unit)) = &self.file_size {
+            let pointer = self.pointer(names.file_size);
+            let unit = unit.as_deref().unwrap_or("bytes");
+            let actual = image.file_size;
+            let message = match byte_factor(unit) {
+                None => Some(format!(
+                    "{} has unit '{}', which is not a unit of file size",
+                    names.file_size, unit
+                )),
+                // Sizes are rounded to the last decimal place written.
+                Some(factor)
+                    if (size * factor - actual as f64).abs() > factor * rounding_error(*size) =>
+                {
+                    Some(format!(
+                        "{} is {} {} but the image has {} bytes",
+                        names.file_size, size, unit, actual
+                    ))
+                }
+                Some(_) => None,
+            };
+            if let Some(message) = message {
+                verification.mismatches.push(Mismatch {
+                    check: "fileSize",
+                    pointer: pointer.clone(),
+                    header: json!({ "value": size, "unit": unit }),
+                    image: json!({ "value": actual, "unit": "bytes" }),
+                    message,
+                });
+            }
+            verification.compared.push(pointer);
+        }
+
+        if let Some(format) = &self.file_format {
+            let pointer = self.pointer(names.file_format);
+            let extension = format.trim().trim_start_matches('.').to_ascii_lowercase();
+            if extension != "tif" && extension != "tiff" {
+                verification.mismatches.push(Mismatch {
+                    check: "fileFormat",
+                    message: format!(
+                        "{} is '{}' but the image is a TIFF file",
+                        names.file_format, format
+                    ),
+                    pointer: pointer.clone(),
+                    header: json!(format),
+                    image: json!(".tiff"),
+                });
+            }
+            verification.compared.push(pointer);
+        }
+
+        if let Some(claimed) = self.compressed_bits_per_pixel {
+            let pointer = self.pointer(names.compressed_bits_per_pixel);
+            let actual = image.compressed_bits_per_pixel();
+            if (claimed - actual).abs() > BITS_PER_PIXEL_TOLERANCE {
+                let actual = (actual * 100.0).round() / 100.0;
+                verification.mismatches.push(Mismatch {
+                    check: "compression",
+                    message: format!(
+                        "{} is {} but the image stores {} bits per pixel ({} compression)",
+                        names.compressed_bits_per_pixel, claimed, actual, image.compression
+                    ),
+                    pointer: pointer.clone(),
+                    header: json!(claimed),
+                    image: json!(actual),
+                });
+            }
+            verification.compared.push(pointer);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Verification {
+    compared: Vec<String>,
+    mismatches: Vec<Mismatch>,
+}
+
+/// Bytes per `unit` of a file size, or `None` if `unit` is not a size unit.
+fn byte_factor(unit: &str) -> Option<f64> {
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "b" | "byte" | "bytes" => 1.0,
+        "kb" => 1e3,
+        "kib" => 1024.0,
+        "mb" => 1e6,
+        "mib" => 1024.0 * 1024.0,
+        "gb" => 1e9,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some(factor)
+}
+
+/// Largest error of a value rounded to the last decimal place it is written
+/// with, e.g. 0.05 for 0.3 and 0.5 for 310202.
+fn rounding_error(value: f64) -> f64 {
+    let text = value.to_string();
+    let decimals = text
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    0.5 * 10f64.powi(-(decimals as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE: &str = "tests/fixtures/images/example_image.tif";
+
+    /// A v2 header next to a copy of the example image, which is a 640 x 480
+    /// PackBits compressed 8 bit TIFF of 310202 bytes.
+    fn header_with_image(general: Value) -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::copy(IMAGE, dir.path().join("scan.tif")).unwrap();
+        let path = dir.path().join("scan.json");
+        std::fs::write(&path, json!({ "generalSection": general }).to_string()).unwrap();
+        (dir, path.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn test_matching_header_is_consistent() {
+        let (_dir, header) = header_with_image(json!({
+            "filePath": "/instrument/export/scan.tif",
+            "fileFormat": ".TIF",
+            "fileSize": { "value": 0.3, "unit": "MB" },
+            "imageWidth": { "value": 640, "unit": "px" },
+            "imageHeight": { "value": 480, "unit": "px" },
+            "bitDepth": 8,
+            "compressedBitsPerPixel": 8.06
+        }));
+
+        let report = verify_famh_file(&header, None, VersionSelection::Auto).unwrap();
+
+        assert!(report.image.ends_with("scan.tif"));
+        assert_eq!(report.compared.len(), 6);
+        assert!(report.is_consistent(), "{:?}", report.mismatches);
+    }
+
+    #[test]
+    fn test_file_size_tolerance_follows_the_stated_precision() {
+        for (value, consistent) in [(0.31, true), (0.32, false), (310.2, true), (310.3, false)] {
+            let unit = if value < 1.0 { "MB" } else { "kB" };
+            let (_dir, header) = header_with_image(json!({
+                "fileName": "scan.tif",
+                "fileSize": { "value": value, "unit": unit }
+            }));
+
+            let report = verify_famh_file(&header, None, VersionSelection::V2).unwrap();
+
+            assert_eq!(report.is_consistent(), consistent, "{value} {unit}");
+        }
+    }
+
+    #[test]
+    fn test_mismatches_name_field_and_both_values() {
+        let (_dir, header) = header_with_image(json!({
+            "fileName": "scan.tif",
+            "fileFormat": "png",
+            "fileSize": { "value": 310000, "unit": "bytes" },
+            "imageWidth": { "value": 1024, "unit": "px" },
+            "imageHeight": { "value": 480, "unit": "px" },
+            "bitDepth": 16,
+            "compressedBitsPerPixel": 4
+        }));
+
+        let report = verify_famh_file(&header, None, VersionSelection::V2).unwrap();
+
+        let checks: Vec<&str> = report.mismatches.iter().map(|m| m.check).collect();
+        assert_eq!(
+            checks,
+            vec![
+                "imageWidth",
+                "bitDepth",
+                "fileSize",
+                "fileFormat",
+                "compression"
+            ]
+        );
+        let width = &report.mismatches[0];
+        assert_eq!(width.pointer, "/generalSection/imageWidth");
+        assert_eq!((&width.header, &width.image), (&json!(1024), &json!(640)));
+        assert_eq!(
+            report.mismatches[4].message,
+            "compressedBitsPerPixel is 4 but the image stores 8.06 bits per pixel (PackBits compression)"
+        );
+    }
+
+    #[test]
+    fn test_v1_headers_and_explicit_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = dir.path().join("header.json");
+        std::fs::write(
+            &header,
+            json!({ "General Section": {
+                "File Name": "missing.tif",
+                "Image Height": { "Value": 768, "Unit": "px" },
+                "Compressed Bits/Pixel": 8.1
+            }})
+            .to_string(),
+        )
+        .unwrap();
+        let header = header.to_string_lossy().to_string();
+
+        let err = verify_famh_file(&header, None, VersionSelection::Auto).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Cannot find the image described by")
+        );
+
+        let report = verify_famh_file(&header, Some(IMAGE), VersionSelection::Auto).unwrap();
+        assert_eq!(report.version, SchemaVersion::V1);
+        assert_eq!(
+            report.compared,
+            vec![
+                "/General Section/Image Height",
+                "/General Section/Compressed Bits~1Pixel"
+            ]
+        );
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(
+            report.mismatches[0].message,
+            "Image Height is 768 px but the image has 480 px"
+        );
+    }
+}
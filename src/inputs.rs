@@ -0,0 +1,251 @@
+//! Expansion of command line inputs into the list of files to process.
+//!
+//! An input is a file, a directory, or `@<filelist>` naming a text file with
+//! one input per line. Directories are expanded to the files matching the
+//! include globs and none of the exclude globs; explicitly named files are
+//! always kept.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_INCLUDE: &str = "*.json";
+
+/// How directories are expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputOptions {
+    /// Descend into subdirectories.
+    pub recursive: bool,
+    /// Globs matched against paths relative to the expanded directory.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            include: vec![DEFAULT_INCLUDE.to_string()],
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Expand `inputs` into a list of files without duplicates, in the order
+/// given. Files inside a directory are sorted by path. Paths that do not
+/// exist are kept, so the caller can report them per file.
+pub fn collect_inputs(
+    inputs: &[String],
+    options: &InputOptions,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let include = build_glob_set(&options.include, "include")?;
+    let exclude = build_glob_set(&options.exclude, "exclude")?;
+    let mut collector = Collector {
+        options,
+        include,
+        exclude,
+        seen: HashSet::new(),
+        files: Vec::new(),
+    };
+    for input in inputs {
+        collector.add_input(input, 0)?;
+    }
+    Ok(collector.files)
+}
+
+/// Inputs given as `@<filelist>` may name further filelists, up to this depth.
+const MAX_FILELIST_DEPTH: usize = 8;
+
+struct Collector<'a> {
+    options: &'a InputOptions,
+    include: GlobSet,
+    exclude: GlobSet,
+    seen: HashSet<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl Collector<'_> {
+    fn add_input(&mut self, input: &str, depth: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(list) = input.strip_prefix('@') {
+            if depth >= MAX_FILELIST_DEPTH {
+                return Err(format!("Filelists are nested too deeply at @{list}").into());
+            }
+            let content = fs::read_to_string(list)
+                .map_err(|e| format!("Failed to read filelist {list}: {e}"))?;
+            for line in content.lines().map(str::trim) {
+                if !line.is_empty() && !line.starts_with('#') {
+                    self.add_input(line, depth + 1)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut files = Vec::new();
+            self.walk(path, path, &mut HashSet::new(), &mut files)?;
+            files.sort();
+            for file in files {
+                self.push(file);
+            }
+        } else {
+            self.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Collect the files below `dir`. Directories are entered once each, by
+    /// their canonical path, so symlinks pointing back up the tree do not
+    /// loop forever.
+    fn walk(
+        &self,
+        root: &Path,
+        dir: &Path,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let canonical = fs::canonicalize(dir)
+            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+        if !visited.insert(canonical) {
+            return Ok(());
+        }
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry?.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if self.exclude.is_match(relative) {
+                continue;
+            }
+            if path.is_dir() {
+                if self.options.recursive {
+                    self.walk(root, &path, visited, files)?;
+                }
+            } else if self.include.is_match(relative) {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, path: PathBuf) {
+        if self.seen.insert(path.clone()) {
+            self.files.push(path);
+        }
+    }
+}
+
+fn build_glob_set(patterns: &[String], kind: &str) -> Result<GlobSet, Box<dyn std::error::Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).map_err(|e| format!("Invalid {kind} glob '{pattern}': {e}"))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(dir: &Path, relative: &str) {
+        let path = dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "{}").unwrap();
+    }
+
+    fn names(root: &Path, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| {
+                file.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    fn sample_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "b.json");
+        touch(dir.path(), "a.json");
+        touch(dir.path(), "notes.txt");
+        touch(dir.path(), "lot1/c.json");
+        touch(dir.path(), "lot1/archive/d.json");
+        dir
+    }
+
+    #[test]
+    fn test_directories_are_expanded_with_globs() {
+        let dir = sample_tree();
+        let root = dir.path().to_string_lossy().to_string();
+
+        let flat = collect_inputs(std::slice::from_ref(&root), &InputOptions::default()).unwrap();
+        assert_eq!(names(dir.path(), &flat), vec!["a.json", "b.json"]);
+
+        let options = InputOptions {
+            recursive: true,
+            exclude: vec!["**/archive".to_string()],
+            ..InputOptions::default()
+        };
+        let recursive = collect_inputs(&[root], &options).unwrap();
+        assert_eq!(
+            names(dir.path(), &recursive),
+            vec!["a.json", "b.json", "lot1/c.json"]
+        );
+    }
+
+    #[test]
+    fn test_filelists_are_read_and_duplicates_dropped() {
+        let dir = sample_tree();
+        let list = dir.path().join("inputs.txt");
+        let a = dir.path().join("a.json");
+        fs::write(
+            &list,
+            format!(
+                "# nightly batch\n{}\n\n{}\n",
+                a.display(),
+                dir.path().join("notes.txt").display()
+            ),
+        )
+        .unwrap();
+
+        let inputs = vec![
+            format!("@{}", list.display()),
+            a.to_string_lossy().to_string(),
+        ];
+        let files = collect_inputs(&inputs, &InputOptions::default()).unwrap();
+        assert_eq!(names(dir.path(), &files), vec!["a.json", "notes.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loops_are_walked_once() {
+        let dir = sample_tree();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("lot1/loop")).unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let options = InputOptions {
+            recursive: true,
+            ..InputOptions::default()
+        };
+
+        let once = collect_inputs(std::slice::from_ref(&root), &options).unwrap();
+        std::fs::remove_file(dir.path().join("lot1/loop")).unwrap();
+        let without_loop = collect_inputs(&[root], &options).unwrap();
+
+        assert_eq!(once, without_loop);
+    }
+
+    #[test]
+    fn test_invalid_globs_are_rejected() {
+        let options = InputOptions {
+            include: vec!["[".to_string()],
+            ..InputOptions::default()
+        };
+        let err = collect_inputs(&[], &options).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid include glob '['"));
+    }
+}
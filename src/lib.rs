@@ -1,6 +1,15 @@
 pub mod cli;
 pub mod commands;
+pub mod inputs;
+pub mod lint;
+pub mod profile;
+pub mod report;
 pub mod schema;
+pub mod source;
+pub mod units;
 pub mod utils;
+pub mod validator;
 
 pub use cli::{Cli, Commands};
+pub use report::ValidationReport;
+pub use validator::FamhValidator;
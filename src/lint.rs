@@ -0,0 +1,583 @@
+//! Semantic checks of FAMH headers.
+//!
+//! JSON Schema checks each field on its own; lint rules check that fields agree
+//! with each other, e.g. that `generalSection.method` matches the populated
+//! `methodSpecific` subsection. Rules look at a version-neutral
+//! [`HeaderView`], so every rule applies to FAMH v1 and v2 headers alike.
+
+use famh_model::{Numeric, v1, v2};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::schema::SchemaVersion;
+use crate::source::{Snippet, SourceMap};
+
+mod rules;
+
+pub use crate::report::Severity;
+
+/// A semantic check of a FAMH header.
+pub trait LintRule: Send + Sync {
+    /// Stable identifier used to enable, disable or reconfigure the rule.
+    fn id(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn default_severity(&self) -> Severity;
+    /// Append a violation for every problem found in `header`.
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>);
+}
+
+/// A problem reported by a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer of the offending field.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(pointer: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// A violation together with the rule that reported it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub pointer: String,
+    pub message: String,
+}
+
+/// A rule as configured in a [`Linter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleInfo {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub severity: Severity,
+    pub enabled: bool,
+}
+
+struct ConfiguredRule {
+    rule: Box<dyn LintRule>,
+    severity: Severity,
+    enabled: bool,
+}
+
+/// A set of lint rules, each of which can be disabled or given a different
+/// severity.
+pub struct Linter {
+    rules: Vec<ConfiguredRule>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    /// A linter with all built-in rules enabled.
+    pub fn new() -> Self {
+        let mut linter = Self { rules: Vec::new() };
+        for rule in rules::builtin() {
+            linter.push(rule);
+        }
+        linter
+    }
+
+    /// Add a custom rule.
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.push(Box::new(rule));
+        self
+    }
+
+    fn push(&mut self, rule: Box<dyn LintRule>) {
+        self.rules.push(ConfiguredRule {
+            severity: rule.default_severity(),
+            enabled: true,
+            rule,
+        });
+    }
+
+    pub fn rules(&self) -> Vec<RuleInfo> {
+        self.rules
+            .iter()
+            .map(|configured| RuleInfo {
+                id: configured.rule.id(),
+                description: configured.rule.description(),
+                severity: configured.severity,
+                enabled: configured.enabled,
+            })
+            .collect()
+    }
+
+    pub fn set_enabled(
+        &mut self,
+        id: &str,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.rule_mut(id)?.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn set_severity(
+        &mut self,
+        id: &str,
+        severity: Severity,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.rule_mut(id)?.severity = severity;
+        Ok(())
+    }
+
+    fn rule_mut(&mut self, id: &str) -> Result<&mut ConfiguredRule, Box<dyn std::error::Error>> {
+        let known: Vec<&str> = self.rules.iter().map(|rule| rule.rule.id()).collect();
+        let known = known.join(", ");
+        self.rules
+            .iter_mut()
+            .find(|rule| rule.rule.id() == id)
+            .ok_or_else(|| format!("Unknown lint rule '{id}'. Known rules: {known}").into())
+    }
+
+    /// Run all enabled rules on `header`, e.g. a `&v2::FaMetadataHeader`.
+    pub fn lint(&self, header: impl Into<HeaderView>) -> Vec<Finding> {
+        let header = header.into();
+        let mut findings = Vec::new();
+        for configured in self.rules.iter().filter(|rule| rule.enabled) {
+            let mut violations = Vec::new();
+            configured.rule.check(&header, &mut violations);
+            findings.extend(violations.into_iter().map(|violation| Finding {
+                rule: configured.rule.id(),
+                severity: configured.severity,
+                pointer: violation.pointer,
+                message: violation.message,
+            }));
+        }
+        findings
+    }
+
+    /// Lint a JSON document of the given FAMH version.
+    pub fn lint_document(
+        &self,
+        document: Value,
+        version: SchemaVersion,
+    ) -> Result<Vec<Finding>, Box<dyn std::error::Error>> {
+        let header = match version {
+            SchemaVersion::V1 => HeaderView::from(&v1::FaMetadataHeader::from_value(document)?),
+            SchemaVersion::V2 => HeaderView::from(&v2::FaMetadataHeader::from_value(document)?),
+        };
+        Ok(self.lint(header))
+    }
+}
+
+/// Findings of linting one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintReport {
+    pub path: String,
+    pub version: SchemaVersion,
+    pub findings: Vec<Finding>,
+    /// Source snippets of the fields findings point to, filled by
+    /// [`LintReport::locate`].
+    pub snippets: BTreeMap<String, Snippet>,
+}
+
+impl LintReport {
+    /// Record where the field of each finding is written in `source`.
+    pub fn locate(&mut self, source: &SourceMap) {
+        self.snippets = self
+            .findings
+            .iter()
+            .filter_map(|finding| {
+                Some((finding.pointer.clone(), source.snippet(&finding.pointer)?))
+            })
+            .collect();
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    /// Whether no rule reported an error.
+    pub fn is_clean(&self) -> bool {
+        self.count(Severity::Error) == 0
+    }
+}
+
+/// A field of the header together with its JSON pointer, which is known even
+/// when the field is absent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field<T> {
+    pub pointer: String,
+    pub value: Option<T>,
+}
+
+impl<T> Field<T> {
+    fn new(pointer: String, value: Option<T>) -> Self {
+        Self { pointer, value }
+    }
+
+    /// Name of the field, e.g. `signalTypes` or `Signal Type(s)`.
+    pub fn name(&self) -> &str {
+        self.pointer.rsplit('/').next().unwrap_or_default()
+    }
+}
+
+/// Acquisition methods with a subsection in `methodSpecific`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Sem,
+    Fib,
+    Optical,
+}
+
+impl Method {
+    pub const ALL: [Method; 3] = [Method::Sem, Method::Fib, Method::Optical];
+
+    /// The value of `generalSection.method` for this method.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Sem => "SEM",
+            Method::Fib => "FIB",
+            Method::Optical => "Optical",
+        }
+    }
+
+    /// Parse a method name, ignoring case. The subsection names are accepted
+    /// as well, e.g. `Scanning Electron Microscopy`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL.into_iter().find(|method| {
+            name.eq_ignore_ascii_case(method.as_str())
+                || name.eq_ignore_ascii_case(method.subsection(SchemaVersion::V1))
+                || name.eq_ignore_ascii_case(method.subsection(SchemaVersion::V2))
+        })
+    }
+
+    /// Name of the method's subsection in `methodSpecific`.
+    pub fn subsection(&self, version: SchemaVersion) -> &'static str {
+        match (self, version) {
+            (Method::Sem, SchemaVersion::V1) => "Scanning Electron Microscopy",
+            (Method::Fib, SchemaVersion::V1) => "Focused Ion Beam",
+            (Method::Optical, SchemaVersion::V1) => "Optical Microscopy",
+            (Method::Sem, SchemaVersion::V2) => "scanningElectronMicroscopy",
+            (Method::Fib, SchemaVersion::V2) => "focusedIonBeam",
+            (Method::Optical, SchemaVersion::V2) => "opticalMicroscopy",
+        }
+    }
+}
+
+/// A populated subsection of `methodSpecific`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSection {
+    pub method: Method,
+    pub pointer: String,
+    pub signal_types: Field<Vec<String>>,
+    pub signal_proportion: Field<Vec<Option<f64>>>,
+    pub signal_mixing: Field<bool>,
+}
+
+/// The fields checked by lint rules, with their pointers in the source
+/// document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderView {
+    pub version: SchemaVersion,
+    pub method: Field<String>,
+    /// Pointer of the `methodSpecific` section.
+    pub method_specific: String,
+    pub method_sections: Vec<MethodSection>,
+    pub pixel_width: Field<f64>,
+    pub pixel_height: Field<f64>,
+    pub time_stamp: Field<String>,
+}
+
+/// Field names that differ between v1 and v2.
+struct FieldNames {
+    general: &'static str,
+    method_specific: &'static str,
+    method: &'static str,
+    pixel_width: &'static str,
+    pixel_height: &'static str,
+    time_stamp: &'static str,
+    signal_types: &'static str,
+    signal_proportion: &'static str,
+    signal_mixing: &'static str,
+}
+
+const V1_NAMES: FieldNames = FieldNames {
+    general: "General Section",
+    method_specific: "Method Specific",
+    method: "Method",
+    pixel_width: "Pixel Width",
+    pixel_height: "Pixel Height",
+    time_stamp: "Time Stamp",
+    signal_types: "Signal Type(s)",
+    signal_proportion: "Signal Proportion",
+    signal_mixing: "Signal Mixing",
+};
+
+const V2_NAMES: FieldNames = FieldNames {
+    general: "generalSection",
+    method_specific: "methodSpecific",
+    method: "method",
+    pixel_width: "pixelWidth",
+    pixel_height: "pixelHeight",
+    time_stamp: "timeStamp",
+    signal_types: "signalTypes",
+    signal_proportion: "signalProportion",
+    signal_mixing: "signalMixing",
+};
+
+/// Values shared by the method subsections of both versions.
+struct SectionValues<'a> {
+    signal_types: &'a Option<Vec<String>>,
+    signal_proportion: &'a Option<Vec<Option<Numeric>>>,
+    signal_mixing: Option<bool>,
+}
+
+/// Values of the general section shared by both versions.
+struct GeneralValues<'a> {
+    method: Option<&'a String>,
+    pixel_width: Option<&'a Numeric>,
+    pixel_height: Option<&'a Numeric>,
+    time_stamp: Option<&'a String>,
+}
+
+impl HeaderView {
+    fn build(
+        version: SchemaVersion,
+        names: &FieldNames,
+        general: GeneralValues<'_>,
+        sections: Vec<(Method, SectionValues<'_>)>,
+    ) -> Self {
+        let general_field = |name: &str| format!("/{}/{}", names.general, name);
+        let method_specific = format!("/{}", names.method_specific);
+        let method_sections = sections
+            .into_iter()
+            .map(|(method, values)| {
+                let pointer = format!("{}/{}", method_specific, method.subsection(version));
+                let field = |name: &str| format!("{pointer}/{name}");
+                MethodSection {
+                    method,
+                    signal_types: Field::new(
+                        field(names.signal_types),
+                        values.signal_types.clone(),
+                    ),
+                    signal_proportion: Field::new(
+                        field(names.signal_proportion),
+                        values.signal_proportion.as_ref().map(|values| {
+                            values
+                                .iter()
+                                .map(|v| v.as_ref().map(Numeric::as_f64))
+                                .collect()
+                        }),
+                    ),
+                    signal_mixing: Field::new(field(names.signal_mixing), values.signal_mixing),
+                    pointer,
+                }
+            })
+            .collect();
+
+        Self {
+            version,
+            method: Field::new(general_field(names.method), general.method.cloned()),
+            method_specific,
+            method_sections,
+            pixel_width: Field::new(
+                general_field(names.pixel_width),
+                general.pixel_width.map(Numeric::as_f64),
+            ),
+            pixel_height: Field::new(
+                general_field(names.pixel_height),
+                general.pixel_height.map(Numeric::as_f64),
+            ),
+            time_stamp: Field::new(general_field(names.time_stamp), general.time_stamp.cloned()),
+        }
+    }
+}
+
+impl From<&v1::FaMetadataHeader> for HeaderView {
+    fn from(header: &v1::FaMetadataHeader) -> Self {
+        let general = &header.general_section;
+        let method_specific = &header.method_specific;
+        let mut sections = Vec::new();
+        if let Some(sem) = &method_specific.scanning_electron_microscopy {
+            sections.push((
+                Method::Sem,
+                SectionValues {
+                    signal_types: &sem.signal_types,
+                    signal_proportion: &sem.signal_proportion,
+                    signal_mixing: sem.signal_mixing,
+                },
+            ));
+        }
+        if let Some(fib) = &method_specific.focused_ion_beam {
+            sections.push((
+                Method::Fib,
+                SectionValues {
+                    signal_types: &fib.signal_types,
+                    signal_proportion: &fib.signal_proportion,
+                    signal_mixing: fib.signal_mixing,
+                },
+            ));
+        }
+        if method_specific.optical_microscopy.is_some() {
+            sections.push((
+                Method::Optical,
+                SectionValues {
+                    signal_types: &None,
+                    signal_proportion: &None,
+                    signal_mixing: None,
+                },
+            ));
+        }
+
+        let values = GeneralValues {
+            method: general.method.as_ref(),
+            pixel_width: general.pixel_width.as_ref().and_then(|v| v.value.as_ref()),
+            pixel_height: general.pixel_height.as_ref().and_then(|v| v.value.as_ref()),
+            time_stamp: general.time_stamp.as_ref(),
+        };
+        Self::build(SchemaVersion::V1, &V1_NAMES, values, sections)
+    }
+}
+
+impl From<&v2::FaMetadataHeader> for HeaderView {
+    fn from(header: &v2::FaMetadataHeader) -> Self {
+        let mut sections = Vec::new();
+        if let Some(method_specific) = &header.method_specific {
+            if let Some(sem) = &method_specific.scanning_electron_microscopy {
+                sections.push((
+                    Method::Sem,
+                    SectionValues {
+                        signal_types: &sem.signal_types,
+                        signal_proportion: &sem.signal_proportion,
+                        signal_mixing: sem.signal_mixing,
+                    },
+                ));
+            }
+            if let Some(fib) = &method_specific.focused_ion_beam {
+                sections.push((
+                    Method::Fib,
+                    SectionValues {
+                        signal_types: &fib.signal_types,
+                        signal_proportion: &fib.signal_proportion,
+                        signal_mixing: fib.signal_mixing,
+                    },
+                ));
+            }
+            if method_specific.optical_microscopy.is_some() {
+                sections.push((
+                    Method::Optical,
+                    SectionValues {
+                        signal_types: &None,
+                        signal_proportion: &None,
+                        signal_mixing: None,
+                    },
+                ));
+            }
+        }
+
+        let general = header.general_section.as_ref();
+        let values = GeneralValues {
+            method: general.and_then(|g| g.method.as_ref()),
+            pixel_width: general
+                .and_then(|g| g.pixel_width.as_ref())
+                .and_then(|v| v.value.as_ref()),
+            pixel_height: general
+                .and_then(|g| g.pixel_height.as_ref())
+                .and_then(|v| v.value.as_ref()),
+            time_stamp: general.and_then(|g| g.time_stamp.as_ref()),
+        };
+        Self::build(SchemaVersion::V2, &V2_NAMES, values, sections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(path: &str) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fixtures_are_clean() {
+        let linter = Linter::new();
+        for (path, version) in [
+            (
+                "tests/fixtures/v1/minimal_example_sem.json",
+                SchemaVersion::V1,
+            ),
+            (
+                "tests/fixtures/v1/minimal_example_fib.json",
+                SchemaVersion::V1,
+            ),
+            (
+                "tests/fixtures/v2/complete_example_v2.json",
+                SchemaVersion::V2,
+            ),
+            (
+                "tests/fixtures/v2/minimal_example_optical.json",
+                SchemaVersion::V2,
+            ),
+        ] {
+            let findings = linter.lint_document(fixture(path), version).unwrap();
+            assert_eq!(findings, Vec::new(), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_rules_can_be_disabled_and_reconfigured() {
+        let mut document = fixture("tests/fixtures/v2/minimal_example_optical.json");
+        document["generalSection"]["method"] = "SEM".into();
+
+        let mut linter = Linter::new();
+        let findings = linter
+            .lint_document(document.clone(), SchemaVersion::V2)
+            .unwrap();
+        assert_eq!(findings[0].rule, "method-subsection");
+        assert_eq!(findings[0].severity, Severity::Error);
+
+        linter
+            .set_severity("method-subsection", Severity::Info)
+            .unwrap();
+        let findings = linter
+            .lint_document(document.clone(), SchemaVersion::V2)
+            .unwrap();
+        assert_eq!(findings[0].severity, Severity::Info);
+
+        linter.set_enabled("method-subsection", false).unwrap();
+        assert!(
+            linter
+                .lint_document(document, SchemaVersion::V2)
+                .unwrap()
+                .is_empty()
+        );
+
+        let err = linter.set_enabled("no-such-rule", false).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Unknown lint rule 'no-such-rule'. Known rules: method-subsection")
+        );
+    }
+
+    #[test]
+    fn test_v1_pointers_use_v1_field_names() {
+        let mut document = fixture("tests/fixtures/v1/minimal_example_sem.json");
+        document["General Section"]["Pixel Width"] =
+            serde_json::json!({ "Value": 0, "Unit": "nm" });
+
+        let findings = Linter::new()
+            .lint_document(document, SchemaVersion::V1)
+            .unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].pointer, "/General Section/Pixel Width");
+    }
+}
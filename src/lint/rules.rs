@@ -0,0 +1,392 @@
+//! Built-in lint rules.
+
+use chrono::{DateTime, Utc};
+
+use super::{HeaderView, LintRule, Method, Severity, Violation};
+
+/// Allowed deviation of the sum of `signalProportion` from 1.
+const PROPORTION_TOLERANCE: f64 = 1e-3;
+
+pub(super) fn builtin() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(MethodSubsection),
+        Box::new(SignalProportion),
+        Box::new(SignalMixing),
+        Box::new(PositivePixelSize),
+        Box::new(TimestampNotInFuture),
+    ]
+}
+
+/// `generalSection.method` must match the populated `methodSpecific`
+/// subsection.
+struct MethodSubsection;
+
+impl LintRule for MethodSubsection {
+    fn id(&self) -> &'static str {
+        "method-subsection"
+    }
+
+    fn description(&self) -> &'static str {
+        "The method must match the populated method specific subsection"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>) {
+        let Some(name) = &header.method.value else {
+            return;
+        };
+        let Some(method) = Method::parse(name) else {
+            let known: Vec<&str> = Method::ALL.iter().map(Method::as_str).collect();
+            violations.push(Violation::new(
+                &header.method.pointer,
+                format!(
+                    "{} '{}' has no method specific subsection, expected one of {}",
+                    header.method.name(),
+                    name,
+                    known.join(", ")
+                ),
+            ));
+            return;
+        };
+
+        if !header
+            .method_sections
+            .iter()
+            .any(|section| section.method == method)
+        {
+            violations.push(Violation::new(
+                &header.method.pointer,
+                format!(
+                    "{} is '{}' but {} has no '{}' subsection",
+                    header.method.name(),
+                    name,
+                    header.method_specific,
+                    method.subsection(header.version)
+                ),
+            ));
+        }
+        for section in header
+            .method_sections
+            .iter()
+            .filter(|section| section.method != method)
+        {
+            violations.push(Violation::new(
+                &section.pointer,
+                format!(
+                    "'{}' is populated but {} is '{}'",
+                    section.method.subsection(header.version),
+                    header.method.name(),
+                    name
+                ),
+            ));
+        }
+    }
+}
+
+/// `signalProportion` must have one entry per signal type and sum to 1.
+struct SignalProportion;
+
+impl LintRule for SignalProportion {
+    fn id(&self) -> &'static str {
+        "signal-proportion"
+    }
+
+    fn description(&self) -> &'static str {
+        "The signal proportion must have one entry per signal type and sum to 1"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>) {
+        for section in &header.method_sections {
+            let proportion = &section.signal_proportion;
+            let Some(values) = &proportion.value else {
+                continue;
+            };
+
+            if let Some(types) = &section.signal_types.value
+                && types.len() != values.len()
+            {
+                violations.push(Violation::new(
+                    &proportion.pointer,
+                    format!(
+                        "{} has {} entries but {} has {}",
+                        proportion.name(),
+                        values.len(),
+                        section.signal_types.name(),
+                        types.len()
+                    ),
+                ));
+            }
+
+            // Unknown (null) proportions cannot be summed.
+            let sum: Option<f64> = values.iter().copied().sum();
+            if let Some(sum) = sum
+                && !values.is_empty()
+                && (sum - 1.0).abs() > PROPORTION_TOLERANCE
+            {
+                violations.push(Violation::new(
+                    &proportion.pointer,
+                    format!("{} sums to {}, expected 1", proportion.name(), sum),
+                ));
+            }
+        }
+    }
+}
+
+/// `signalMixing` must be true when more than one signal type is recorded.
+struct SignalMixing;
+
+impl LintRule for SignalMixing {
+    fn id(&self) -> &'static str {
+        "signal-mixing"
+    }
+
+    fn description(&self) -> &'static str {
+        "Signal mixing must be enabled when there are multiple signal types"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>) {
+        for section in &header.method_sections {
+            let Some(types) = &section.signal_types.value else {
+                continue;
+            };
+            let mixing = &section.signal_mixing;
+            if types.len() > 1 && mixing.value != Some(true) {
+                let state = match mixing.value {
+                    Some(_) => "false",
+                    None => "missing",
+                };
+                violations.push(Violation::new(
+                    &mixing.pointer,
+                    format!(
+                        "{} is {} but {} lists {} signal types",
+                        mixing.name(),
+                        state,
+                        section.signal_types.name(),
+                        types.len()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// `pixelWidth` and `pixelHeight` must be positive.
+struct PositivePixelSize;
+
+impl LintRule for PositivePixelSize {
+    fn id(&self) -> &'static str {
+        "pixel-size"
+    }
+
+    fn description(&self) -> &'static str {
+        "Pixel width and height must be positive"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>) {
+        for field in [&header.pixel_width, &header.pixel_height] {
+            if let Some(value) = field.value
+                && value <= 0.0
+            {
+                violations.push(Violation::new(
+                    &field.pointer,
+                    format!("{} must be positive, got {}", field.name(), value),
+                ));
+            }
+        }
+    }
+}
+
+/// `timeStamp` must not lie in the future. Timestamps that are not RFC 3339
+/// are left to schema validation.
+struct TimestampNotInFuture;
+
+impl LintRule for TimestampNotInFuture {
+    fn id(&self) -> &'static str {
+        "timestamp-in-future"
+    }
+
+    fn description(&self) -> &'static str {
+        "The time stamp must not be in the future"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, header: &HeaderView, violations: &mut Vec<Violation>) {
+        let field = &header.time_stamp;
+        let Some(time_stamp) = field
+            .value
+            .as_deref()
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        else {
+            return;
+        };
+        if time_stamp > Utc::now() {
+            violations.push(Violation::new(
+                &field.pointer,
+                format!(
+                    "{} {} is in the future",
+                    field.name(),
+                    time_stamp.to_rfc3339()
+                ),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Field, MethodSection};
+    use crate::schema::SchemaVersion;
+
+    fn field<T>(pointer: &str, value: Option<T>) -> Field<T> {
+        Field::new(pointer.to_string(), value)
+    }
+
+    fn sem_section(
+        types: Option<Vec<&str>>,
+        proportion: Option<Vec<Option<f64>>>,
+        mixing: Option<bool>,
+    ) -> MethodSection {
+        let pointer = "/methodSpecific/scanningElectronMicroscopy";
+        MethodSection {
+            method: Method::Sem,
+            pointer: pointer.to_string(),
+            signal_types: field(
+                &format!("{pointer}/signalTypes"),
+                types.map(|types| types.into_iter().map(String::from).collect()),
+            ),
+            signal_proportion: field(&format!("{pointer}/signalProportion"), proportion),
+            signal_mixing: field(&format!("{pointer}/signalMixing"), mixing),
+        }
+    }
+
+    fn header(method: &str, sections: Vec<MethodSection>) -> HeaderView {
+        HeaderView {
+            version: SchemaVersion::V2,
+            method: field("/generalSection/method", Some(method.to_string())),
+            method_specific: "/methodSpecific".to_string(),
+            method_sections: sections,
+            pixel_width: field("/generalSection/pixelWidth", Some(1.5)),
+            pixel_height: field("/generalSection/pixelHeight", None),
+            time_stamp: field(
+                "/generalSection/timeStamp",
+                Some("2025-11-13T14:25:30+01:00".to_string()),
+            ),
+        }
+    }
+
+    fn check(rule: &dyn LintRule, header: &HeaderView) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        rule.check(header, &mut violations);
+        violations
+    }
+
+    #[test]
+    fn test_method_must_match_populated_subsection() {
+        let sem = || sem_section(None, None, None);
+        assert!(check(&MethodSubsection, &header("sem", vec![sem()])).is_empty());
+
+        let violations = check(&MethodSubsection, &header("FIB", vec![sem()]));
+        assert_eq!(
+            violations,
+            vec![
+                Violation::new(
+                    "/generalSection/method",
+                    "method is 'FIB' but /methodSpecific has no 'focusedIonBeam' subsection"
+                ),
+                Violation::new(
+                    "/methodSpecific/scanningElectronMicroscopy",
+                    "'scanningElectronMicroscopy' is populated but method is 'FIB'"
+                ),
+            ]
+        );
+
+        let violations = check(&MethodSubsection, &header("TEM", vec![sem()]));
+        assert_eq!(
+            violations[0].message,
+            "method 'TEM' has no method specific subsection, expected one of SEM, FIB, Optical"
+        );
+    }
+
+    #[test]
+    fn test_signal_proportion_length_and_sum() {
+        let section = sem_section(
+            Some(vec!["SE", "BSE"]),
+            Some(vec![Some(0.25), Some(0.75)]),
+            Some(true),
+        );
+        assert!(check(&SignalProportion, &header("SEM", vec![section])).is_empty());
+
+        let section = sem_section(
+            Some(vec!["SE", "BSE", "EDS"]),
+            Some(vec![Some(0.5), Some(0.75)]),
+            Some(true),
+        );
+        let messages: Vec<String> = check(&SignalProportion, &header("SEM", vec![section]))
+            .into_iter()
+            .map(|violation| violation.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "signalProportion has 2 entries but signalTypes has 3",
+                "signalProportion sums to 1.25, expected 1",
+            ]
+        );
+
+        let unknown = sem_section(Some(vec!["SE", "BSE"]), Some(vec![Some(0.5), None]), None);
+        assert!(check(&SignalProportion, &header("SEM", vec![unknown])).is_empty());
+    }
+
+    #[test]
+    fn test_signal_mixing_required_for_multiple_types() {
+        let single = sem_section(Some(vec!["SE"]), None, Some(false));
+        assert!(check(&SignalMixing, &header("SEM", vec![single])).is_empty());
+
+        let mixed = sem_section(Some(vec!["SE", "BSE"]), None, None);
+        assert_eq!(
+            check(&SignalMixing, &header("SEM", vec![mixed])),
+            vec![Violation::new(
+                "/methodSpecific/scanningElectronMicroscopy/signalMixing",
+                "signalMixing is missing but signalTypes lists 2 signal types"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_pixel_size_and_timestamp() {
+        let mut view = header("SEM", Vec::new());
+        assert!(check(&PositivePixelSize, &view).is_empty());
+        assert!(check(&TimestampNotInFuture, &view).is_empty());
+
+        view.pixel_width.value = Some(0.0);
+        view.pixel_height.value = Some(-2.0);
+        view.time_stamp.value = Some("2999-01-01T00:00:00Z".to_string());
+        assert_eq!(check(&PositivePixelSize, &view).len(), 2);
+        assert_eq!(
+            check(&TimestampNotInFuture, &view),
+            vec![Violation::new(
+                "/generalSection/timeStamp",
+                "timeStamp 2999-01-01T00:00:00+00:00 is in the future"
+            )]
+        );
+    }
+}
@@ -1,10 +1,21 @@
 use clap::Parser;
 use colored::Colorize;
-use famdo::cli::{Cli, Commands};
+use famdo::cli::{Cli, Commands, LintArgs, SchemaCommands, VerifyArgs};
 use famdo::commands::delete::delete_metadata_field;
 use famdo::commands::edit::edit_famh_file;
-use famdo::commands::extract::extract_and_save_metadata;
-use famdo::commands::validate::validate_json;
+use famdo::commands::extract::{extract_and_save_famh_header, extract_and_save_metadata};
+use famdo::commands::lint::lint_famh_file;
+use famdo::commands::schema::{
+    clear_schema_cache, describe_schema_field, list_cached_schemas, refresh_schema_cache,
+    schema_cache_path, show_schema_fragment,
+};
+use famdo::commands::validate::{BatchOptions, FixReport, fix_files, validate_files};
+use famdo::commands::verify::verify_famh_file;
+use famdo::inputs::collect_inputs;
+use famdo::lint::Severity;
+use famdo::profile::Profile;
+use famdo::report::{BatchReport, FileResult, OutputFormat, render};
+use famdo::utils::default_jobs;
 
 #[tokio::main]
 async fn main() {
@@ -12,26 +23,93 @@ async fn main() {
 
     match cli.command {
         Commands::Validate(args) => {
-            match validate_json(&args.path, args.version, args.no_cache, args.strict).await {
-                Ok(is_valid) => {
-                    if is_valid {
-                        println!("{}", "FA Metadata Header is valid!".green());
-                    } else {
-                        println!("{}", "FA Metadata Header is invalid!".red());
+            let schema_config = args.schema.config();
+            let paths = match collect_inputs(&args.paths, &args.input_options()) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    eprintln!("Error validating JSON: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if args.fix {
+                match fix_files(
+                    &paths,
+                    &args.version,
+                    &schema_config,
+                    args.no_cache,
+                    args.dry_run,
+                )
+                .await
+                {
+                    Ok(reports) => {
+                        print_fixes(&reports, args.dry_run, args.format);
+                        if args.dry_run {
+                            if reports.iter().any(Result::is_err) {
+                                std::process::exit(1);
+                            }
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error fixing JSON: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let profiles = match args
+                .profile
+                .iter()
+                .map(|name| Profile::load(name, args.profile_dir.as_deref()))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(profiles) => profiles,
+                Err(e) => {
+                    eprintln!("Error loading profile: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if args.format == OutputFormat::Text {
+                for profile in &profiles {
+                    println!("Applying profile {}", profile.name.bold());
+                }
+            }
+            let options = BatchOptions {
+                no_cache: args.no_cache,
+                strict: args.strict,
+                ndjson: args.ndjson,
+                jobs: args.jobs.unwrap_or_else(default_jobs),
+                profiles,
+            };
+            match validate_files(&paths, &args.version, &schema_config, &options).await {
+                Ok(batch) => {
+                    print_validation_report(&batch, args.format);
+                    if !batch.is_valid() {
+                        std::process::exit(1);
                     }
                 }
                 Err(e) => {
                     eprintln!("Error validating JSON: {}", e);
+                    std::process::exit(1);
                 }
             }
         }
-        Commands::Extract(args) => match extract_and_save_metadata(&args.path, &args.out) {
-            Ok(_) => {
-                println!("Extracted image metadata and saved to {}", &args.out);
-            }
-            Err(e) => {
-                println!("Could not extract metadata: {e}")
-            }
+        Commands::Extract(args) => match args.famh {
+            Some(version) => match extract_and_save_famh_header(&args.path, &args.out, version) {
+                Ok(()) => {
+                    println!("Built FAMH {} header and saved to {}", version, &args.out);
+                }
+                Err(e) => {
+                    println!("Could not extract metadata: {e}")
+                }
+            },
+            None => match extract_and_save_metadata(&args.path, &args.out) {
+                Ok(_) => {
+                    println!("Extracted image metadata and saved to {}", &args.out);
+                }
+                Err(e) => {
+                    println!("Could not extract metadata: {e}")
+                }
+            },
         },
         Commands::Edit(args) => {
             match edit_famh_file(&args.path, args.field, args.value, &args.out, args.version) {
@@ -56,5 +134,320 @@ async fn main() {
                 }
             }
         }
+        Commands::Lint(args) => run_lint_command(&args),
+        Commands::Verify(args) => run_verify_command(&args),
+        Commands::Schema(args) => run_schema_command(args.command).await,
+    }
+}
+
+async fn run_schema_command(command: SchemaCommands) {
+    match command {
+        SchemaCommands::List(args) => match list_cached_schemas(&args.schema.config()) {
+            Ok(cached) if cached.is_empty() => println!("No cached schemas"),
+            Ok(cached) => {
+                for entry in cached {
+                    let modified = entry
+                        .modified
+                        .map(|date| date.to_string())
+                        .unwrap_or_else(|| "unknown".to_string());
+                    let checked = entry
+                        .checked
+                        .map(|date| format!(", checked {date}"))
+                        .unwrap_or_default();
+                    println!(
+                        "{} {} @ {} ({} fragments, fetched {}{})",
+                        entry.version.bold(),
+                        entry.source,
+                        entry.git_ref,
+                        entry.fragments,
+                        modified,
+                        checked
+                    );
+                    println!("    {}", entry.path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("Could not list schema cache: {}", e);
+                std::process::exit(1);
+            }
+        },
+        SchemaCommands::Path(args) => {
+            match schema_cache_path(&args.schema.config(), args.version.as_deref()) {
+                Ok(path) => println!("{}", path.display()),
+                Err(e) => {
+                    eprintln!("Could not determine schema cache path: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SchemaCommands::Refresh(args) => {
+            match refresh_schema_cache(&args.schema.config(), args.version.as_deref()).await {
+                Ok(paths) => {
+                    for path in paths {
+                        println!("Refreshed schema cache in {}", path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Schema refresh failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SchemaCommands::Clear(args) => {
+            match clear_schema_cache(&args.schema.config(), args.version.as_deref(), args.all) {
+                Ok(paths) if paths.is_empty() => println!("Schema cache is already empty"),
+                Ok(paths) => {
+                    for path in paths {
+                        println!("Removed {}", path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Could not clear schema cache: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SchemaCommands::Show(args) => {
+            match show_schema_fragment(
+                &args.schema.config(),
+                &args.version,
+                &args.section,
+                args.no_cache,
+            )
+            .await
+            {
+                Ok(fragment) => match serde_json::to_string_pretty(&fragment) {
+                    Ok(pretty) => println!("{pretty}"),
+                    Err(e) => {
+                        eprintln!("Could not format schema fragment: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Could not show schema fragment: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SchemaCommands::Describe(args) => {
+            match describe_schema_field(
+                &args.schema.config(),
+                &args.version,
+                &args.field,
+                args.no_cache,
+            )
+            .await
+            {
+                Ok(field) if args.tree => print!("{}", field.tree()),
+                Ok(field) => print!("{field}"),
+                Err(e) => {
+                    eprintln!("Could not describe field: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+fn print_validation_report(batch: &BatchReport, format: OutputFormat) {
+    let mut sources: Vec<String> = batch
+        .files
+        .iter()
+        .filter_map(|file| match file {
+            FileResult::Validated(report) => Some(format!(
+                "Using {} FAMH {} schema{}",
+                report.origin,
+                report.version,
+                if report.detected { " (detected)" } else { "" }
+            )),
+            FileResult::Failed { .. } => None,
+        })
+        .collect();
+    sources.sort_unstable();
+    sources.dedup();
+    let mut warnings: Vec<&String> = batch
+        .files
+        .iter()
+        .filter_map(|file| match file {
+            FileResult::Validated(report) => Some(&report.warnings),
+            FileResult::Failed { .. } => None,
+        })
+        .flatten()
+        .collect();
+    warnings.sort_unstable();
+    warnings.dedup();
+    for warning in warnings {
+        eprintln!("{}", warning.yellow());
+    }
+    // Machine readable formats keep stdout free for the report, which states
+    // the verdict itself.
+    if format != OutputFormat::Text {
+        for source in sources {
+            eprintln!("{source}");
+        }
+        print!("{}", render(format, batch));
+        return;
+    }
+
+    for source in sources {
+        println!("{source}");
+    }
+    match batch.files.as_slice() {
+        [FileResult::Failed { error, .. }] => eprintln!("Error validating JSON: {}", error),
+        [FileResult::Validated(report)] => {
+            print!("{}", render(format, batch));
+            if report.is_valid() {
+                println!("{}", "FA Metadata Header is valid!".green());
+            } else {
+                println!("{}", "FA Metadata Header is invalid!".red());
+            }
+        }
+        _ => print!("{}", render(format, batch)),
+    }
+}
+
+/// List the repairs of each file and, unless `dry_run` is set, the diff
+/// written. Machine readable formats keep stdout free for the report.
+fn print_fixes(reports: &[Result<FixReport, String>], dry_run: bool, format: OutputFormat) {
+    let mut out = String::new();
+    let mut fixes = 0;
+    let mut files = 0;
+    for report in reports {
+        let report = match report {
+            Ok(report) if report.fixes.is_empty() => continue,
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Fix failed: {}", e);
+                continue;
+            }
+        };
+        fixes += report.fixes.len();
+        files += 1;
+        out.push_str(&format!("{}\n", report.path.bold()));
+        for fix in &report.fixes {
+            out.push_str(&format!(
+                "  {}: {}[{}]: {}\n",
+                fix.instance_path,
+                "fix".blue(),
+                fix.kind,
+                fix.message
+            ));
+        }
+        if !dry_run {
+            out.push_str(&report.diff());
+        }
+    }
+    let summary = match (dry_run, fixes) {
+        (_, 0) => "Nothing to fix".to_string(),
+        (true, _) => format!("Would apply {fixes} fix(es) to {files} file(s)"),
+        (false, _) => format!("Applied {fixes} fix(es) to {files} file(s)"),
+    };
+    out.push_str(&format!("{}\n", summary.green()));
+    if format == OutputFormat::Text {
+        print!("{out}");
+    } else {
+        eprint!("{out}");
+    }
+}
+
+fn run_lint_command(args: &LintArgs) {
+    let linter = match args.linter() {
+        Ok(linter) => linter,
+        Err(e) => {
+            eprintln!("Lint failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if args.list_rules {
+        for rule in linter.rules() {
+            let state = if rule.enabled { "" } else { " (disabled)" };
+            println!(
+                "{:<20} {:<8} {}{}",
+                rule.id, rule.severity, rule.description, state
+            );
+        }
+        return;
+    }
+
+    let path = args.path.as_deref().unwrap_or_default();
+    let report = match lint_famh_file(path, args.version, &linter) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Lint failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for finding in &report.findings {
+        let severity = match finding.severity {
+            Severity::Error => finding.severity.as_str().red(),
+            Severity::Warning => finding.severity.as_str().yellow(),
+            Severity::Info => finding.severity.as_str().blue(),
+        };
+        println!(
+            "{}: {}[{}]: {}",
+            finding.pointer, severity, finding.rule, finding.message
+        );
+        if let Some(snippet) = report.snippets.get(&finding.pointer) {
+            print!("{}", snippet.render(&report.path));
+        }
+    }
+    if report.findings.is_empty() {
+        println!("{}", "No lint findings".green());
+    } else {
+        println!(
+            "{} error(s), {} warning(s), {} info",
+            report.count(Severity::Error),
+            report.count(Severity::Warning),
+            report.count(Severity::Info)
+        );
+    }
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+}
+
+fn run_verify_command(args: &VerifyArgs) {
+    let report = match verify_famh_file(&args.header, args.image.as_deref(), args.version) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Verify failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Comparing {} with {}",
+        report.header,
+        report.image.display()
+    );
+    for mismatch in &report.mismatches {
+        println!(
+            "{}: {}[{}]: {}",
+            mismatch.pointer,
+            "mismatch".red(),
+            mismatch.check,
+            mismatch.message
+        );
+    }
+    if report.compared.is_empty() {
+        println!(
+            "{}",
+            "The header describes none of the compared properties".yellow()
+        );
+    } else if report.is_consistent() {
+        println!(
+            "{}",
+            format!(
+                "Header matches the image ({} fields compared)",
+                report.compared.len()
+            )
+            .green()
+        );
+    } else {
+        println!(
+            "{} of {} compared field(s) disagree with the image",
+            report.mismatches.len(),
+            report.compared.len()
+        );
+        std::process::exit(1);
     }
 }
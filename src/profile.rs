@@ -0,0 +1,159 @@
+//! Validation profiles layered on top of a schema version.
+//!
+//! A profile makes a team's or customer's requirements stricter than the
+//! upstream schema. It is a JSON file with JSON Schema overlays for root-level
+//! sections, fields that must be present, and lists of allowed values:
+//!
+//! ```json
+//! {
+//!   "description": "Automotive failure analysis jobs",
+//!   "version": "v2",
+//!   "sections": {
+//!     "customerSpecific": {
+//!       "properties": { "orderId": { "pattern": "^FA-" } }
+//!     }
+//!   },
+//!   "required": ["customerSpecific.orderId", "customerSpecific.lotNumber"],
+//!   "allowed": { "customerSpecific.customFields.toolCalibrated": [true] }
+//! }
+//! ```
+//!
+//! Overlays are validated against the section value and may `$ref` the
+//! fragments of the schema version. Fields are dot separated paths or JSON
+//! pointers, as for `famdo edit`. Without `version`, a profile applies to
+//! every schema version.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::utils::field_to_json_pointer;
+
+/// A named set of requirements on top of a schema version.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Name reported with failures, by default the file name without `.json`.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Name of the schema version the profile is written for, e.g. `v2`.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// JSON Schema overlays by root-level section name.
+    #[serde(default)]
+    pub sections: BTreeMap<String, Value>,
+    /// Fields that must be present.
+    #[serde(default)]
+    pub required: Vec<String>,
+    /// Values allowed for a field, if it is present.
+    #[serde(default)]
+    pub allowed: BTreeMap<String, Vec<Value>>,
+}
+
+impl Profile {
+    /// Read a profile by name from `dir`, [`Profile::default_dir`] if `None`.
+    /// A `name` ending in `.json` or containing a path separator is read as a
+    /// file instead.
+    pub fn load(name: &str, dir: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Path::new(name);
+        if name.ends_with(".json") || path.components().count() > 1 {
+            return Self::read(path);
+        }
+        let dir = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => Self::default_dir()?,
+        };
+        let path = dir.join(format!("{name}.json"));
+        if !path.is_file() {
+            return Err(format!(
+                "Unknown profile '{}': {} does not exist",
+                name,
+                path.display()
+            )
+            .into());
+        }
+        Self::read(&path)
+    }
+
+    /// Read the profile file at `path`.
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read profile {}: {}", path.display(), e))?;
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid profile {}: {}", path.display(), e))?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self::from_value(&stem, value)
+            .map_err(|e| format!("Invalid profile {}: {}", path.display(), e).into())
+    }
+
+    /// A profile from its JSON document, named `name` unless it names itself.
+    pub fn from_value(name: &str, value: Value) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut profile: Profile = serde_json::from_value(value)?;
+        if profile.name.is_empty() {
+            profile.name = name.to_string();
+        }
+        if let Some((field, _)) = profile.allowed.iter().find(|(_, values)| values.is_empty()) {
+            return Err(format!("no values are allowed for {field}").into());
+        }
+        Ok(profile)
+    }
+
+    /// Where profiles are looked up by name: `famdo/profiles` in the user's
+    /// configuration directory.
+    pub fn default_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(dirs::config_dir()
+            .ok_or("Could not determine configuration directory")?
+            .join("famdo")
+            .join("profiles"))
+    }
+
+    /// JSON pointers of the required fields.
+    pub fn required_pointers(&self) -> Vec<String> {
+        self.required
+            .iter()
+            .map(|field| field_to_json_pointer(field))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_profiles_are_loaded_by_name_or_path() {
+        let profile = Profile::load("automotive", Some(Path::new("profiles"))).unwrap();
+        assert_eq!(profile.name, "automotive");
+        assert_eq!(profile.version.as_deref(), Some("v2"));
+        assert!(
+            profile
+                .required_pointers()
+                .contains(&"/customerSpecific/orderId".to_string())
+        );
+
+        let same = Profile::load("profiles/automotive.json", None).unwrap();
+        assert_eq!(same, profile);
+
+        let err = Profile::load("aerospace", Some(Path::new("profiles"))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile 'aerospace': profiles/aerospace.json does not exist"
+        );
+    }
+
+    #[test]
+    fn test_invalid_profiles_are_rejected() {
+        let err = Profile::from_value("p", json!({ "requried": ["a.b"] })).unwrap_err();
+        assert!(err.to_string().starts_with("unknown field `requried`"));
+
+        let err = Profile::from_value("p", json!({ "allowed": { "a.b": [] } })).unwrap_err();
+        assert_eq!(err.to_string(), "no values are allowed for a.b");
+    }
+}
@@ -0,0 +1,1127 @@
+//! Rendering of validation results.
+//!
+//! Every problem found in a document is flattened into an [`Issue`] carrying
+//! the section, the JSON pointer into the document, the failing schema keyword
+//! and its location in the schema. When the source text is known, issues also
+//! carry the line and column of the offending value. The issues are rendered as
+//! coloured text for people, or as JSON, SARIF 2.1.0 or JUnit XML for CI
+//! systems.
+
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::schema::{SchemaOrigin, UnknownField};
+use crate::source::{Snippet, SourceLocation, SourceMap};
+use crate::utils::encode_pointer_token;
+use crate::validator::{SchemaViolation, ValidationOutcome};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/Failure-Analysis-Metadata-Header/famdo";
+
+/// Output format of `famdo validate`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Coloured, human readable output
+    #[default]
+    Text,
+    /// A JSON document listing every issue
+    Json,
+    /// SARIF 2.1.0, e.g. for GitHub code scanning
+    Sarif,
+    /// JUnit XML with one test case per section
+    Junit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    /// SARIF `level` of the severity.
+    fn sarif_level(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "note",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Severity::Error),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "info" => Ok(Severity::Info),
+            _ => Err(format!(
+                "unknown severity '{s}', use error, warning or info"
+            )),
+        }
+    }
+}
+
+/// A single problem found in a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    /// Root-level section the issue belongs to.
+    pub section: String,
+    /// JSON pointer into the document, e.g. `/generalSection/imageWidth`.
+    pub instance_path: String,
+    pub keyword: String,
+    pub schema_path: String,
+    pub message: String,
+    pub severity: Severity,
+    /// Name of the profile the issue comes from, `None` for the schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Line and column of the value at `instance_path`.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
+    #[serde(skip)]
+    pub snippet: Option<Snippet>,
+}
+
+/// The result of validating one file: per-section status, unknown and
+/// missing sections, and the schema the file was checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub path: String,
+    /// Line of the record in an NDJSON stream at `path`.
+    pub record: Option<usize>,
+    /// Name of the schema version the file was validated against.
+    pub version: String,
+    pub origin: SchemaOrigin,
+    /// Whether the version was detected from the document.
+    pub detected: bool,
+    pub strict: bool,
+    pub outcome: ValidationOutcome,
+    /// Source snippets of the values issues point to, filled by
+    /// [`ValidationReport::locate`].
+    pub snippets: BTreeMap<String, Snippet>,
+    /// Warnings raised while loading the schema.
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.outcome.is_valid(self.strict)
+    }
+
+    /// The path, followed by the line of the record for NDJSON records, e.g.
+    /// `<stdin>:3`.
+    pub fn name(&self) -> String {
+        match self.record {
+            Some(line) => format!("{}:{}", self.path, line),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Record where the value of each issue is written in `source`. Only the
+    /// snippets of the issues are kept, not the source itself.
+    pub fn locate(&mut self, source: &SourceMap) {
+        self.snippets = self
+            .issues()
+            .into_iter()
+            .filter_map(|issue| {
+                // Unknown properties are pointed out by their key.
+                let snippet = if issue.keyword == "additionalProperties" {
+                    source.key_snippet(&issue.instance_path)
+                } else {
+                    source.snippet(&issue.instance_path)
+                };
+                Some((issue.instance_path, snippet?))
+            })
+            .collect();
+    }
+
+    /// All issues of the file: missing and unknown root-level sections first,
+    /// then the schema violations, unit violations and unknown fields of each
+    /// section.
+    pub fn issues(&self) -> Vec<Issue> {
+        let violations = self.outcome.sections.iter().flat_map(|section| {
+            let issue = |violation: &SchemaViolation, severity| Issue {
+                section: section.name.clone(),
+                instance_path: violation.instance_path.clone(),
+                keyword: violation.keyword.clone(),
+                schema_path: violation.schema_path.clone(),
+                message: violation.message.clone(),
+                severity,
+                profile: violation.profile.clone(),
+                location: None,
+                snippet: None,
+            };
+            let errors = section
+                .errors
+                .iter()
+                .map(move |violation| issue(violation, Severity::Error));
+            let units = self
+                .unit_violations(&section.name)
+                .map(move |violation| issue(violation, self.lenient_severity()));
+            // Unknown fields are only suspicious: the schemas allow them.
+            let unknown = self.unknown_fields(&section.name).map(|field| Issue {
+                section: section.name.clone(),
+                instance_path: field.instance_path.clone(),
+                keyword: "additionalProperties".to_string(),
+                schema_path: "#/additionalProperties".to_string(),
+                message: field.describe("field"),
+                severity: Severity::Warning,
+                profile: None,
+                location: None,
+                snippet: None,
+            });
+            errors.chain(units).chain(unknown)
+        });
+        self.root_issues()
+            .into_iter()
+            .chain(violations)
+            .map(|mut issue| {
+                issue.snippet = self.snippets.get(&issue.instance_path).cloned();
+                issue.location = issue.snippet.as_ref().map(|snippet| snippet.location);
+                issue
+            })
+            .collect()
+    }
+
+    /// Issues about the set of root-level sections.
+    fn root_issues(&self) -> Vec<Issue> {
+        let missing = self.outcome.missing_sections.iter().map(|name| Issue {
+            section: name.clone(),
+            instance_path: String::new(),
+            keyword: "required".to_string(),
+            schema_path: "#/required".to_string(),
+            message: format!("Missing required section \"{name}\""),
+            severity: Severity::Error,
+            profile: None,
+            location: None,
+            snippet: None,
+        });
+        let unknown = self.outcome.unknown_sections.iter().map(|name| {
+            let instance_path = format!("/{}", encode_pointer_token(name));
+            Issue {
+                section: name.clone(),
+                message: self.describe_unknown_section(name, &instance_path),
+                instance_path,
+                keyword: "additionalProperties".to_string(),
+                schema_path: "#/additionalProperties".to_string(),
+                severity: self.lenient_severity(),
+                profile: None,
+                location: None,
+                snippet: None,
+            }
+        });
+        missing.chain(unknown).collect()
+    }
+
+    /// Severity of issues that only fail validation in strict mode.
+    fn lenient_severity(&self) -> Severity {
+        if self.strict {
+            Severity::Error
+        } else {
+            Severity::Warning
+        }
+    }
+
+    /// Unit violations nested in `section`.
+    fn unit_violations(&self, section: &str) -> impl Iterator<Item = &SchemaViolation> {
+        let prefix = format!("/{}/", encode_pointer_token(section));
+        self.outcome
+            .unit_violations
+            .iter()
+            .filter(move |violation| violation.instance_path.starts_with(&prefix))
+    }
+
+    /// Unknown fields nested in `section`.
+    fn unknown_fields(&self, section: &str) -> impl Iterator<Item = &UnknownField> {
+        let prefix = format!("/{}/", encode_pointer_token(section));
+        self.outcome
+            .unknown_fields
+            .iter()
+            .filter(move |field| field.instance_path.starts_with(&prefix))
+    }
+
+    /// `Unknown root-level section "<name>"`, with the closest known section.
+    fn describe_unknown_section(&self, name: &str, instance_path: &str) -> String {
+        self.outcome
+            .unknown_fields
+            .iter()
+            .find(|field| field.instance_path == instance_path)
+            .map(|field| field.describe("root-level section"))
+            .unwrap_or_else(|| format!("Unknown root-level section \"{name}\""))
+    }
+}
+
+/// The result of validating one file of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileResult {
+    Validated(ValidationReport),
+    /// The file could not be read, parsed or validated. The path of an NDJSON
+    /// record names its line, e.g. `<stdin>:3`.
+    Failed {
+        path: String,
+        error: String,
+    },
+}
+
+impl FileResult {
+    pub fn path(&self) -> &str {
+        match self {
+            FileResult::Validated(report) => &report.path,
+            FileResult::Failed { path, .. } => path,
+        }
+    }
+
+    /// The path, with the line of NDJSON records.
+    pub fn name(&self) -> String {
+        match self {
+            FileResult::Validated(report) => report.name(),
+            FileResult::Failed { path, .. } => path.clone(),
+        }
+    }
+}
+
+/// The results of validating a batch of files, in input order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub files: Vec<FileResult>,
+}
+
+/// Number of valid, invalid and errored files of a batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BatchSummary {
+    pub valid: usize,
+    pub invalid: usize,
+    pub errored: usize,
+}
+
+impl BatchSummary {
+    pub fn total(&self) -> usize {
+        self.valid + self.invalid + self.errored
+    }
+}
+
+impl From<ValidationReport> for BatchReport {
+    fn from(report: ValidationReport) -> Self {
+        Self {
+            files: vec![FileResult::Validated(report)],
+        }
+    }
+}
+
+impl BatchReport {
+    /// Whether every file was validated and is valid.
+    pub fn is_valid(&self) -> bool {
+        let summary = self.summary();
+        summary.valid == summary.total()
+    }
+
+    pub fn summary(&self) -> BatchSummary {
+        let mut summary = BatchSummary::default();
+        for file in &self.files {
+            match file {
+                FileResult::Validated(report) if report.is_valid() => summary.valid += 1,
+                FileResult::Validated(_) => summary.invalid += 1,
+                FileResult::Failed { .. } => summary.errored += 1,
+            }
+        }
+        summary
+    }
+
+    /// The `limit` pointers with the most errors across all files, most
+    /// frequent first. Array indices are replaced by `*`, so the same field
+    /// of different array items is counted together.
+    pub fn most_frequent_failures(&self, limit: usize) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file in &self.files {
+            let FileResult::Validated(report) = file else {
+                continue;
+            };
+            for issue in report.issues() {
+                if issue.severity == Severity::Error {
+                    *counts.entry(failure_pointer(&issue)).or_default() += 1;
+                }
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(limit);
+        counts
+    }
+
+    fn reports(&self) -> impl Iterator<Item = &ValidationReport> {
+        self.files.iter().filter_map(|file| match file {
+            FileResult::Validated(report) => Some(report),
+            FileResult::Failed { .. } => None,
+        })
+    }
+
+    fn failures(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().filter_map(|file| match file {
+            FileResult::Failed { path, error } => Some((path.as_str(), error.as_str())),
+            FileResult::Validated(_) => None,
+        })
+    }
+}
+
+/// Pointer under which an issue is counted in the failure statistics.
+fn failure_pointer(issue: &Issue) -> String {
+    if issue.instance_path.is_empty() {
+        return format!("/{}", encode_pointer_token(&issue.section));
+    }
+    issue
+        .instance_path
+        .split('/')
+        .map(|token| {
+            if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) {
+                "*"
+            } else {
+                token
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Number of pointers listed in the summary of a batch.
+pub const MOST_FREQUENT_FAILURES: usize = 10;
+
+/// Render `batch` in `format`.
+pub fn render(format: OutputFormat, batch: &BatchReport) -> String {
+    match format {
+        OutputFormat::Text => render_text(batch),
+        OutputFormat::Json => to_pretty_json(&render_json(batch)),
+        OutputFormat::Sarif => to_pretty_json(&render_sarif(batch)),
+        OutputFormat::Junit => render_junit(batch),
+    }
+}
+
+fn to_pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values always serialize")
+}
+
+/// A single file is reported section by section. A batch lists the valid
+/// files in one line each and the problems of the others, followed by a
+/// summary.
+fn render_text(batch: &BatchReport) -> String {
+    let mut out = String::new();
+    if let [FileResult::Validated(report)] = batch.files.as_slice() {
+        render_text_report(&mut out, report, true);
+        return out;
+    }
+
+    for file in &batch.files {
+        match file {
+            FileResult::Validated(report) if report.is_valid() => {
+                let _ = writeln!(out, "{} {}", report.name(), "is valid".green());
+                for section in &report.outcome.sections {
+                    render_unknown_fields(&mut out, report, &section.name);
+                }
+            }
+            FileResult::Validated(report) => {
+                let _ = writeln!(out, "{} {}", report.name().bold(), "is invalid".red());
+                render_text_report(&mut out, report, false);
+            }
+            FileResult::Failed { path, error } => {
+                let _ = writeln!(out, "{} {}: {}", path.bold(), "failed".red(), error);
+            }
+        }
+    }
+
+    let summary = batch.summary();
+    let _ = writeln!(out, "\nSummary");
+    let _ = writeln!(out, "  {:<8} {:>7}", "Valid", summary.valid);
+    let _ = writeln!(out, "  {:<8} {:>7}", "Invalid", summary.invalid);
+    let _ = writeln!(out, "  {:<8} {:>7}", "Errored", summary.errored);
+    let _ = writeln!(out, "  {:<8} {:>7}", "Total", summary.total());
+
+    let failures = batch.most_frequent_failures(MOST_FREQUENT_FAILURES);
+    if !failures.is_empty() {
+        let _ = writeln!(out, "\nMost frequent failures");
+        for (pointer, count) in failures {
+            let _ = writeln!(out, "  {count:>7}  {pointer}");
+        }
+    }
+    out
+}
+
+fn render_text_report(out: &mut String, report: &ValidationReport, valid_sections: bool) {
+    let outcome = &report.outcome;
+    for name in &outcome.unknown_sections {
+        let instance_path = format!("/{}", encode_pointer_token(name));
+        let _ = writeln!(
+            out,
+            "{}",
+            report
+                .describe_unknown_section(name, &instance_path)
+                .yellow()
+        );
+    }
+    if !outcome.missing_sections.is_empty() {
+        let _ = writeln!(
+            out,
+            "Missing required sections: {}",
+            outcome.missing_sections.join(", ").bold()
+        );
+    }
+    for section in &outcome.sections {
+        if section.is_valid() {
+            if valid_sections {
+                let _ = writeln!(out, "{} {}", section.name, "section is valid".green());
+            }
+            render_unit_violations(out, report, &section.name);
+            render_unknown_fields(out, report, &section.name);
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "{} section - {} validation error(s):",
+            section.name,
+            section.errors.len()
+        );
+        for err in &section.errors {
+            let profile = err
+                .profile
+                .as_ref()
+                .map(|profile| format!(" [profile {profile}]").cyan().to_string())
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{}: {}{}",
+                err.instance_path.red(),
+                err.message,
+                profile
+            );
+            if let Some(snippet) = report.snippets.get(&err.instance_path) {
+                out.push_str(&snippet.render(&report.path));
+            }
+        }
+        render_unit_violations(out, report, &section.name);
+        render_unknown_fields(out, report, &section.name);
+    }
+}
+
+fn render_unit_violations(out: &mut String, report: &ValidationReport, section: &str) {
+    for violation in report.unit_violations(section) {
+        let path = match report.strict {
+            true => violation.instance_path.red(),
+            false => violation.instance_path.yellow(),
+        };
+        let _ = writeln!(out, "{}: {}", path, violation.message);
+        if let Some(snippet) = report.snippets.get(&violation.instance_path) {
+            out.push_str(&snippet.render(&report.path));
+        }
+    }
+}
+
+fn render_unknown_fields(out: &mut String, report: &ValidationReport, section: &str) {
+    for field in report.unknown_fields(section) {
+        let _ = writeln!(
+            out,
+            "{}: {}",
+            field.instance_path.yellow(),
+            field.describe("field")
+        );
+        if let Some(snippet) = report.snippets.get(&field.instance_path) {
+            out.push_str(&snippet.render(&report.path));
+        }
+    }
+}
+
+fn render_json(batch: &BatchReport) -> Value {
+    let files: Vec<Value> = batch
+        .files
+        .iter()
+        .map(|file| match file {
+            FileResult::Validated(report) => {
+                let mut file = json!({
+                    "path": report.path,
+                    "version": report.version,
+                    "valid": report.is_valid(),
+                    "issues": report.issues(),
+                });
+                if let Some(line) = report.record {
+                    file["record"] = json!(line);
+                }
+                file
+            }
+            FileResult::Failed { path, error } => json!({
+                "path": path,
+                "valid": false,
+                "error": error,
+            }),
+        })
+        .collect();
+    let failures: Vec<Value> = batch
+        .most_frequent_failures(MOST_FREQUENT_FAILURES)
+        .into_iter()
+        .map(|(pointer, count)| json!({ "pointer": pointer, "count": count }))
+        .collect();
+    json!({
+        "valid": batch.is_valid(),
+        "summary": batch.summary(),
+        "mostFrequentFailures": failures,
+        "files": files,
+    })
+}
+
+fn render_sarif(batch: &BatchReport) -> Value {
+    let issues: Vec<(&ValidationReport, Issue)> = batch
+        .reports()
+        .flat_map(|report| {
+            report
+                .issues()
+                .into_iter()
+                .map(move |issue| (report, issue))
+        })
+        .collect();
+    let rule_ids: Vec<&str> = issues
+        .iter()
+        .map(|(_, issue)| issue.keyword.as_str())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let rules: Vec<Value> = rule_ids
+        .iter()
+        .map(|id| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": format!("Schema keyword '{id}' is not satisfied") },
+            })
+        })
+        .collect();
+    let results: Vec<Value> = issues
+        .iter()
+        .map(|(report, issue)| {
+            let mut result = json!({
+                "ruleId": issue.keyword,
+                "ruleIndex": rule_ids.binary_search(&issue.keyword.as_str()).unwrap_or_default(),
+                "level": issue.severity.sarif_level(),
+                "message": { "text": issue.message },
+                "locations": [{
+                    "physicalLocation": sarif_physical_location(&report.path, issue.location),
+                    "logicalLocations": [{
+                        "fullyQualifiedName": issue.instance_path,
+                        "kind": "member",
+                    }],
+                }],
+                "properties": {
+                    "section": issue.section,
+                    "instancePath": issue.instance_path,
+                    "schemaPath": issue.schema_path,
+                    "schemaVersion": report.version,
+                },
+            });
+            if let Some(profile) = &issue.profile {
+                result["properties"]["profile"] = json!(profile);
+            }
+            result
+        })
+        .collect();
+    let notifications: Vec<Value> = batch
+        .failures()
+        .map(|(path, error)| {
+            json!({
+                "level": "error",
+                "message": { "text": error },
+                "locations": [{ "physicalLocation": { "artifactLocation": { "uri": path } } }],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "famdo",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": rules,
+                }
+            },
+            "invocations": [{
+                "executionSuccessful": notifications.is_empty(),
+                "toolExecutionNotifications": notifications,
+            }],
+            "results": results,
+        }],
+    })
+}
+
+fn sarif_physical_location(path: &str, location: Option<SourceLocation>) -> Value {
+    match location {
+        Some(location) => json!({
+            "artifactLocation": { "uri": path },
+            "region": { "startLine": location.line, "startColumn": location.column },
+        }),
+        None => json!({ "artifactLocation": { "uri": path } }),
+    }
+}
+
+/// A JUnit test case: a section of a file, or the file's root-level sections.
+struct TestCase {
+    name: String,
+    failures: Vec<Issue>,
+    warnings: Vec<Issue>,
+}
+
+fn test_cases(report: &ValidationReport) -> Vec<TestCase> {
+    let (failures, warnings) = report
+        .root_issues()
+        .into_iter()
+        .partition(|issue| issue.severity == Severity::Error);
+    let root = TestCase {
+        name: "root-level sections".to_string(),
+        failures,
+        warnings,
+    };
+    let issues = report.issues();
+    let sections = report.outcome.sections.iter().map(|section| {
+        let (failures, warnings) = issues
+            .iter()
+            .filter(|issue| issue.section == section.name)
+            .cloned()
+            .partition(|issue| issue.severity == Severity::Error);
+        TestCase {
+            name: section.name.clone(),
+            failures,
+            warnings,
+        }
+    });
+    std::iter::once(root).chain(sections).collect()
+}
+
+/// Files that could not be validated become a single test case with an
+/// `<error>`.
+fn render_junit(batch: &BatchReport) -> String {
+    let suites: Vec<(&FileResult, Vec<TestCase>)> = batch
+        .files
+        .iter()
+        .map(|file| match file {
+            FileResult::Validated(report) => (file, test_cases(report)),
+            FileResult::Failed { .. } => (file, Vec::new()),
+        })
+        .collect();
+    // (tests, failures, errors) of a suite
+    let count = |file: &FileResult, cases: &[TestCase]| match file {
+        FileResult::Validated(_) => {
+            let failures = cases
+                .iter()
+                .filter(|case| !case.failures.is_empty())
+                .count();
+            (cases.len(), failures, 0)
+        }
+        FileResult::Failed { .. } => (1, 0, 1),
+    };
+    let (tests, failures, errors) = suites
+        .iter()
+        .map(|(file, cases)| count(file, cases))
+        .fold((0, 0, 0), |acc, (t, f, e)| {
+            (acc.0 + t, acc.1 + f, acc.2 + e)
+        });
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"famdo validate\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\">"
+    );
+    for (file, cases) in &suites {
+        let (tests, failures, errors) = count(file, cases);
+        let path = escape_xml(&file.name());
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{path}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\">"
+        );
+        match file {
+            FileResult::Validated(report) => {
+                let _ = writeln!(
+                    out,
+                    "    <properties><property name=\"schemaVersion\" value=\"{}\"/></properties>",
+                    escape_xml(&report.version)
+                );
+            }
+            FileResult::Failed { error, .. } => {
+                let _ = writeln!(
+                    out,
+                    "    <testcase classname=\"{path}\" name=\"file\">\n      <error message=\"{}\"/>\n    </testcase>",
+                    escape_xml(error)
+                );
+            }
+        }
+        for case in cases {
+            let _ = write!(
+                out,
+                "    <testcase classname=\"{path}\" name=\"{}\"",
+                escape_xml(&case.name)
+            );
+            if case.failures.is_empty() && case.warnings.is_empty() {
+                out.push_str("/>\n");
+                continue;
+            }
+            out.push_str(">\n");
+            if !case.failures.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "      <failure type=\"schema\" message=\"{} validation error(s)\">{}</failure>",
+                    case.failures.len(),
+                    escape_xml(&describe_issues(&case.failures))
+                );
+            }
+            if !case.warnings.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "      <system-out>{}</system-out>",
+                    escape_xml(&describe_issues(&case.warnings))
+                );
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn describe_issues(issues: &[Issue]) -> String {
+    issues
+        .iter()
+        .map(|issue| {
+            let path = match issue.instance_path.as_str() {
+                "" => "/",
+                path => path,
+            };
+            let line = issue
+                .location
+                .map(|location| format!(" (line {}, column {})", location.line, location.column))
+                .unwrap_or_default();
+            let profile = issue
+                .profile
+                .as_ref()
+                .map(|profile| format!(", profile {profile}"))
+                .unwrap_or_default();
+            format!(
+                "{path}{line}: {} [{} at {}{profile}]",
+                issue.message, issue.keyword, issue.schema_path
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Suggestion;
+    use crate::validator::{SchemaViolation, SectionOutcome};
+
+    fn sample() -> ValidationReport {
+        ValidationReport {
+            path: "scans/a&b.json".to_string(),
+            record: None,
+            version: "v2".to_string(),
+            origin: SchemaOrigin::downloaded(),
+            detected: false,
+            strict: false,
+            snippets: BTreeMap::new(),
+            warnings: Vec::new(),
+            outcome: ValidationOutcome {
+                unknown_sections: vec!["General".to_string()],
+                unknown_fields: vec![
+                    UnknownField {
+                        instance_path: "/General".to_string(),
+                        suggestion: Some(Suggestion {
+                            key: "generalSection".to_string(),
+                            naming: None,
+                        }),
+                    },
+                    UnknownField {
+                        instance_path: "/generalSection/Pixel Width".to_string(),
+                        suggestion: Some(Suggestion {
+                            key: "pixelWidth".to_string(),
+                            naming: Some("v1"),
+                        }),
+                    },
+                ],
+                missing_sections: vec!["methodSpecific".to_string()],
+                sections: vec![
+                    SectionOutcome {
+                        name: "generalSection".to_string(),
+                        errors: vec![SchemaViolation {
+                            instance_path: "/generalSection/imageWidth".to_string(),
+                            keyword: "type".to_string(),
+                            schema_path: "generalSection.json#/properties/generalSection/properties/imageWidth/type".to_string(),
+                            message: "\"wide\" is not of type \"integer\"".to_string(),
+                            profile: None,
+                        }],
+                    },
+                    SectionOutcome {
+                        name: "toolSpecific".to_string(),
+                        errors: Vec::new(),
+                    },
+                ],
+                unit_violations: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_issues_cover_missing_unknown_and_violations() {
+        let issues = sample().issues();
+
+        assert_eq!(issues.len(), 4);
+        assert_eq!(issues[0].keyword, "required");
+        assert_eq!(issues[0].section, "methodSpecific");
+        assert_eq!(issues[1].keyword, "additionalProperties");
+        assert_eq!(issues[1].severity, Severity::Warning);
+        assert_eq!(
+            issues[1].message,
+            "Unknown root-level section \"General\", did you mean \"generalSection\"?"
+        );
+        assert_eq!(issues[2].section, "generalSection");
+        assert_eq!(issues[2].instance_path, "/generalSection/imageWidth");
+        assert_eq!(issues[3].section, "generalSection");
+        assert_eq!(issues[3].severity, Severity::Warning);
+        assert_eq!(
+            issues[3].message,
+            "Unknown field \"Pixel Width\" uses FAMH v1 naming, did you mean \"pixelWidth\"?"
+        );
+    }
+
+    #[test]
+    fn test_unit_violations_are_warnings_unless_strict() {
+        let mut report = sample();
+        report.outcome.unknown_sections.clear();
+        report.outcome.unknown_fields.clear();
+        report.outcome.missing_sections.clear();
+        report.outcome.sections[0].errors.clear();
+        report.outcome.unit_violations.push(SchemaViolation {
+            instance_path: "/toolSpecific/acceleratingVoltage/unit".to_string(),
+            keyword: "unit".to_string(),
+            schema_path: "units#/voltage".to_string(),
+            message: "\"mm\" is not a unit of voltage".to_string(),
+            profile: None,
+        });
+
+        let issues = report.issues();
+        assert_eq!(issues[0].section, "toolSpecific");
+        assert_eq!(issues[0].keyword, "unit");
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert!(report.is_valid());
+
+        report.strict = true;
+        assert_eq!(report.issues()[0].severity, Severity::Error);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_json_output_lists_issues_per_file() {
+        let output: Value =
+            serde_json::from_str(&render(OutputFormat::Json, &BatchReport::from(sample())))
+                .unwrap();
+
+        assert_eq!(output["valid"], false);
+        let issue = &output["files"][0]["issues"][2];
+        assert_eq!(issue["section"], "generalSection");
+        assert_eq!(issue["instancePath"], "/generalSection/imageWidth");
+        assert_eq!(issue["keyword"], "type");
+        assert_eq!(
+            issue["schemaPath"],
+            "generalSection.json#/properties/generalSection/properties/imageWidth/type"
+        );
+        assert_eq!(issue["severity"], "error");
+    }
+
+    #[test]
+    fn test_sarif_output_references_rules_and_locations() {
+        let output: Value =
+            serde_json::from_str(&render(OutputFormat::Sarif, &BatchReport::from(sample())))
+                .unwrap();
+
+        assert_eq!(output["version"], "2.1.0");
+        let run = &output["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        for result in results {
+            let index = result["ruleIndex"].as_u64().unwrap() as usize;
+            assert_eq!(rules[index]["id"], result["ruleId"]);
+        }
+        let result = &results[2];
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "scans/a&b.json"
+        );
+        assert_eq!(
+            result["locations"][0]["logicalLocations"][0]["fullyQualifiedName"],
+            "/generalSection/imageWidth"
+        );
+        assert_eq!(results[1]["level"], "warning");
+    }
+
+    #[test]
+    fn test_junit_output_has_a_test_case_per_section() {
+        let output = render(OutputFormat::Junit, &BatchReport::from(sample()));
+
+        assert!(output.contains(
+            r#"<testsuite name="scans/a&amp;b.json" tests="3" failures="2" errors="0">"#
+        ));
+        assert!(output.contains(r#"name="root-level sections">"#));
+        assert!(output.contains(r#"name="generalSection">"#));
+        assert!(output.contains(r#"name="toolSpecific"/>"#));
+        assert!(output.contains("&quot;wide&quot; is not of type &quot;integer&quot;"));
+        assert!(output.contains("<system-out>/General: Unknown root-level section"));
+        // Unknown fields are reported, but do not fail their section.
+        assert!(output.contains(r#"<failure type="schema" message="1 validation error(s)">"#));
+        assert!(output.contains("<system-out>/generalSection/Pixel Width: Unknown field"));
+    }
+
+    #[test]
+    fn test_located_issues_carry_line_and_column() {
+        let mut report = sample();
+        report.locate(&SourceMap::new(
+            "{\n  \"generalSection\": {\n    \"imageWidth\": \"wide\"\n  },\n  \"General\": {}\n}\n",
+        ));
+
+        let issues = report.issues();
+        // Missing sections are located at the root object.
+        assert_eq!(
+            issues[0].location,
+            Some(SourceLocation { line: 1, column: 1 })
+        );
+        assert_eq!(
+            issues[1].location,
+            Some(SourceLocation { line: 5, column: 3 })
+        );
+        assert_eq!(
+            issues[2].location,
+            Some(SourceLocation {
+                line: 3,
+                column: 19
+            })
+        );
+
+        let batch = BatchReport::from(report);
+        let text = render(OutputFormat::Text, &batch);
+        assert!(text.contains(" --> scans/a&b.json:3:19\n"));
+        assert!(text.contains("3 |     \"imageWidth\": \"wide\"\n  |                   ^^^^^^\n"));
+
+        let json: Value = serde_json::from_str(&render(OutputFormat::Json, &batch)).unwrap();
+        assert_eq!(json["files"][0]["issues"][2]["line"], 3);
+        assert_eq!(json["files"][0]["issues"][2]["column"], 19);
+
+        let sarif: Value = serde_json::from_str(&render(OutputFormat::Sarif, &batch)).unwrap();
+        let region = &sarif["runs"][0]["results"][2]["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 3);
+    }
+
+    fn batch() -> BatchReport {
+        let mut valid = sample();
+        valid.path = "scans/valid.json".to_string();
+        valid.outcome = ValidationOutcome::default();
+        let mut other = sample();
+        other.path = "scans/other.json".to_string();
+        let errors = &mut other.outcome.sections[0].errors;
+        errors[0].instance_path = "/generalSection/rois/3/x".to_string();
+        errors.push(SchemaViolation {
+            instance_path: "/generalSection/rois/7/x".to_string(),
+            ..errors[0].clone()
+        });
+        BatchReport {
+            files: vec![
+                FileResult::Validated(sample()),
+                FileResult::Validated(valid),
+                FileResult::Failed {
+                    path: "scans/broken.json".to_string(),
+                    error: "expected value at line 1 column 1".to_string(),
+                },
+                FileResult::Validated(other),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_batch_summary_counts_files_and_failures() {
+        let batch = batch();
+
+        assert_eq!(
+            batch.summary(),
+            BatchSummary {
+                valid: 1,
+                invalid: 2,
+                errored: 1
+            }
+        );
+        assert!(!batch.is_valid());
+        assert_eq!(
+            batch.most_frequent_failures(2),
+            vec![
+                ("/generalSection/rois/*/x".to_string(), 2),
+                ("/methodSpecific".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_output_includes_errored_files() {
+        let batch = batch();
+
+        let text = render(OutputFormat::Text, &batch);
+        assert!(text.contains("scans/valid.json"));
+        assert!(text.contains("expected value at line 1 column 1"));
+        assert!(text.contains("Most frequent failures"));
+
+        let json: Value = serde_json::from_str(&render(OutputFormat::Json, &batch)).unwrap();
+        assert_eq!(json["summary"]["errored"], 1);
+        assert_eq!(
+            json["files"][2]["error"],
+            "expected value at line 1 column 1"
+        );
+
+        let junit = render(OutputFormat::Junit, &batch);
+        assert!(junit.contains(r#"tests="1" failures="0" errors="1">"#));
+        assert!(junit.contains(r#"<error message="expected value at line 1 column 1"/>"#));
+    }
+}
@@ -1,12 +1,46 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use clap::ValueEnum;
+use futures_util::future::join_all;
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
 use serde_json::Value;
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs;
 
-const SCHEMA_BASE_URL: &str = "https://raw.githubusercontent.com/Failure-Analysis-Metadata-Header/fa-metadata-schema/refs/heads";
-
-#[derive(Debug, Clone, Copy, ValueEnum)]
+mod bundled;
+mod cache;
+mod describe;
+mod detect;
+mod fix;
+mod http;
+mod registry;
+mod resolve;
+mod unknown;
+
+use cache::FragmentMetadata;
+use resolve::FragmentResources;
+
+pub use bundled::{SNAPSHOT, Snapshot};
+pub use describe::{FieldDescription, FieldTree, UnitConstraint};
+pub use detect::{AUTO_VERSION, VersionSelection, detect_version, resolve_version_name};
+pub use fix::{Change, Fix, FixKind};
+pub use http::{DEFAULT_RETRIES, HttpClient, HttpConfig};
+pub use registry::{
+    DEFAULT_SCHEMA_REF, MANIFEST_FILE_NAME, SchemaManifest, SchemaRegistry, SectionSpec,
+    VersionSpec,
+};
+pub use unknown::{Suggestion, UnknownField, closest_key};
+
+pub const SCHEMA_BASE_URL: &str =
+    "https://raw.githubusercontent.com/Failure-Analysis-Metadata-Header/fa-metadata-schema";
+
+/// How long a cached schema is used before it is revalidated with the server.
+pub const DEFAULT_SCHEMA_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// FAMH versions with a typed model in `famh-model`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum SchemaVersion {
     #[value(name = "v1")]
     V1,
@@ -15,480 +49,569 @@ pub enum SchemaVersion {
 }
 
 impl SchemaVersion {
-    fn branch(&self) -> &'static str {
-        match self {
-            SchemaVersion::V1 => "master",
-            SchemaVersion::V2 => "master",
-        }
-    }
-
-    fn folder(&self) -> &'static str {
+    /// Name of the version in the [`SchemaRegistry`].
+    pub fn as_str(&self) -> &'static str {
         match self {
             SchemaVersion::V1 => "v1",
             SchemaVersion::V2 => "v2",
         }
     }
+}
 
-    fn cache_dir_name(&self) -> &'static str {
-        match self {
-            SchemaVersion::V1 => "v1",
-            SchemaVersion::V2 => "v2",
-        }
+impl AsRef<str> for SchemaVersion {
+    fn as_ref(&self) -> &str {
+        self.as_str()
     }
 }
 
-// Trait for schema type operations
-trait SchemaTypeTrait: Copy {
-    fn file_name(&self) -> &'static str;
-    fn label(&self) -> &'static str;
-    fn url_for(&self, version: SchemaVersion) -> String {
-        format!(
-            "{}/{}/schema/{}/{}",
-            SCHEMA_BASE_URL,
-            version.branch(),
-            version.folder(),
-            self.file_name()
-        )
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum V1SchemaType {
-    General,
-    Customer,
-    Tool,
-    Method,
-    DataEvaluation,
-    History,
+/// Where a loaded schema came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaSource {
+    Bundled,
+    Cached,
+    Downloaded,
+    Local,
 }
 
-impl SchemaTypeTrait for V1SchemaType {
-    fn file_name(&self) -> &'static str {
+impl fmt::Display for SchemaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            V1SchemaType::General => "General Section.json",
-            V1SchemaType::Customer => "Customer Section.json",
-            V1SchemaType::Tool => "Tool Specific.json",
-            V1SchemaType::Method => "Method Specific.json",
-            V1SchemaType::DataEvaluation => "Data Evaluation.json",
-            V1SchemaType::History => "History.json",
+            SchemaSource::Bundled => f.write_str("bundled"),
+            SchemaSource::Cached => f.write_str("cached"),
+            SchemaSource::Downloaded => f.write_str("downloaded"),
+            SchemaSource::Local => f.write_str("local"),
         }
     }
+}
 
-    fn label(&self) -> &'static str {
-        match self {
-            V1SchemaType::General => "General Section",
-            V1SchemaType::Customer => "Customer Section",
-            V1SchemaType::Tool => "Tool Specific",
-            V1SchemaType::Method => "Method Specific",
-            V1SchemaType::DataEvaluation => "Data Evaluation",
-            V1SchemaType::History => "History",
+/// Source and snapshot date of a loaded schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaOrigin {
+    pub source: SchemaSource,
+    pub snapshot_date: NaiveDate,
+}
+
+impl SchemaOrigin {
+    pub fn bundled(snapshot: &Snapshot) -> Self {
+        let snapshot_date = NaiveDate::parse_from_str(snapshot.date, "%Y-%m-%d")
+            .expect("bundled snapshot date must be a valid YYYY-MM-DD date");
+        Self {
+            source: SchemaSource::Bundled,
+            snapshot_date,
+        }
+    }
+
+    pub(crate) fn downloaded() -> Self {
+        Self {
+            source: SchemaSource::Downloaded,
+            snapshot_date: Local::now().date_naive(),
+        }
+    }
+
+    async fn from_file_time(source: SchemaSource, file_path: &Path) -> Self {
+        let modified = fs::metadata(file_path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(|_| Local::now());
+        Self {
+            source,
+            snapshot_date: modified.date_naive(),
         }
     }
 }
 
-impl V1SchemaType {
-    pub fn all() -> &'static [V1SchemaType] {
-        &[
-            V1SchemaType::General,
-            V1SchemaType::Customer,
-            V1SchemaType::Tool,
-            V1SchemaType::Method,
-            V1SchemaType::DataEvaluation,
-            V1SchemaType::History,
-        ]
+impl fmt::Display for SchemaOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (snapshot {})", self.source, self.snapshot_date)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum V2SchemaType {
-    General,
-    Customer,
-    Tool,
-    Method,
-    DataEvaluation,
-    History,
+/// Where schema fragments are loaded from.
+///
+/// By default the fragments are downloaded from the upstream
+/// `fa-metadata-schema` repository. A different base URL (e.g. a fork or
+/// mirror) and git ref can be configured, or a local directory can be used
+/// instead of the network altogether.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaConfig {
+    /// Base URL that is followed by `/<ref>/schema/<version>/<file>`.
+    pub base_url: String,
+    /// Branch, tag or commit to download. Defaults to the version's ref.
+    pub git_ref: Option<String>,
+    /// Directory containing the `v1`/`v2` schema folders, e.g. the `schema`
+    /// folder of a `fa-metadata-schema` checkout.
+    pub dir: Option<PathBuf>,
+    /// Age after which cached fragments are revalidated with the server.
+    pub ttl: Duration,
+    /// Cache root overriding the platform cache directory.
+    pub cache_dir: Option<PathBuf>,
+    /// Manifest adding or replacing schema versions, see [`SchemaRegistry`].
+    pub manifest: Option<PathBuf>,
+    /// Proxy, CA, timeout and retry settings for downloads.
+    pub http: HttpConfig,
 }
 
-impl SchemaTypeTrait for V2SchemaType {
-    fn file_name(&self) -> &'static str {
-        match self {
-            V2SchemaType::General => "generalSection.json",
-            V2SchemaType::Customer => "customerSection.json",
-            V2SchemaType::Tool => "toolSpecific.json",
-            V2SchemaType::Method => "methodSpecific.json",
-            V2SchemaType::DataEvaluation => "dataEvaluation.json",
-            V2SchemaType::History => "historySection.json",
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: SCHEMA_BASE_URL.to_string(),
+            git_ref: None,
+            dir: None,
+            ttl: DEFAULT_SCHEMA_TTL,
+            cache_dir: None,
+            manifest: None,
+            http: HttpConfig::default(),
         }
     }
+}
 
-    fn label(&self) -> &'static str {
-        match self {
-            V2SchemaType::General => "general",
-            V2SchemaType::Customer => "customer",
-            V2SchemaType::Tool => "tool",
-            V2SchemaType::Method => "method",
-            V2SchemaType::DataEvaluation => "data evaluation",
-            V2SchemaType::History => "history",
-        }
+impl SchemaConfig {
+    pub fn git_ref<'a>(&'a self, version: &'a VersionSpec) -> &'a str {
+        self.git_ref.as_deref().unwrap_or(version.git_ref())
     }
-}
 
-impl V2SchemaType {
-    pub fn all() -> &'static [V2SchemaType] {
-        &[
-            V2SchemaType::General,
-            V2SchemaType::Customer,
-            V2SchemaType::Tool,
-            V2SchemaType::Method,
-            V2SchemaType::DataEvaluation,
-            V2SchemaType::History,
-        ]
+    /// Whether `version` is downloaded from the upstream repository at the
+    /// default ref, the only source the bundled snapshot stands in for.
+    fn is_upstream_default(&self, version: &VersionSpec) -> bool {
+        self.base_url.trim_end_matches('/') == SCHEMA_BASE_URL
+            && self.git_ref(version) == DEFAULT_SCHEMA_REF
     }
-}
 
-impl fmt::Display for V1SchemaType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.label())
+    /// Resolve the folder holding the fragments of `version` inside `dir`.
+    ///
+    /// Accepts both the repository root and its `schema` folder.
+    fn local_dir(dir: &Path, version: &VersionSpec) -> PathBuf {
+        let nested = dir.join("schema").join(version.folder());
+        if nested.is_dir() {
+            nested
+        } else {
+            dir.join(version.folder())
+        }
     }
 }
 
-impl fmt::Display for V2SchemaType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.label())
-    }
+/// URL of the folder holding the fragments: `<base>/<ref>/schema/<folder>/`.
+pub fn fragment_base_url(config: &SchemaConfig, version: &VersionSpec) -> String {
+    format!(
+        "{}/{}/schema/{}/",
+        config.base_url.trim_end_matches('/'),
+        config.git_ref(version),
+        version.folder()
+    )
 }
 
-// V1-specific schema cache
-pub struct V1SchemaCache {
-    pub general: Value,
-    pub customer: Value,
-    pub tool: Value,
-    pub method: Value,
-    pub data_evaluation: Value,
-    pub history: Value,
+/// URL of a single fragment: `<base>/<ref>/schema/<folder>/<file>`.
+pub fn fragment_url(config: &SchemaConfig, version: &VersionSpec, section: &SectionSpec) -> String {
+    format!("{}{}", fragment_base_url(config, version), section.file)
 }
 
-impl V1SchemaCache {
-    async fn download_all(
-        version: SchemaVersion,
-        use_cache: bool,
+/// All fragments of a single schema version, in manifest order.
+#[derive(Debug, Clone)]
+pub struct SchemaCache {
+    version: VersionSpec,
+    fragments: Vec<Value>,
+    required: Vec<String>,
+    /// URL the fragments are registered under to resolve `$ref`s.
+    base_url: String,
+    pub origin: SchemaOrigin,
+    /// Problems that did not stop the schema from loading, e.g. a failed
+    /// download answered from the bundled snapshot.
+    warnings: Vec<String>,
+}
+
+impl SchemaCache {
+    /// Assemble a schema from `fragments`, one per section of `version`.
+    pub fn new(
+        version: VersionSpec,
+        fragments: Vec<Value>,
+        origin: SchemaOrigin,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        if use_cache && let Ok(cache) = Self::load_from_cache(version).await {
-            return Ok(cache);
+        if fragments.len() != version.sections.len() {
+            return Err(format!(
+                "Schema version {} has {} sections but {} fragments were given",
+                version.name,
+                version.sections.len(),
+                fragments.len()
+            )
+            .into());
         }
+        let required = version.required_sections(&fragments);
+        let base_url = fragment_base_url(&SchemaConfig::default(), &version);
+        Ok(Self {
+            version,
+            fragments,
+            required,
+            base_url,
+            origin,
+            warnings: Vec::new(),
+        })
+    }
 
-        let (general, customer, tool, method, data_evaluation, history) = tokio::join!(
-            download_and_parse_schema(V1SchemaType::General, version),
-            download_and_parse_schema(V1SchemaType::Customer, version),
-            download_and_parse_schema(V1SchemaType::Tool, version),
-            download_and_parse_schema(V1SchemaType::Method, version),
-            download_and_parse_schema(V1SchemaType::DataEvaluation, version),
-            download_and_parse_schema(V1SchemaType::History, version),
-        );
+    // Load all schemas of a version from the configured source
+    pub async fn download_all(
+        config: &SchemaConfig,
+        version: impl AsRef<str>,
+        use_cache: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = HttpClient::new(&config.http)?;
+        Self::download_all_with(config, &client, version, use_cache).await
+    }
 
-        let cache = V1SchemaCache {
-            general: general?,
-            customer: customer?,
-            tool: tool?,
-            method: method?,
-            data_evaluation: data_evaluation?,
-            history: history?,
-        };
+    /// [`SchemaCache::download_all`] with a client built by the caller, for
+    /// loading several versions with the same client.
+    pub async fn download_all_with(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: impl AsRef<str>,
+        use_cache: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let registry = SchemaRegistry::load(config)?;
+        let version = registry.get(version.as_ref())?.clone();
+        Self::load(config, client, version, use_cache).await
+    }
 
-        let _ = cache.save_to_cache(version).await;
+    /// Load the fragments of `version` from the local directory, the cache,
+    /// the network or the bundled snapshot, in that order. The snapshot is
+    /// only used for the default upstream URL and ref, and only if one is
+    /// bundled; otherwise a failed download is returned as an error.
+    ///
+    /// Wherever they come from, `$ref`s between the fragments resolve
+    /// against their URLs below the configured base URL and ref.
+    pub async fn load(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: VersionSpec,
+        use_cache: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let base_url = fragment_base_url(config, &version);
+        let mut cache = Self::load_fragments(config, client, version, use_cache).await?;
+        cache.base_url = base_url;
         Ok(cache)
     }
 
-    pub fn get(&self, schema_type: V1SchemaType) -> &Value {
-        match schema_type {
-            V1SchemaType::General => &self.general,
-            V1SchemaType::Customer => &self.customer,
-            V1SchemaType::Tool => &self.tool,
-            V1SchemaType::Method => &self.method,
-            V1SchemaType::DataEvaluation => &self.data_evaluation,
-            V1SchemaType::History => &self.history,
+    async fn load_fragments(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: VersionSpec,
+        use_cache: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(dir) = &config.dir {
+            let local_dir = SchemaConfig::local_dir(dir, &version);
+            let name = version.name.clone();
+            return Self::load_from_dir(version, &local_dir, SchemaSource::Local)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to load {} schema from {}: {}",
+                        name,
+                        local_dir.display(),
+                        e
+                    )
+                    .into()
+                });
         }
-    }
 
-    async fn load_from_cache(version: SchemaVersion) -> Result<Self, Box<dyn std::error::Error>> {
-        let cache_dir = get_cache_dir(version)?;
-
-        let general = load_schema_from_file(&cache_dir, V1SchemaType::General).await?;
-        let customer = load_schema_from_file(&cache_dir, V1SchemaType::Customer).await?;
-        let tool = load_schema_from_file(&cache_dir, V1SchemaType::Tool).await?;
-        let method = load_schema_from_file(&cache_dir, V1SchemaType::Method).await?;
-        let data_evaluation =
-            load_schema_from_file(&cache_dir, V1SchemaType::DataEvaluation).await?;
-        let history = load_schema_from_file(&cache_dir, V1SchemaType::History).await?;
-
-        Ok(V1SchemaCache {
-            general,
-            customer,
-            tool,
-            method,
-            data_evaluation,
-            history,
-        })
+        if use_cache && let Ok(cache) = Self::load_from_cache(config, client, &version).await {
+            return Ok(cache);
+        }
+
+        match Self::download(config, client, &version).await {
+            Ok(cache) => Ok(cache),
+            Err(err) if version.is_bundled() && config.is_upstream_default(&version) => {
+                let mut cache = Self::load_bundled(version)?;
+                cache.warnings.push(format!(
+                    "Schema download failed, falling back to the bundled snapshot: {err}"
+                ));
+                Ok(cache)
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    async fn save_to_cache(
-        &self,
-        version: SchemaVersion,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let cache_dir = get_cache_dir(version)?;
-        fs::create_dir_all(&cache_dir).await.ok();
-
-        save_schema_to_file(&cache_dir, V1SchemaType::General, &self.general).await?;
-        save_schema_to_file(&cache_dir, V1SchemaType::Customer, &self.customer).await?;
-        save_schema_to_file(&cache_dir, V1SchemaType::Tool, &self.tool).await?;
-        save_schema_to_file(&cache_dir, V1SchemaType::Method, &self.method).await?;
-        save_schema_to_file(
-            &cache_dir,
-            V1SchemaType::DataEvaluation,
-            &self.data_evaluation,
+    /// Download all fragments, bypassing the cache, and store them in the cache.
+    ///
+    /// Unlike [`SchemaCache::download_all`] this never falls back to the
+    /// bundled snapshot. Returns the cache directory that was written.
+    pub async fn refresh(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: impl AsRef<str>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if config.dir.is_some() {
+            return Err("A local schema directory is used, there is nothing to refresh".into());
+        }
+
+        let registry = SchemaRegistry::load(config)?;
+        let version = registry.get(version.as_ref())?;
+        let downloads = join_all(
+            version
+                .sections
+                .iter()
+                .map(|section| download_and_parse_schema(config, client, version, section)),
         )
-        .await?;
-        save_schema_to_file(&cache_dir, V1SchemaType::History, &self.history).await?;
+        .await;
+        let downloads = collect_downloads(version, downloads)?;
 
-        Ok(())
+        let cache_dir = get_cache_dir(config, version)?;
+        for (section, downloaded) in version.sections.iter().zip(&downloads) {
+            save_schema_to_file(&cache_dir, section, downloaded).await?;
+        }
+        Ok(cache_dir)
     }
-}
 
-// V2-specific schema cache
-pub struct V2SchemaCache {
-    pub general: Value,
-    pub customer: Value,
-    pub tool: Value,
-    pub method: Value,
-    pub data_evaluation: Value,
-    pub history: Value,
-}
-
-impl V2SchemaCache {
-    async fn download_all(
-        version: SchemaVersion,
-        use_cache: bool,
+    async fn download(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: &VersionSpec,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        if use_cache && let Ok(cache) = Self::load_from_cache(version).await {
-            return Ok(cache);
-        }
+        let fragments = join_all(
+            version
+                .sections
+                .iter()
+                .map(|section| download_and_cache_schema(config, client, version, section)),
+        )
+        .await;
+        let fragments = collect_downloads(version, fragments)?;
 
-        let (general, customer, tool, method, data_evaluation, history) = tokio::join!(
-            download_and_parse_schema(V2SchemaType::General, version),
-            download_and_parse_schema(V2SchemaType::Customer, version),
-            download_and_parse_schema(V2SchemaType::Tool, version),
-            download_and_parse_schema(V2SchemaType::Method, version),
-            download_and_parse_schema(V2SchemaType::DataEvaluation, version),
-            download_and_parse_schema(V2SchemaType::History, version),
-        );
+        Self::new(version.clone(), fragments, SchemaOrigin::downloaded())
+    }
+
+    /// Assemble `version` from the snapshot compiled into the binary.
+    pub fn load_bundled(version: VersionSpec) -> Result<Self, Box<dyn std::error::Error>> {
+        let snapshot = SNAPSHOT.ok_or("No schema snapshot is bundled")?;
+        let fragments = version
+            .sections
+            .iter()
+            .map(|section| parse_bundled_schema(&version, section))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(version, fragments, SchemaOrigin::bundled(&snapshot))
+    }
 
-        let cache = V2SchemaCache {
-            general: general?,
-            customer: customer?,
-            tool: tool?,
-            method: method?,
-            data_evaluation: data_evaluation?,
-            history: history?,
+    /// The built-in `version` assembled from the schema fragments in
+    /// `tests/schemas`.
+    #[cfg(test)]
+    pub(crate) fn fixture(version: &str) -> Self {
+        let version = SchemaRegistry::built_in().get(version).unwrap().clone();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/schemas")
+            .join(version.folder());
+        let fragments = version
+            .sections
+            .iter()
+            .map(|section| {
+                let text = std::fs::read_to_string(dir.join(&section.file)).unwrap();
+                serde_json::from_str(&text).unwrap()
+            })
+            .collect();
+        let origin = SchemaOrigin {
+            source: SchemaSource::Local,
+            snapshot_date: Local::now().date_naive(),
         };
+        Self::new(version, fragments, origin).unwrap()
+    }
 
-        let _ = cache.save_to_cache(version).await;
+    async fn load_from_cache(
+        config: &SchemaConfig,
+        client: &HttpClient,
+        version: &VersionSpec,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let cache_dir = get_cache_dir(config, version)?;
+        let cached = join_all(
+            version
+                .sections
+                .iter()
+                .map(|section| load_cached_schema(config, client, version, section, &cache_dir)),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let (origin, warning) = cached_origin(version, &cached);
+        let fragments = cached.into_iter().map(|fragment| fragment.schema).collect();
+        let mut cache = Self::new(version.clone(), fragments, origin)?;
+        cache.warnings.extend(warning);
         Ok(cache)
     }
 
-    pub fn get(&self, schema_type: V2SchemaType) -> &Value {
-        match schema_type {
-            V2SchemaType::General => &self.general,
-            V2SchemaType::Customer => &self.customer,
-            V2SchemaType::Tool => &self.tool,
-            V2SchemaType::Method => &self.method,
-            V2SchemaType::DataEvaluation => &self.data_evaluation,
-            V2SchemaType::History => &self.history,
+    async fn load_from_dir(
+        version: VersionSpec,
+        dir: &Path,
+        source: SchemaSource,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut fragments = Vec::with_capacity(version.sections.len());
+        for section in &version.sections {
+            fragments.push(load_schema_from_file(dir, section).await?);
         }
+        let origin =
+            SchemaOrigin::from_file_time(source, &dir.join(&version.sections[0].file)).await;
+        Self::new(version, fragments, origin)
     }
 
-    async fn load_from_cache(version: SchemaVersion) -> Result<Self, Box<dyn std::error::Error>> {
-        let cache_dir = get_cache_dir(version)?;
-
-        let general = load_schema_from_file(&cache_dir, V2SchemaType::General).await?;
-        let customer = load_schema_from_file(&cache_dir, V2SchemaType::Customer).await?;
-        let tool = load_schema_from_file(&cache_dir, V2SchemaType::Tool).await?;
-        let method = load_schema_from_file(&cache_dir, V2SchemaType::Method).await?;
-        let data_evaluation =
-            load_schema_from_file(&cache_dir, V2SchemaType::DataEvaluation).await?;
-        let history = load_schema_from_file(&cache_dir, V2SchemaType::History).await?;
-
-        Ok(V2SchemaCache {
-            general,
-            customer,
-            tool,
-            method,
-            data_evaluation,
-            history,
-        })
+    pub fn version(&self) -> &VersionSpec {
+        &self.version
     }
 
-    async fn save_to_cache(
-        &self,
-        version: SchemaVersion,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let cache_dir = get_cache_dir(version)?;
-        fs::create_dir_all(&cache_dir).await.ok();
-
-        save_schema_to_file(&cache_dir, V2SchemaType::General, &self.general).await?;
-        save_schema_to_file(&cache_dir, V2SchemaType::Customer, &self.customer).await?;
-        save_schema_to_file(&cache_dir, V2SchemaType::Tool, &self.tool).await?;
-        save_schema_to_file(&cache_dir, V2SchemaType::Method, &self.method).await?;
-        save_schema_to_file(
-            &cache_dir,
-            V2SchemaType::DataEvaluation,
-            &self.data_evaluation,
-        )
-        .await?;
-        save_schema_to_file(&cache_dir, V2SchemaType::History, &self.history).await?;
-
-        Ok(())
+    pub fn origin(&self) -> &SchemaOrigin {
+        &self.origin
     }
-}
 
-// Unified schema cache enum
-pub enum SchemaCache {
-    V1(V1SchemaCache),
-    V2(V2SchemaCache),
-}
-
-impl SchemaCache {
-    // Download all schemas from GitHub
-    pub async fn download_all(
-        version: SchemaVersion,
-        use_cache: bool,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        match version {
-            SchemaVersion::V1 => {
-                let cache = V1SchemaCache::download_all(version, use_cache).await?;
-                Ok(SchemaCache::V1(cache))
-            }
-            SchemaVersion::V2 => {
-                let cache = V2SchemaCache::download_all(version, use_cache).await?;
-                Ok(SchemaCache::V2(cache))
-            }
-        }
+    /// Warnings raised while loading the schema, for the caller to report.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
-    // Access schemas by field name (works across versions)
-    pub fn general(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.general,
-            SchemaCache::V2(cache) => &cache.general,
-        }
+    /// Fragment of the root-level section `name`, e.g. `methodSpecific`.
+    pub fn section(&self, name: &str) -> Option<&Value> {
+        self.version
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+            .map(|index| &self.fragments[index])
     }
 
-    pub fn customer(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.customer,
-            SchemaCache::V2(cache) => &cache.customer,
-        }
+    pub fn all_sections(&self) -> Vec<(&str, &Value)> {
+        self.version
+            .sections
+            .iter()
+            .map(|section| section.name.as_str())
+            .zip(&self.fragments)
+            .collect()
     }
 
-    pub fn tool(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.tool,
-            SchemaCache::V2(cache) => &cache.tool,
-        }
+    /// Look up a single fragment by its section name (`methodSpecific`), file
+    /// name (`methodSpecific.json`) or label (`method`).
+    pub fn find_fragment(&self, name: &str) -> Option<&Value> {
+        self.version
+            .sections
+            .iter()
+            .position(|section| section.matches(name))
+            .map(|index| &self.fragments[index])
     }
 
-    pub fn method(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.method,
-            SchemaCache::V2(cache) => &cache.method,
-        }
+    pub fn required_sections(&self) -> &[String] {
+        &self.required
     }
 
-    pub fn data_evaluation(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.data_evaluation,
-            SchemaCache::V2(cache) => &cache.data_evaluation,
-        }
+    /// Location of the schema of section `name` inside its fragment, e.g.
+    /// `generalSection.json#/properties/generalSection`.
+    pub fn section_location(&self, name: &str) -> Option<String> {
+        let section = self.version.section(name)?;
+        let pointer = resolve::section_pointer(self.section(name)?, name)?;
+        Some(format!("{}#{}", section.file, pointer))
     }
 
-    pub fn history(&self) -> &Value {
-        match self {
-            SchemaCache::V1(cache) => &cache.history,
-            SchemaCache::V2(cache) => &cache.history,
-        }
+    /// Compile a validator for the root-level section `name`.
+    ///
+    /// `$ref`s are resolved against the loaded fragments only, so compiling
+    /// never touches the network. Unresolvable references are reported with
+    /// the fragment and JSON pointer of the offending `$ref`.
+    pub fn section_validator(
+        &self,
+        name: &str,
+    ) -> Result<jsonschema::Validator, Box<dyn std::error::Error>> {
+        let section = self.version.section(name).ok_or_else(|| {
+            format!(
+                "Schema version {} has no section '{}'",
+                self.version.name, name
+            )
+        })?;
+        FragmentResources::new(&self.base_url, &self.version.sections, &self.fragments)
+            .section_validator(&section.file, name)
     }
 
-    pub fn all_sections(&self) -> Vec<(&'static str, &Value)> {
-        match self {
-            SchemaCache::V1(_) => vec![
-                ("General Section", self.general()),
-                ("Customer Section", self.customer()),
-                ("Tool Specific", self.tool()),
-                ("Method Specific", self.method()),
-                ("Data Evaluation", self.data_evaluation()),
-                ("History", self.history()),
-            ],
-            SchemaCache::V2(_) => vec![
-                ("generalSection", self.general()),
-                ("customerSpecific", self.customer()),
-                ("toolSpecific", self.tool()),
-                ("methodSpecific", self.method()),
-                ("dataEvaluation", self.data_evaluation()),
-                ("history", self.history()),
-            ],
-        }
+    /// Compile `schema`, e.g. a profile overlay, whose `$ref`s may point into
+    /// the loaded fragments.
+    pub fn overlay_validator(
+        &self,
+        schema: &Value,
+    ) -> Result<jsonschema::Validator, Box<dyn std::error::Error>> {
+        FragmentResources::new(&self.base_url, &self.version.sections, &self.fragments)
+            .validator(&self.base_url, schema)
     }
+}
+/// Body and HTTP validators of a downloaded fragment.
+struct Download {
+    text: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
 
-    pub fn required_sections(&self) -> &'static [&'static str] {
-        match self {
-            SchemaCache::V1(_) => &["General Section", "Method Specific"],
-            SchemaCache::V2(_) => &["generalSection", "methodSpecific"],
+/// Download a fragment, or return `None` if the server confirms that the
+/// copy described by `cached` is still current.
+async fn download_schema(
+    client: &HttpClient,
+    section: &SectionSpec,
+    url: &str,
+    cached: Option<&FragmentMetadata>,
+) -> Result<Option<Download>, Box<dyn std::error::Error>> {
+    let mut request = client.get(url);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
         }
     }
-}
-
-async fn download_schema<T: SchemaTypeTrait>(
-    schema_type: T,
-    version: SchemaVersion,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let url = schema_type.url_for(version);
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await.map_err(|e| {
+    let response = client.send(request).await.map_err(|e| {
         format!(
             "Failed to download {} schema from {}: {}",
-            schema_type.label(),
+            section.label(),
             url,
             e
         )
     })?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
     let response = response.error_for_status().map_err(|e| {
         format!(
             "{} schema endpoint {} returned an error response: {}",
-            schema_type.label(),
+            section.label(),
             url,
             e
         )
     })?;
-    response.text().await.map_err(|e| {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let text = response.text().await.map_err(|e| {
         format!(
             "Failed to read {} schema body from {}: {}",
-            schema_type.label(),
+            section.label(),
             url,
             e
         )
-        .into()
-    })
+    })?;
+    Ok(Some(Download {
+        text,
+        etag,
+        last_modified,
+    }))
 }
 
-fn parse_schema<T: SchemaTypeTrait>(
-    schema_type: T,
+fn parse_schema(
+    section: &SectionSpec,
     schema_text: &str,
-    version: SchemaVersion,
+    url: &str,
 ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    let url = schema_type.url_for(version);
     serde_json::from_str(schema_text).map_err(|e| {
         format!(
             "Failed to parse {} schema downloaded from {}: {}",
-            schema_type.label(),
+            section.label(),
             url,
             e
         )
@@ -496,167 +619,986 @@ fn parse_schema<T: SchemaTypeTrait>(
     })
 }
 
-async fn download_and_parse_schema<T: SchemaTypeTrait>(
-    schema_type: T,
-    version: SchemaVersion,
+fn parse_bundled_schema(
+    version: &VersionSpec,
+    section: &SectionSpec,
 ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    let schema_text = download_schema(schema_type, version).await?;
-    let schema = parse_schema(schema_type, &schema_text, version)?;
-    Ok(schema)
+    let text = version.bundled_text(section).ok_or_else(|| {
+        format!(
+            "No bundled {} {} schema available",
+            version.name,
+            section.label()
+        )
+    })?;
+    serde_json::from_str(text).map_err(|e| {
+        format!(
+            "Failed to parse bundled {} {} schema: {}",
+            version.name,
+            section.label(),
+            e
+        )
+        .into()
+    })
+}
+
+/// A freshly downloaded fragment together with its cache metadata.
+struct DownloadedSchema {
+    schema: Value,
+    text: String,
+    metadata: FragmentMetadata,
+}
+
+async fn download_and_parse_schema(
+    config: &SchemaConfig,
+    client: &HttpClient,
+    version: &VersionSpec,
+    section: &SectionSpec,
+) -> Result<DownloadedSchema, Box<dyn std::error::Error>> {
+    let url = fragment_url(config, version, section);
+    let download = download_schema(client, section, &url, None)
+        .await?
+        .ok_or_else(|| {
+            format!(
+                "{} schema endpoint {} returned no content",
+                section.label(),
+                url
+            )
+        })?;
+    let schema = parse_schema(section, &download.text, &url)?;
+    let metadata =
+        FragmentMetadata::new(&url, &download.text, download.etag, download.last_modified);
+    Ok(DownloadedSchema {
+        schema,
+        text: download.text,
+        metadata,
+    })
+}
+
+// Download a single schema and store it in the cache on a best effort basis
+async fn download_and_cache_schema(
+    config: &SchemaConfig,
+    client: &HttpClient,
+    version: &VersionSpec,
+    section: &SectionSpec,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let downloaded = download_and_parse_schema(config, client, version, section).await?;
+    if let Ok(cache_dir) = get_cache_dir(config, version) {
+        let _ = save_schema_to_file(&cache_dir, section, &downloaded).await;
+    }
+    Ok(downloaded.schema)
 }
 
-// Get the cache directory for schemas
-fn get_cache_dir(version: SchemaVersion) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let cache_dir = dirs::cache_dir()
+/// Collect the downloads of all fragments of `version`, reporting every
+/// fragment that failed rather than only the first one.
+fn collect_downloads<T>(
+    version: &VersionSpec,
+    results: Vec<Result<T, Box<dyn std::error::Error>>>,
+) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+    let total = results.len();
+    let mut downloads = Vec::with_capacity(total);
+    let mut failures = Vec::new();
+    for result in results {
+        match result {
+            Ok(download) => downloads.push(download),
+            Err(e) => failures.push(format!("  - {e}")),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(downloads);
+    }
+    Err(format!(
+        "Failed to download {} of {} {} schema fragments:\n{}",
+        failures.len(),
+        total,
+        version.name,
+        failures.join("\n")
+    )
+    .into())
+}
+
+/// A fragment loaded from the on-disk cache.
+struct CachedFragment {
+    schema: Value,
+    downloaded_at: DateTime<Utc>,
+    /// Why the stale fragment could not be revalidated, if it could not.
+    revalidation_error: Option<String>,
+}
+
+// Load a single schema from the cache, revalidating it with the server once
+// it is older than the configured TTL
+async fn load_cached_schema(
+    config: &SchemaConfig,
+    client: &HttpClient,
+    version: &VersionSpec,
+    section: &SectionSpec,
+    cache_dir: &Path,
+) -> Result<CachedFragment, Box<dyn std::error::Error>> {
+    let file_path = cache_dir.join(&section.file);
+    let text = fs::read_to_string(&file_path).await?;
+    let url = fragment_url(config, version, section);
+    let metadata = FragmentMetadata::load(&file_path)
+        .await
+        .filter(|metadata| metadata.url == url);
+    if let Some(metadata) = &metadata
+        && !metadata.matches(&text)
+    {
+        return Err(format!(
+            "Cached {} schema {} does not match its recorded checksum",
+            section.label(),
+            file_path.display()
+        )
+        .into());
+    }
+    let schema: Value = serde_json::from_str(&text)?;
+
+    let now = Utc::now();
+    if let Some(metadata) = &metadata
+        && metadata.is_fresh(config.ttl, now)
+    {
+        return Ok(CachedFragment {
+            schema,
+            downloaded_at: metadata.downloaded_at,
+            revalidation_error: None,
+        });
+    }
+
+    let revalidated = async {
+        match download_schema(client, section, &url, metadata.as_ref()).await? {
+            None => {
+                let mut metadata = metadata
+                    .clone()
+                    .unwrap_or_else(|| FragmentMetadata::new(&url, &text, None, None));
+                metadata.checked_at = now;
+                let _ = metadata.save(&file_path).await;
+                Ok::<_, Box<dyn std::error::Error>>(CachedFragment {
+                    schema: schema.clone(),
+                    downloaded_at: metadata.downloaded_at,
+                    revalidation_error: None,
+                })
+            }
+            Some(download) => {
+                let downloaded = DownloadedSchema {
+                    schema: parse_schema(section, &download.text, &url)?,
+                    metadata: FragmentMetadata::new(
+                        &url,
+                        &download.text,
+                        download.etag,
+                        download.last_modified,
+                    ),
+                    text: download.text,
+                };
+                let _ = save_schema_to_file(cache_dir, section, &downloaded).await;
+                Ok(CachedFragment {
+                    schema: downloaded.schema,
+                    downloaded_at: downloaded.metadata.downloaded_at,
+                    revalidation_error: None,
+                })
+            }
+        }
+    }
+    .await;
+
+    match revalidated {
+        Ok(cached) => Ok(cached),
+        Err(e) => {
+            let downloaded_at = match &metadata {
+                Some(metadata) => metadata.downloaded_at,
+                None => fs::metadata(&file_path)
+                    .await
+                    .and_then(|metadata| metadata.modified())
+                    .map(DateTime::<Utc>::from)
+                    .unwrap_or(now),
+            };
+            Ok(CachedFragment {
+                schema,
+                downloaded_at,
+                revalidation_error: Some(e.to_string()),
+            })
+        }
+    }
+}
+
+// Origin of a schema assembled from cached fragments, and a warning if stale
+// fragments had to be used because the server could not be reached.
+fn cached_origin(
+    version: &VersionSpec,
+    fragments: &[CachedFragment],
+) -> (SchemaOrigin, Option<String>) {
+    let oldest = fragments
+        .iter()
+        .map(|fragment| fragment.downloaded_at)
+        .min()
+        .unwrap_or_else(Utc::now);
+    let snapshot_date = DateTime::<Local>::from(oldest).date_naive();
+
+    let warning = fragments
+        .iter()
+        .find_map(|fragment| fragment.revalidation_error.as_ref())
+        .map(|error| {
+            format!(
+                "Could not revalidate the cached {} schema, using the stale copy from {}: {error}",
+                version.name, snapshot_date
+            )
+        });
+
+    let origin = SchemaOrigin {
+        source: SchemaSource::Cached,
+        snapshot_date,
+    };
+    (origin, warning)
+}
+
+/// Root directory holding all cached schemas.
+pub fn get_cache_root(config: &SchemaConfig) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(cache_dir) = &config.cache_dir {
+        return Ok(cache_dir.clone());
+    }
+    let cache_root = dirs::cache_dir()
         .ok_or("Could not determine cache directory")?
         .join("famdo")
-        .join("schemas")
-        .join(version.cache_dir_name());
+        .join("schemas");
+    Ok(cache_root)
+}
+
+/// Get the cache directory for schemas, keyed by source URL and git ref so
+/// that different sources never overwrite each other.
+pub fn get_cache_dir(
+    config: &SchemaConfig,
+    version: &VersionSpec,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let cache_dir = get_cache_root(config)?
+        .join(cache_key(config.base_url.trim_end_matches('/')))
+        .join(cache_key(config.git_ref(version)))
+        .join(cache_key(version.folder()));
     Ok(cache_dir)
 }
 
+/// Encode an arbitrary string into a single, collision free path component.
+///
+/// ASCII alphanumerics, `.` and `-` are kept, everything else is written as
+/// `_XX` hex bytes. The `https://` prefix is dropped to keep names readable.
+fn cache_key(raw: &str) -> String {
+    let raw = raw.strip_prefix("https://").unwrap_or(raw);
+    let mut key = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'-' {
+            key.push(byte as char);
+        } else {
+            key.push_str(&format!("_{byte:02X}"));
+        }
+    }
+    key
+}
+
+/// Reverse [`cache_key`] for display purposes.
+pub fn decode_cache_key(key: &str) -> String {
+    let mut bytes = Vec::with_capacity(key.len());
+    let mut rest = key.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'_'
+            && let Some(hex) = tail.get(..2)
+            && let Ok(decoded) = u8::from_str_radix(&String::from_utf8_lossy(hex), 16)
+        {
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
 // Load a single schema from cache file
-async fn load_schema_from_file<T: SchemaTypeTrait>(
+async fn load_schema_from_file(
     cache_dir: &Path,
-    schema_type: T,
+    section: &SectionSpec,
 ) -> Result<Value, Box<dyn std::error::Error>> {
-    let file_path = cache_dir.join(schema_type.file_name());
+    let file_path = cache_dir.join(&section.file);
     let content = fs::read_to_string(&file_path).await?;
     let schema = serde_json::from_str(&content)?;
     Ok(schema)
 }
 
-// Save a single schema to cache file
-async fn save_schema_to_file<T: SchemaTypeTrait>(
+// Save a single schema and its metadata to the cache
+async fn save_schema_to_file(
     cache_dir: &Path,
-    schema_type: T,
-    schema: &Value,
+    section: &SectionSpec,
+    downloaded: &DownloadedSchema,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file_path = cache_dir.join(schema_type.file_name());
-    let content = serde_json::to_string(schema)?;
-    fs::write(&file_path, content).await?;
+    fs::create_dir_all(cache_dir).await?;
+    let file_path = cache_dir.join(&section.file);
+    fs::write(&file_path, &downloaded.text).await?;
+    downloaded.metadata.save(&file_path).await?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::{Arc, Mutex};
+
+    const TEST_ETAG: &str = "\"snapshot-1\"";
+
+    /// Minimal HTTP server standing in for the schema repository. Every
+    /// fragment is served with [`TEST_ETAG`]; requests presenting that ETag get
+    /// `304 Not Modified`. Returns the base URL and the received request heads.
+    fn spawn_schema_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        spawn_flaky_schema_server(|_, _| None)
+    }
+
+    /// Like [`spawn_schema_server`], but answers with the status returned by
+    /// `failure` for the path and the number of earlier requests for it.
+    fn spawn_flaky_schema_server(
+        failure: fn(&str, usize) -> Option<u16>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::<String>::new()));
+        let log = Arc::clone(&requests);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+
+                let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let not_modified = head.lines().any(|line| {
+                    line.to_ascii_lowercase().starts_with("if-none-match:")
+                        && line.contains(TEST_ETAG)
+                });
+                let earlier = {
+                    let mut log = log.lock().unwrap();
+                    let earlier = log
+                        .iter()
+                        .filter(|request| request.split_whitespace().nth(1) == Some(path.as_str()))
+                        .count();
+                    log.push(head);
+                    earlier
+                };
+
+                let response = if let Some(status) = failure(&path, earlier) {
+                    format!(
+                        "HTTP/1.1 {status} Failure\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                } else if not_modified {
+                    format!(
+                        "HTTP/1.1 304 Not Modified\r\nETag: {TEST_ETAG}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                } else {
+                    let body = format!(r#"{{"title": "{}"}}"#, path.rsplit('/').next().unwrap());
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: {TEST_ETAG}\r\nLast-Modified: Sat, 17 Oct 2026 07:28:00 GMT\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        (base_url, requests)
+    }
+
+    fn test_config(base_url: &str, cache_dir: &Path, ttl: Duration) -> SchemaConfig {
+        SchemaConfig {
+            base_url: base_url.to_string(),
+            ttl,
+            cache_dir: Some(cache_dir.to_path_buf()),
+            http: HttpConfig {
+                retry_backoff: Duration::from_millis(10),
+                ..HttpConfig::default()
+            },
+            ..SchemaConfig::default()
+        }
+    }
+
+    fn built_in_version(name: &str) -> VersionSpec {
+        SchemaRegistry::built_in().get(name).unwrap().clone()
+    }
 
     #[test]
-    fn test_schema_version_branch() {
-        assert_eq!(SchemaVersion::V1.branch(), "master");
-        assert_eq!(SchemaVersion::V2.branch(), "master");
+    fn test_schema_version_names_match_registry() {
+        let registry = SchemaRegistry::built_in();
+        assert_eq!(registry.get(SchemaVersion::V1.as_str()).unwrap().name, "v1");
+        assert_eq!(registry.get(SchemaVersion::V2.as_str()).unwrap().name, "v2");
+        assert_eq!(SchemaVersion::V2.to_string(), "v2");
     }
 
     #[test]
-    fn test_schema_version_folder() {
-        assert_eq!(SchemaVersion::V1.folder(), "v1");
-        assert_eq!(SchemaVersion::V2.folder(), "v2");
+    fn test_built_in_versions_use_master_ref_and_folder() {
+        for name in ["v1", "v2"] {
+            let version = built_in_version(name);
+            assert_eq!(version.git_ref(), "master");
+            assert_eq!(version.folder(), name);
+        }
     }
 
     #[test]
-    fn test_v1_schema_type_file_name() {
-        assert_eq!(V1SchemaType::General.file_name(), "General Section.json");
-        assert_eq!(V1SchemaType::Customer.file_name(), "Customer Section.json");
-        assert_eq!(V1SchemaType::Tool.file_name(), "Tool Specific.json");
-        assert_eq!(V1SchemaType::Method.file_name(), "Method Specific.json");
+    fn test_v1_section_file_names() {
+        let version = built_in_version("v1");
+        let files: Vec<&str> = version
+            .sections
+            .iter()
+            .map(|section| section.file.as_str())
+            .collect();
         assert_eq!(
-            V1SchemaType::DataEvaluation.file_name(),
-            "Data Evaluation.json"
+            files,
+            vec![
+                "General Section.json",
+                "Customer Section.json",
+                "Tool Specific.json",
+                "Method Specific.json",
+                "Data Evaluation.json",
+                "History.json",
+            ]
         );
-        assert_eq!(V1SchemaType::History.file_name(), "History.json");
     }
 
     #[test]
-    fn test_v2_schema_type_file_name() {
-        assert_eq!(V2SchemaType::General.file_name(), "generalSection.json");
-        assert_eq!(V2SchemaType::Customer.file_name(), "customerSection.json");
-        assert_eq!(V2SchemaType::Tool.file_name(), "toolSpecific.json");
-        assert_eq!(V2SchemaType::Method.file_name(), "methodSpecific.json");
+    fn test_v2_section_file_names() {
+        let version = built_in_version("v2");
+        let files: Vec<&str> = version
+            .sections
+            .iter()
+            .map(|section| section.file.as_str())
+            .collect();
         assert_eq!(
-            V2SchemaType::DataEvaluation.file_name(),
-            "dataEvaluation.json"
+            files,
+            vec![
+                "generalSection.json",
+                "customerSection.json",
+                "toolSpecific.json",
+                "methodSpecific.json",
+                "dataEvaluation.json",
+                "historySection.json",
+            ]
         );
-        assert_eq!(V2SchemaType::History.file_name(), "historySection.json");
     }
 
     #[test]
-    fn test_v1_schema_type_label() {
-        assert_eq!(V1SchemaType::General.label(), "General Section");
-        assert_eq!(V1SchemaType::Customer.label(), "Customer Section");
-        assert_eq!(V1SchemaType::Tool.label(), "Tool Specific");
-        assert_eq!(V1SchemaType::Method.label(), "Method Specific");
-        assert_eq!(V1SchemaType::DataEvaluation.label(), "Data Evaluation");
-        assert_eq!(V1SchemaType::History.label(), "History");
+    fn test_v1_section_labels() {
+        let version = built_in_version("v1");
+        let labels: Vec<&str> = version.sections.iter().map(SectionSpec::label).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "General Section",
+                "Customer Section",
+                "Tool Specific",
+                "Method Specific",
+                "Data Evaluation",
+                "History",
+            ]
+        );
     }
 
     #[test]
-    fn test_v2_schema_type_label() {
-        assert_eq!(V2SchemaType::General.label(), "general");
-        assert_eq!(V2SchemaType::Customer.label(), "customer");
-        assert_eq!(V2SchemaType::Tool.label(), "tool");
-        assert_eq!(V2SchemaType::Method.label(), "method");
-        assert_eq!(V2SchemaType::DataEvaluation.label(), "data evaluation");
-        assert_eq!(V2SchemaType::History.label(), "history");
+    fn test_v2_section_labels() {
+        let version = built_in_version("v2");
+        let labels: Vec<&str> = version.sections.iter().map(SectionSpec::label).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "general",
+                "customer",
+                "tool",
+                "method",
+                "data evaluation",
+                "history",
+            ]
+        );
     }
 
     #[test]
-    fn test_schema_type_url_for() {
-        let url = V2SchemaType::General.url_for(SchemaVersion::V2);
+    fn test_fragment_url() {
+        let version = built_in_version("v2");
+        let url = fragment_url(&SchemaConfig::default(), &version, &version.sections[0]);
         assert!(url.contains("master"));
         assert!(url.contains("v2"));
         assert!(url.contains("generalSection.json"));
         assert!(url.starts_with(SCHEMA_BASE_URL));
     }
 
+    #[test]
+    fn test_fragment_url_custom_source_and_ref() {
+        let config = SchemaConfig {
+            base_url: "https://example.com/fork/".to_string(),
+            git_ref: Some("feature/units".to_string()),
+            ..SchemaConfig::default()
+        };
+        let version = built_in_version("v1");
+        assert_eq!(
+            fragment_url(&config, &version, &version.sections[0]),
+            "https://example.com/fork/feature/units/schema/v1/General Section.json"
+        );
+    }
+
+    #[test]
+    fn test_cache_dir_is_keyed_by_source_and_ref() {
+        let version = built_in_version("v2");
+        let default_dir = get_cache_dir(&SchemaConfig::default(), &version).unwrap();
+        let fork_dir = get_cache_dir(
+            &SchemaConfig {
+                base_url: "https://example.com/fork".to_string(),
+                ..SchemaConfig::default()
+            },
+            &version,
+        )
+        .unwrap();
+        let ref_dir = get_cache_dir(
+            &SchemaConfig {
+                git_ref: Some("v2.1".to_string()),
+                ..SchemaConfig::default()
+            },
+            &version,
+        )
+        .unwrap();
+
+        assert_ne!(default_dir, fork_dir);
+        assert_ne!(default_dir, ref_dir);
+        assert!(default_dir.ends_with("master/v2"));
+    }
+
+    #[test]
+    fn test_cache_key_is_a_single_unambiguous_path_component() {
+        assert_eq!(cache_key("feature/units"), "feature_2Funits");
+        assert_eq!(cache_key("https://example.com"), "example.com");
+        assert_ne!(cache_key("a/b"), cache_key("a_b"));
+    }
+
+    #[test]
+    fn test_decode_cache_key_round_trips() {
+        for raw in ["feature/units", "a_b", "example.com/fork", "ümlaut"] {
+            assert_eq!(decode_cache_key(&cache_key(raw)), raw);
+        }
+    }
+
+    #[test]
+    fn test_find_fragment_by_section_file_or_label() {
+        let cache = SchemaCache::fixture("v2");
+        let method = cache.section("methodSpecific");
+        assert!(method.is_some());
+
+        assert_eq!(cache.find_fragment("methodSpecific"), method);
+        assert_eq!(cache.find_fragment("methodSpecific.json"), method);
+        assert_eq!(cache.find_fragment("method"), method);
+        assert_eq!(
+            cache.find_fragment("customerSpecific"),
+            cache.section("customerSpecific")
+        );
+        assert!(cache.find_fragment("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_download_all_reads_local_schema_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let v2_dir = dir.path().join("schema").join("v2");
+        std::fs::create_dir_all(&v2_dir).unwrap();
+        for section in &built_in_version("v2").sections {
+            std::fs::write(
+                v2_dir.join(&section.file),
+                format!(r#"{{"title": "{}"}}"#, section.label()),
+            )
+            .unwrap();
+        }
+
+        let config = SchemaConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..SchemaConfig::default()
+        };
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+
+        assert_eq!(cache.origin().source, SchemaSource::Local);
+        assert_eq!(
+            cache.section("methodSpecific"),
+            Some(&serde_json::json!({"title": "method"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_all_reports_missing_local_schema_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = SchemaConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..SchemaConfig::default()
+        };
+
+        let result = SchemaCache::download_all(&config, SchemaVersion::V1, true).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_download_all_reports_unknown_version() {
+        let result = SchemaCache::download_all(&SchemaConfig::default(), "v9", true).await;
+        assert!(result.unwrap_err().to_string().contains("Known versions"));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_version_loads_from_local_schema_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let variant_dir = dir.path().join("acme");
+        std::fs::create_dir_all(&variant_dir).unwrap();
+        std::fs::write(
+            variant_dir.join("general.json"),
+            r#"{"required": ["general"], "properties": {"general": {"type": "object"}}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            variant_dir.join("lab.json"),
+            r#"{"properties": {"lab": {"type": "object"}}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(MANIFEST_FILE_NAME),
+            r#"{"versions": [{"name": "acme", "sections": [
+                {"name": "general", "file": "general.json"},
+                {"name": "lab", "file": "lab.json"}
+            ]}]}"#,
+        )
+        .unwrap();
+
+        let config = SchemaConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..SchemaConfig::default()
+        };
+        let cache = SchemaCache::download_all(&config, "acme", true)
+            .await
+            .unwrap();
+
+        let sections: Vec<&str> = cache.all_sections().iter().map(|(name, _)| *name).collect();
+        assert_eq!(sections, vec!["general", "lab"]);
+        assert_eq!(cache.required_sections(), ["general"]);
+    }
+
     #[test]
     fn test_parse_schema_valid() {
         let schema_text = r#"{"$schema": "http://json-schema.org/draft-07/schema#"}"#;
-        let result = parse_schema(V2SchemaType::General, schema_text, SchemaVersion::V2);
+        let version = built_in_version("v2");
+        let result = parse_schema(&version.sections[0], schema_text, SCHEMA_BASE_URL);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_parse_schema_invalid() {
         let schema_text = "not valid json";
-        let result = parse_schema(V2SchemaType::General, schema_text, SchemaVersion::V2);
+        let version = built_in_version("v2");
+        let result = parse_schema(&version.sections[0], schema_text, SCHEMA_BASE_URL);
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_v1_schema_cache_get() {
-        let cache = V1SchemaCache {
-            general: serde_json::json!({"type": "general"}),
-            customer: serde_json::json!({"type": "customer"}),
-            tool: serde_json::json!({"type": "tool"}),
-            method: serde_json::json!({"type": "method"}),
-            data_evaluation: serde_json::json!({"type": "data_evaluation"}),
-            history: serde_json::json!({"type": "history"}),
-        };
+    fn test_schema_cache_section() {
+        let cache = SchemaCache::new(
+            built_in_version("v1"),
+            vec![
+                serde_json::json!({"type": "general"}),
+                serde_json::json!({"type": "customer"}),
+                serde_json::json!({"type": "tool"}),
+                serde_json::json!({"type": "method"}),
+                serde_json::json!({"type": "data_evaluation"}),
+                serde_json::json!({"type": "history"}),
+            ],
+            SchemaOrigin::downloaded(),
+        )
+        .unwrap();
 
         assert_eq!(
-            cache.get(V1SchemaType::General),
-            &serde_json::json!({"type": "general"})
+            cache.section("General Section"),
+            Some(&serde_json::json!({"type": "general"}))
+        );
+        assert_eq!(
+            cache.section("Customer Section"),
+            Some(&serde_json::json!({"type": "customer"}))
+        );
+        assert!(cache.section("customerSpecific").is_none());
+    }
+
+    #[test]
+    fn test_schema_cache_rejects_fragment_count_mismatch() {
+        let result = SchemaCache::new(
+            built_in_version("v1"),
+            vec![serde_json::json!({})],
+            SchemaOrigin::downloaded(),
         );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_fixture_schemas_define_all_sections() {
+        for version in SchemaRegistry::built_in().versions() {
+            let cache = SchemaCache::fixture(&version.name);
+
+            for (section_name, schema) in cache.all_sections() {
+                assert!(
+                    schema["properties"].get(section_name).is_some(),
+                    "{} fixture schema is missing section {section_name}",
+                    version.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_built_in_versions_require_general_and_method_sections() {
+        let v1 = SchemaCache::fixture("v1");
         assert_eq!(
-            cache.get(V1SchemaType::Customer),
-            &serde_json::json!({"type": "customer"})
+            v1.required_sections(),
+            ["General Section", "Method Specific"]
         );
+        let v2 = SchemaCache::fixture("v2");
+        assert_eq!(v2.required_sections(), ["generalSection", "methodSpecific"]);
     }
 
     #[test]
-    fn test_unified_schema_cache_accessors() {
-        let v1_cache = V1SchemaCache {
-            general: serde_json::json!({"type": "general"}),
-            customer: serde_json::json!({"type": "customer"}),
-            tool: serde_json::json!({"type": "tool"}),
-            method: serde_json::json!({"type": "method"}),
-            data_evaluation: serde_json::json!({"type": "data_evaluation"}),
-            history: serde_json::json!({"type": "history"}),
+    fn test_schema_origin_display_names_source_and_date() {
+        let origin = SchemaOrigin {
+            source: SchemaSource::Cached,
+            snapshot_date: NaiveDate::from_ymd_opt(2025, 11, 13).unwrap(),
         };
+        assert_eq!(origin.to_string(), "cached (snapshot 2025-11-13)");
+    }
 
-        let cache = SchemaCache::V1(v1_cache);
-        assert_eq!(cache.general(), &serde_json::json!({"type": "general"}));
-        assert_eq!(cache.customer(), &serde_json::json!({"type": "customer"}));
+    #[test]
+    fn test_cache_root_can_be_overridden() {
+        let config = SchemaConfig {
+            cache_dir: Some(PathBuf::from("/tmp/famdo-cache")),
+            ..SchemaConfig::default()
+        };
+        assert_eq!(
+            get_cache_root(&config).unwrap(),
+            PathBuf::from("/tmp/famdo-cache")
+        );
+        assert!(
+            get_cache_dir(&config, &built_in_version("v1"))
+                .unwrap()
+                .starts_with("/tmp/famdo-cache")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_stores_fragment_metadata_and_reuses_fresh_cache() {
+        let (base_url, requests) = spawn_schema_server();
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        assert_eq!(cache.origin().source, SchemaSource::Downloaded);
+        assert_eq!(
+            cache.section("methodSpecific"),
+            Some(&serde_json::json!({"title": "methodSpecific.json"}))
+        );
+
+        let version = built_in_version("v2");
+        let method = version.section("methodSpecific").unwrap();
+        let file_path = get_cache_dir(&config, &version).unwrap().join(&method.file);
+        let metadata = FragmentMetadata::load(&file_path).await.unwrap();
+        assert_eq!(metadata.url, fragment_url(&config, &version, method));
+        assert_eq!(metadata.etag.as_deref(), Some(TEST_ETAG));
+        assert_eq!(
+            metadata.last_modified.as_deref(),
+            Some("Sat, 17 Oct 2026 07:28:00 GMT")
+        );
+        assert!(metadata.matches(&std::fs::read_to_string(&file_path).unwrap()));
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        assert_eq!(cache.origin().source, SchemaSource::Cached);
+        assert_eq!(requests.lock().unwrap().len(), version.sections.len());
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_is_revalidated_with_conditional_requests() {
+        let (base_url, requests) = spawn_schema_server();
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), Duration::ZERO);
+
+        SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        let version = built_in_version("v2");
+        let file_path = get_cache_dir(&config, &version)
+            .unwrap()
+            .join("generalSection.json");
+        let before = FragmentMetadata::load(&file_path).await.unwrap();
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        assert_eq!(cache.origin().source, SchemaSource::Cached);
+        assert!(cache.warnings().is_empty());
+        assert_eq!(
+            cache.section("generalSection"),
+            Some(&serde_json::json!({"title": "generalSection.json"}))
+        );
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2 * version.sections.len());
+        assert!(
+            requests[version.sections.len()..]
+                .iter()
+                .all(|head| head.to_ascii_lowercase().contains("if-none-match"))
+        );
+
+        let after = FragmentMetadata::load(&file_path).await.unwrap();
+        assert_eq!(after.downloaded_at, before.downloaded_at);
+        assert!(after.checked_at >= before.checked_at);
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_is_used_when_server_is_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+        let version = built_in_version("v2");
+        let cache_dir = get_cache_dir(&config, &version).unwrap();
+        std::fs::create_dir_all(&cache_dir).unwrap();
+
+        let downloaded_at = Utc::now() - chrono::Duration::days(30);
+        for section in &version.sections {
+            let text = format!(r#"{{"title": "stale {}"}}"#, section.label());
+            let file_path = cache_dir.join(&section.file);
+            std::fs::write(&file_path, &text).unwrap();
+            let mut metadata = FragmentMetadata::new(
+                &fragment_url(&config, &version, section),
+                &text,
+                Some(TEST_ETAG.to_string()),
+                None,
+            );
+            metadata.downloaded_at = downloaded_at;
+            metadata.checked_at = downloaded_at;
+            metadata.save(&file_path).await.unwrap();
+        }
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        assert_eq!(cache.origin().source, SchemaSource::Cached);
+        assert_eq!(
+            cache.origin().snapshot_date,
+            DateTime::<Local>::from(downloaded_at).date_naive()
+        );
+        assert_eq!(
+            cache.section("methodSpecific"),
+            Some(&serde_json::json!({"title": "stale method"}))
+        );
+        assert_eq!(cache.warnings().len(), 1);
+        assert!(cache.warnings()[0].contains("using the stale copy"));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_cache_is_downloaded_again() {
+        let (base_url, requests) = spawn_schema_server();
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+
+        SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        let version = built_in_version("v2");
+        let file_path = get_cache_dir(&config, &version)
+            .unwrap()
+            .join("toolSpecific.json");
+        std::fs::write(&file_path, r#"{"title": "tampered"}"#).unwrap();
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, true)
+            .await
+            .unwrap();
+        assert_eq!(cache.origin().source, SchemaSource::Downloaded);
+        assert_eq!(
+            cache.section("toolSpecific"),
+            Some(&serde_json::json!({"title": "toolSpecific.json"}))
+        );
+        assert_eq!(requests.lock().unwrap().len(), 2 * version.sections.len());
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_server_errors_with_backoff() {
+        let (base_url, requests) =
+            spawn_flaky_schema_server(|_, earlier| (earlier < 2).then_some(503));
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+
+        let cache = SchemaCache::download_all(&config, SchemaVersion::V2, false)
+            .await
+            .unwrap();
+
+        assert_eq!(cache.origin().source, SchemaSource::Downloaded);
+        assert_eq!(requests.lock().unwrap().len(), 3 * 6);
+    }
+
+    #[tokio::test]
+    async fn test_download_failures_are_reported_per_fragment() {
+        let (base_url, requests) = spawn_flaky_schema_server(|path, _| {
+            if path.ends_with("/toolSpecific.json") {
+                Some(503)
+            } else if path.ends_with("/historySection.json") {
+                Some(404)
+            } else {
+                None
+            }
+        });
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+
+        let err = SchemaCache::refresh(
+            &config,
+            &HttpClient::new(&config.http).unwrap(),
+            SchemaVersion::V2,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.starts_with("Failed to download 2 of 6 v2 schema fragments:"));
+        assert!(err.contains(&format!(
+            "tool schema from {base_url}/master/schema/v2/toolSpecific.json: server responded with 503"
+        )));
+        assert!(err.contains("(gave up after 4 attempts)"));
+        assert!(err.contains("history schema endpoint"));
+        let requests = requests.lock().unwrap();
+        let count = |file: &str| requests.iter().filter(|head| head.contains(file)).count();
+        assert_eq!(count("toolSpecific.json"), 4);
+        assert_eq!(count("historySection.json"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_custom_source_does_not_fall_back_to_bundled_snapshot() {
+        let (base_url, _) = spawn_flaky_schema_server(|_, _| Some(404));
+        let cache_root = tempfile::TempDir::new().unwrap();
+        let config = test_config(&base_url, cache_root.path(), DEFAULT_SCHEMA_TTL);
+        assert!(!config.is_upstream_default(&built_in_version("v2")));
+
+        let err = SchemaCache::download_all(&config, SchemaVersion::V2, false)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Failed to download 6 of 6 v2 schema fragments:")
+        );
+
+        let pinned = SchemaConfig {
+            git_ref: Some("v2.1.0".to_string()),
+            ..SchemaConfig::default()
+        };
+        assert!(!pinned.is_upstream_default(&built_in_version("v2")));
+        assert!(SchemaConfig::default().is_upstream_default(&built_in_version("v2")));
     }
 }
@@ -0,0 +1,80 @@
+//! Snapshot of the upstream FAMH schema fragments compiled into the binary.
+//!
+//! The snapshot is only used when neither the on-disk cache nor the network
+//! can provide the schema, e.g. on air-gapped machines or in fresh CI
+//! containers, and only for the default upstream URL and ref.
+//!
+//! No snapshot is vendored yet, so a failed download is an error. To vendor
+//! one, copy the `schema/v1` and `schema/v2` folders of a `fa-metadata-schema`
+//! commit verbatim to `schemas/v1` and `schemas/v2`, list every file in
+//! [`FRAGMENTS`] and record the commit and its date in [`SNAPSHOT`].
+
+/// The upstream commit the bundled fragments were copied from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Commit of `fa-metadata-schema` the fragments were copied from.
+    pub commit: &'static str,
+    /// Date of that commit, `YYYY-MM-DD`.
+    pub date: &'static str,
+}
+
+/// The bundled snapshot, `None` until fragments are vendored.
+pub const SNAPSHOT: Option<Snapshot> = None;
+
+/// Bundled fragments as `(folder, file, text)`, e.g.
+/// `("v2", "generalSection.json", include_str!("../../schemas/v2/generalSection.json"))`.
+const FRAGMENTS: &[(&str, &str, &str)] = &[];
+
+/// Bundled fragment `file` of the version stored in `folder`.
+pub(super) fn fragment_text(folder: &str, file: &str) -> Option<&'static str> {
+    FRAGMENTS
+        .iter()
+        .find(|(f, name, _)| *f == folder && *name == file)
+        .map(|(_, _, text)| *text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SchemaRegistry;
+
+    #[test]
+    fn test_bundled_fragments_match_the_manifest() {
+        let registry = SchemaRegistry::built_in();
+        assert_eq!(SNAPSHOT.is_some(), !FRAGMENTS.is_empty());
+        if let Some(snapshot) = SNAPSHOT {
+            assert!(chrono::NaiveDate::parse_from_str(snapshot.date, "%Y-%m-%d").is_ok());
+            assert!(!snapshot.commit.is_empty());
+        }
+
+        for (folder, file, text) in FRAGMENTS {
+            let section = registry
+                .versions()
+                .iter()
+                .filter(|version| version.folder() == *folder)
+                .find_map(|version| version.sections.iter().find(|s| s.file == *file));
+            let section =
+                section.unwrap_or_else(|| panic!("{folder}/{file} is not in the manifest"));
+            let schema: serde_json::Value = serde_json::from_str(text).unwrap();
+            assert!(
+                schema["properties"].get(&section.name).is_some(),
+                "{folder}/{file} does not define {}",
+                section.name
+            );
+        }
+
+        // A version is either bundled completely or not at all.
+        for version in registry.versions() {
+            let bundled = version
+                .sections
+                .iter()
+                .filter(|section| fragment_text(version.folder(), &section.file).is_some())
+                .count();
+            assert!(
+                bundled == 0 || bundled == version.sections.len(),
+                "{} is bundled partially",
+                version.name
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Freshness metadata of cached schema fragments.
+//!
+//! Every cached fragment `<file>` is accompanied by a `<file>.meta` JSON file
+//! recording where and when it was downloaded, the HTTP validators needed for
+//! conditional requests and a checksum of the cached content.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct FragmentMetadata {
+    /// URL the fragment was downloaded from.
+    pub url: String,
+    /// When the content was last downloaded.
+    pub downloaded_at: DateTime<Utc>,
+    /// When the content was last confirmed to be up to date.
+    pub checked_at: DateTime<Utc>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Hex encoded SHA-256 of the cached file.
+    pub sha256: String,
+}
+
+impl FragmentMetadata {
+    pub fn new(url: &str, text: &str, etag: Option<String>, last_modified: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            url: url.to_string(),
+            downloaded_at: now,
+            checked_at: now,
+            etag,
+            last_modified,
+            sha256: sha256_hex(text),
+        }
+    }
+
+    /// Whether the fragment was checked within the last `ttl`.
+    pub fn is_fresh(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| self.checked_at.checked_add_signed(ttl))
+            .is_some_and(|expires| expires > now)
+    }
+
+    /// Whether `text` is the content this metadata was recorded for.
+    pub fn matches(&self, text: &str) -> bool {
+        self.sha256 == sha256_hex(text)
+    }
+
+    /// Read the metadata stored next to `fragment_path`, if any.
+    pub async fn load(fragment_path: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(metadata_path(fragment_path))
+            .await
+            .ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub async fn save(&self, fragment_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(metadata_path(fragment_path), content).await?;
+        Ok(())
+    }
+}
+
+/// Path of the metadata file belonging to `fragment_path`.
+pub(super) fn metadata_path(fragment_path: &Path) -> PathBuf {
+    let mut path = fragment_path.as_os_str().to_owned();
+    path.push(".meta");
+    PathBuf::from(path)
+}
+
+pub(super) fn sha256_hex(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_metadata_path_appends_meta_suffix() {
+        assert_eq!(
+            metadata_path(Path::new("cache/v1/General Section.json")),
+            PathBuf::from("cache/v1/General Section.json.meta")
+        );
+    }
+
+    #[test]
+    fn test_is_fresh_respects_ttl() {
+        let metadata = FragmentMetadata::new("https://example.com", "{}", None, None);
+        let later = metadata.checked_at + chrono::Duration::hours(2);
+
+        assert!(metadata.is_fresh(Duration::from_secs(3 * 60 * 60), later));
+        assert!(!metadata.is_fresh(Duration::from_secs(60 * 60), later));
+        assert!(!metadata.is_fresh(Duration::ZERO, metadata.checked_at));
+    }
+
+    #[test]
+    fn test_matches_detects_modified_content() {
+        let metadata = FragmentMetadata::new("https://example.com", r#"{"a": 1}"#, None, None);
+        assert!(metadata.matches(r#"{"a": 1}"#));
+        assert!(!metadata.matches(r#"{"a": 2}"#));
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fragment_path = dir.path().join("generalSection.json");
+        let metadata = FragmentMetadata::new(
+            "https://example.com/generalSection.json",
+            "{}",
+            Some("\"abc\"".to_string()),
+            Some("Wed, 21 Oct 2026 07:28:00 GMT".to_string()),
+        );
+
+        metadata.save(&fragment_path).await.unwrap();
+
+        assert_eq!(FragmentMetadata::load(&fragment_path).await, Some(metadata));
+        assert!(dir.path().join("generalSection.json.meta").exists());
+    }
+}
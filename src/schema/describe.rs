@@ -0,0 +1,587 @@
+//! Field documentation derived from the schema fragments.
+//!
+//! A field is addressed by a JSON pointer whose first token is the root-level
+//! section. The walk follows `properties`, array `items`, the subschemas of
+//! `allOf`/`anyOf`/`oneOf` and `$ref`s into the loaded fragments.
+
+use serde_json::Value;
+use std::fmt;
+
+use super::SchemaCache;
+use super::resolve::{follow_reference, section_pointer};
+use crate::utils::{decode_pointer_token, encode_pointer_token};
+
+/// Keywords listed as constraints of a field.
+const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "const",
+    "minimum",
+    "exclusiveMinimum",
+    "maximum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "format",
+    "minItems",
+    "maxItems",
+];
+
+/// Limit for chains of `$ref`s and nested fields, guarding against
+/// recursive schemas.
+pub(super) const MAX_REFERENCE_DEPTH: usize = 32;
+
+/// Documentation of a single field of a schema version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescription {
+    /// Dot separated path, e.g. `generalSection.fileSize`.
+    pub path: String,
+    /// JSON pointer to the field, e.g. `/generalSection/fileSize`.
+    pub pointer: String,
+    pub name: String,
+    /// Type of the field, e.g. `string` or `array of number|null`.
+    pub types: String,
+    pub description: Option<String>,
+    pub enum_values: Vec<Value>,
+    pub units: Option<UnitConstraint>,
+    pub required: bool,
+    pub examples: Vec<Value>,
+    /// Further constraints such as `minimum` or `pattern`.
+    pub constraints: Vec<(String, Value)>,
+    /// Nested fields, sorted by name.
+    pub children: Vec<FieldDescription>,
+}
+
+/// Units accepted by a `{value, unit}` field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitConstraint {
+    pub units: Vec<String>,
+    /// Whether the schema enforces the units (`enum`) or only suggests them
+    /// (`examples`).
+    pub enforced: bool,
+}
+
+/// A schema together with the index of the fragment it was found in, which
+/// `$ref`s inside it are resolved against.
+#[derive(Clone, Copy)]
+pub(super) struct Node<'a> {
+    pub(super) fragment: usize,
+    pub(super) schema: &'a Value,
+}
+
+impl SchemaCache {
+    /// Describe the field at `pointer`, including its nested fields.
+    pub fn describe_field(
+        &self,
+        pointer: &str,
+    ) -> Result<FieldDescription, Box<dyn std::error::Error>> {
+        let tokens: Vec<String> = pointer
+            .strip_prefix('/')
+            .ok_or_else(|| format!("Invalid field path '{pointer}'"))?
+            .split('/')
+            .map(decode_pointer_token)
+            .collect();
+        let section_name = &tokens[0];
+
+        let (index, section) = self
+            .version
+            .sections
+            .iter()
+            .enumerate()
+            .find(|(_, section)| &section.name == section_name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown {} section '{}'. Known sections: {}",
+                    self.version.name,
+                    section_name,
+                    self.section_names().join(", ")
+                )
+            })?;
+        let fragment = &self.fragments[index];
+        let section_schema = section_pointer(fragment, &section.name)
+            .and_then(|pointer| fragment.pointer(&pointer))
+            .ok_or_else(|| {
+                format!("Could not find schema definition for section '{section_name}'")
+            })?;
+
+        let mut node = Node {
+            fragment: index,
+            schema: section_schema,
+        };
+        let mut required = self.required.contains(section_name);
+        let mut path = section_name.clone();
+        for token in &tokens[1..] {
+            let (child, child_required) = self
+                .child(node, token)
+                .ok_or_else(|| format!("Field '{path}' has no field '{token}'"))?;
+            node = child;
+            required = child_required;
+            if token.parse::<usize>().is_ok() {
+                path.push_str("[]");
+            } else {
+                path = format!("{path}.{token}");
+            }
+        }
+
+        let name = tokens.last().unwrap();
+        Ok(self.describe(node, name, path, pointer.to_string(), required, 0))
+    }
+
+    pub(super) fn section_names(&self) -> Vec<&str> {
+        self.version
+            .sections
+            .iter()
+            .map(|section| section.name.as_str())
+            .collect()
+    }
+
+    fn describe(
+        &self,
+        node: Node<'_>,
+        name: &str,
+        path: String,
+        pointer: String,
+        required: bool,
+        depth: usize,
+    ) -> FieldDescription {
+        let chain = self.reference_chain(node);
+        let keyword = |key: &str| chain.iter().find_map(|node| node.schema.get(key));
+
+        let item = self.array_items(&chain);
+        let nested = match depth {
+            MAX_REFERENCE_DEPTH.. => Vec::new(),
+            _ => self.properties(item.unwrap_or(node)),
+        };
+        let children = nested
+            .into_iter()
+            .map(|(child_name, child, child_required)| {
+                let token = encode_pointer_token(child_name);
+                let (child_path, child_pointer) = match item {
+                    Some(_) => (
+                        format!("{path}[].{child_name}"),
+                        format!("{pointer}/0/{token}"),
+                    ),
+                    None => (format!("{path}.{child_name}"), format!("{pointer}/{token}")),
+                };
+                self.describe(
+                    child,
+                    child_name,
+                    child_path,
+                    child_pointer,
+                    child_required,
+                    depth + 1,
+                )
+            })
+            .collect();
+
+        FieldDescription {
+            path,
+            pointer,
+            name: name.to_string(),
+            types: self.type_name(&chain),
+            description: keyword("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            enum_values: keyword("enum")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+            units: self.units(node),
+            required,
+            examples: keyword("examples")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default(),
+            constraints: CONSTRAINT_KEYWORDS
+                .iter()
+                .filter_map(|key| keyword(key).map(|value| (key.to_string(), value.clone())))
+                .collect(),
+            children,
+        }
+    }
+
+    /// `node` followed by the schemas its `$ref`s point to.
+    pub(super) fn reference_chain<'a>(&'a self, node: Node<'a>) -> Vec<Node<'a>> {
+        let mut chain = vec![node];
+        let mut current = node;
+        while chain.len() < MAX_REFERENCE_DEPTH
+            && let Some(reference) = current.schema.get("$ref").and_then(Value::as_str)
+            && let Some((fragment, schema)) = follow_reference(
+                &self.version.sections,
+                &self.fragments,
+                current.fragment,
+                reference,
+            )
+        {
+            current = Node { fragment, schema };
+            chain.push(current);
+        }
+        chain
+    }
+
+    /// The schemas defining the properties of `node`: its reference chain
+    /// and the subschemas of `allOf`, `anyOf` and `oneOf`.
+    fn property_sources<'a>(&'a self, node: Node<'a>, depth: usize) -> Vec<Node<'a>> {
+        let mut sources = Vec::new();
+        for node in self.reference_chain(node) {
+            sources.push(node);
+            if depth >= MAX_REFERENCE_DEPTH {
+                continue;
+            }
+            for combinator in ["allOf", "anyOf", "oneOf"] {
+                for schema in node
+                    .schema
+                    .get(combinator)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let sub = Node {
+                        fragment: node.fragment,
+                        schema,
+                    };
+                    sources.extend(self.property_sources(sub, depth + 1));
+                }
+            }
+        }
+        sources
+    }
+
+    /// The nested fields of `node` with their schemas and required-ness.
+    pub(super) fn properties<'a>(&'a self, node: Node<'a>) -> Vec<(&'a str, Node<'a>, bool)> {
+        let sources = self.property_sources(node, 0);
+        let required = |name: &str| {
+            sources.iter().any(|source| {
+                source
+                    .schema
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .is_some_and(|required| required.iter().any(|r| r.as_str() == Some(name)))
+            })
+        };
+
+        let mut properties: Vec<(&str, Node<'_>, bool)> = Vec::new();
+        for source in &sources {
+            let Some(map) = source.schema.get("properties").and_then(Value::as_object) else {
+                continue;
+            };
+            for (name, schema) in map {
+                if properties.iter().all(|(existing, _, _)| existing != name) {
+                    let child = Node {
+                        fragment: source.fragment,
+                        schema,
+                    };
+                    properties.push((name.as_str(), child, required(name)));
+                }
+            }
+        }
+        properties
+    }
+
+    /// Step from `node` into the field `token`, or into the items of an
+    /// array for a numeric token.
+    fn child<'a>(&'a self, node: Node<'a>, token: &str) -> Option<(Node<'a>, bool)> {
+        if let Some((_, child, required)) = self
+            .properties(node)
+            .into_iter()
+            .find(|(name, _, _)| *name == token)
+        {
+            return Some((child, required));
+        }
+        if token.parse::<usize>().is_ok() {
+            let items = self.array_items(&self.reference_chain(node))?;
+            return Some((items, false));
+        }
+        None
+    }
+
+    pub(super) fn array_items<'a>(&'a self, chain: &[Node<'a>]) -> Option<Node<'a>> {
+        chain.iter().find_map(|node| {
+            node.schema
+                .get("items")
+                .filter(|items| items.is_object())
+                .map(|schema| Node {
+                    fragment: node.fragment,
+                    schema,
+                })
+        })
+    }
+
+    fn type_name(&self, chain: &[Node<'_>]) -> String {
+        let declared = chain.iter().find_map(|node| node.schema.get("type"));
+        let types = match declared {
+            Some(Value::String(name)) => name.clone(),
+            Some(Value::Array(names)) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("|"),
+            _ if chain
+                .iter()
+                .any(|node| node.schema.get("properties").is_some()) =>
+            {
+                "object".to_string()
+            }
+            _ => "any".to_string(),
+        };
+        match self.array_items(chain) {
+            Some(items) if types == "array" => {
+                format!("array of {}", self.type_name(&self.reference_chain(items)))
+            }
+            _ => types,
+        }
+    }
+
+    /// Units of a `{value, unit}` field, taken from the `enum` or the
+    /// `examples` of its `unit` (v2) or `Unit` (v1) property.
+    fn units(&self, node: Node<'_>) -> Option<UnitConstraint> {
+        let (_, unit, _) = self
+            .properties(node)
+            .into_iter()
+            .find(|(name, _, _)| matches!(*name, "unit" | "Unit"))?;
+        let chain = self.reference_chain(unit);
+        let strings = |key: &str| -> Option<Vec<String>> {
+            let values = chain
+                .iter()
+                .find_map(|node| node.schema.get(key))?
+                .as_array()?;
+            Some(
+                values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect(),
+            )
+        };
+        if let Some(units) = strings("enum") {
+            return Some(UnitConstraint {
+                units,
+                enforced: true,
+            });
+        }
+        strings("examples").map(|units| UnitConstraint {
+            units,
+            enforced: false,
+        })
+    }
+}
+
+impl FieldDescription {
+    /// Displays the field and all nested fields as an indented tree.
+    pub fn tree(&self) -> FieldTree<'_> {
+        FieldTree(self)
+    }
+}
+
+impl fmt::Display for FieldDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} ({})", self.path, self.pointer)?;
+        writeln!(f, "  Type:        {}", self.types)?;
+        writeln!(
+            f,
+            "  Required:    {}",
+            if self.required { "yes" } else { "no" }
+        )?;
+        if let Some(description) = &self.description {
+            writeln!(f, "  Description: {description}")?;
+        }
+        if !self.enum_values.is_empty() {
+            writeln!(f, "  Allowed:     {}", join_values(&self.enum_values))?;
+        }
+        if let Some(units) = &self.units {
+            writeln!(f, "  Units:       {units}")?;
+        }
+        if !self.constraints.is_empty() {
+            let constraints: Vec<String> = self
+                .constraints
+                .iter()
+                .map(|(keyword, value)| format!("{keyword} {value}"))
+                .collect();
+            writeln!(f, "  Constraints: {}", constraints.join(", "))?;
+        }
+        if let Some(example) = self.examples.first() {
+            writeln!(f, "  Example:     {example}")?;
+        }
+        if !self.children.is_empty() {
+            let names: Vec<&str> = self
+                .children
+                .iter()
+                .map(|child| child.name.as_str())
+                .collect();
+            writeln!(f, "  Fields:      {}", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for UnitConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.units.join(", "))?;
+        if !self.enforced {
+            write!(f, " (suggested)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Indented tree of a field and its nested fields, see [`FieldDescription::tree`].
+pub struct FieldTree<'a>(&'a FieldDescription);
+
+impl fmt::Display for FieldTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tree(f, self.0, 0)
+    }
+}
+
+fn write_tree(f: &mut fmt::Formatter<'_>, field: &FieldDescription, depth: usize) -> fmt::Result {
+    write!(f, "{}{}: {}", "  ".repeat(depth), field.name, field.types)?;
+    if field.required {
+        write!(f, " (required)")?;
+    }
+    if !field.enum_values.is_empty() {
+        write!(f, " [{}]", join_values(&field.enum_values))?;
+    }
+    if let Some(units) = &field.units {
+        write!(f, " [units: {units}]")?;
+    }
+    writeln!(f)?;
+    for child in &field.children {
+        write_tree(f, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn join_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SchemaOrigin;
+    use serde_json::json;
+
+    #[test]
+    fn test_describe_field_with_enforced_units() {
+        let field = SchemaCache::fixture("v2")
+            .describe_field("/generalSection/fileSize")
+            .unwrap();
+
+        assert_eq!(field.path, "generalSection.fileSize");
+        assert_eq!(field.types, "object");
+        assert!(!field.required);
+        assert_eq!(
+            field.description.as_deref(),
+            Some("Size of the image file on disk.")
+        );
+        assert_eq!(
+            field.units,
+            Some(UnitConstraint {
+                units: vec!["bytes".into(), "kB".into(), "MB".into(), "GB".into()],
+                enforced: true,
+            })
+        );
+        let value = field
+            .children
+            .iter()
+            .find(|child| child.name == "value")
+            .unwrap();
+        assert!(value.required);
+        assert_eq!(value.constraints, vec![("minimum".to_string(), json!(0))]);
+    }
+
+    #[test]
+    fn test_describe_field_with_suggested_units() {
+        let field = SchemaCache::fixture("v2")
+            .describe_field("/generalSection/coordinates/relativeOrientation")
+            .unwrap();
+
+        let units = field.units.unwrap();
+        assert!(!units.enforced);
+        assert_eq!(units.units, vec!["deg", "degrees", "rad"]);
+        let value = field
+            .children
+            .iter()
+            .find(|child| child.name == "value")
+            .unwrap();
+        assert_eq!(value.types, "array of number|null");
+    }
+
+    #[test]
+    fn test_describe_v1_section_tree() {
+        let field = SchemaCache::fixture("v1")
+            .describe_field("/General Section")
+            .unwrap();
+        let tree = field.tree().to_string();
+
+        assert!(field.required);
+        assert!(tree.starts_with("General Section: object (required)\n"));
+        assert!(tree.contains("\n  Compressed Bits/Pixel: number\n"));
+        assert!(tree.contains("\n    Unit: string (required) [bytes, kB, MB, GB]\n"));
+    }
+
+    #[test]
+    fn test_describe_field_follows_refs_and_array_items() {
+        let version: crate::schema::VersionSpec = serde_json::from_value(json!({
+            "name": "test",
+            "sections": [
+                { "name": "history", "file": "history.json" },
+                { "name": "common", "file": "common.json" }
+            ]
+        }))
+        .unwrap();
+        let history = json!({
+            "properties": {
+                "history": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/entry" }
+                }
+            },
+            "$defs": {
+                "entry": {
+                    "type": "object",
+                    "required": ["actor"],
+                    "properties": { "actor": { "$ref": "common.json#/$defs/name" } }
+                }
+            }
+        });
+        let common = json!({
+            "properties": { "common": {} },
+            "$defs": { "name": { "type": "string", "description": "Who did it." } }
+        });
+        let schema_cache =
+            SchemaCache::new(version, vec![history, common], SchemaOrigin::downloaded()).unwrap();
+
+        let history = schema_cache.describe_field("/history").unwrap();
+        assert_eq!(history.types, "array of object");
+        assert_eq!(history.children[0].path, "history[].actor");
+        assert_eq!(history.children[0].pointer, "/history/0/actor");
+
+        let actor = schema_cache.describe_field("/history/3/actor").unwrap();
+        assert_eq!(actor.path, "history[].actor");
+        assert_eq!(actor.types, "string");
+        assert_eq!(actor.description.as_deref(), Some("Who did it."));
+        assert!(actor.required);
+    }
+
+    #[test]
+    fn test_describe_unknown_field() {
+        let schema_cache = SchemaCache::fixture("v2");
+
+        let err = schema_cache
+            .describe_field("/generalSection/fileSize/bytes")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Field 'generalSection.fileSize' has no field 'bytes'"
+        );
+        let err = schema_cache.describe_field("/general").unwrap_err();
+        assert!(err.to_string().starts_with("Unknown v2 section 'general'"));
+    }
+}
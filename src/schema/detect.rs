@@ -0,0 +1,218 @@
+//! Detection of the FAMH version of a document.
+//!
+//! The version is derived from the root-level section names (`General Section`
+//! vs. `generalSection`) and cross-checked against the version declared in
+//! `General Section.Version` or `generalSection.version`. Documents mixing
+//! both generations, or declaring a version that contradicts their section
+//! names, are rejected instead of guessed.
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use super::{SchemaRegistry, SchemaVersion};
+
+/// Value of `--version` that requests auto-detection.
+pub const AUTO_VERSION: &str = "auto";
+
+/// FAMH version requested on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum VersionSelection {
+    /// Detect the version from the document
+    #[default]
+    #[value(name = "auto")]
+    Auto,
+    #[value(name = "v1")]
+    V1,
+    #[value(name = "v2")]
+    V2,
+}
+
+impl From<SchemaVersion> for VersionSelection {
+    fn from(version: SchemaVersion) -> Self {
+        match version {
+            SchemaVersion::V1 => VersionSelection::V1,
+            SchemaVersion::V2 => VersionSelection::V2,
+        }
+    }
+}
+
+impl VersionSelection {
+    /// The selected version, detecting it from `document` for `auto`.
+    pub fn resolve(self, document: &Value) -> Result<SchemaVersion, Box<dyn std::error::Error>> {
+        match self {
+            VersionSelection::Auto => detect_version(document),
+            VersionSelection::V1 => Ok(SchemaVersion::V1),
+            VersionSelection::V2 => Ok(SchemaVersion::V2),
+        }
+    }
+}
+
+/// Resolve a `--version` value naming a registry version, or `auto`.
+pub fn resolve_version_name(
+    requested: &str,
+    document: &Value,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if requested.eq_ignore_ascii_case(AUTO_VERSION) {
+        Ok(detect_version(document)?.to_string())
+    } else {
+        Ok(requested.to_string())
+    }
+}
+
+/// Detect whether `document` is a FAMH v1 or v2 header.
+pub fn detect_version(document: &Value) -> Result<SchemaVersion, Box<dyn std::error::Error>> {
+    let Some(top_level) = document.as_object() else {
+        return Err("Cannot detect the FAMH version: the document is not a JSON object".into());
+    };
+
+    let registry = SchemaRegistry::built_in();
+    let sections_of = |version: SchemaVersion| -> Vec<&str> {
+        registry
+            .get(version.as_str())
+            .map(|spec| {
+                spec.sections
+                    .iter()
+                    .map(|section| section.name.as_str())
+                    .filter(|name| top_level.contains_key(*name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let v1_sections = sections_of(SchemaVersion::V1);
+    let v2_sections = sections_of(SchemaVersion::V2);
+
+    let by_sections = match (v1_sections.is_empty(), v2_sections.is_empty()) {
+        (false, true) => SchemaVersion::V1,
+        (true, false) => SchemaVersion::V2,
+        (false, false) => {
+            return Err(format!(
+                "Cannot detect the FAMH version: the document mixes v1 sections ({}) and v2 sections ({}). Pass --version explicitly",
+                v1_sections.join(", "),
+                v2_sections.join(", ")
+            )
+            .into());
+        }
+        (true, true) => {
+            return Err(
+                "Cannot detect the FAMH version: no v1 or v2 root-level section found. Pass --version explicitly"
+                    .into(),
+            );
+        }
+    };
+
+    let (field, declared) = declared_version(document, by_sections);
+    let Some(declared) = declared else {
+        return Ok(by_sections);
+    };
+    match major_version(declared) {
+        Some(1) if by_sections == SchemaVersion::V1 => Ok(by_sections),
+        Some(2) if by_sections == SchemaVersion::V2 => Ok(by_sections),
+        Some(1 | 2) => Err(format!(
+            "Cannot detect the FAMH version: the document uses {} section names but {} declares version '{}'. Pass --version explicitly",
+            by_sections, field, declared
+        )
+        .into()),
+        Some(_) => Err(format!(
+            "Cannot detect the FAMH version: {} declares unsupported version '{}'. Pass --version explicitly",
+            field, declared
+        )
+        .into()),
+        None => Ok(by_sections),
+    }
+}
+
+/// The version declared in the general section of a `version` document.
+fn declared_version(document: &Value, version: SchemaVersion) -> (&'static str, Option<&str>) {
+    let (field, pointer) = match version {
+        SchemaVersion::V1 => ("General Section.Version", "/General Section/Version"),
+        SchemaVersion::V2 => ("generalSection.version", "/generalSection/version"),
+    };
+    (field, document.pointer(pointer).and_then(Value::as_str))
+}
+
+/// Major version of a declared version such as `2.0`, `v1` or `1.2.3`.
+fn major_version(declared: &str) -> Option<u64> {
+    let declared = declared.trim();
+    let declared = declared.strip_prefix(['v', 'V']).unwrap_or(declared);
+    let digits: String = declared.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_version_from_section_names() {
+        let v1 = json!({"General Section": {}, "Method Specific": {}});
+        let v2 = json!({"generalSection": {}, "methodSpecific": {}});
+
+        assert_eq!(detect_version(&v1).unwrap(), SchemaVersion::V1);
+        assert_eq!(detect_version(&v2).unwrap(), SchemaVersion::V2);
+    }
+
+    #[test]
+    fn test_detect_version_accepts_matching_declared_version() {
+        let v1 = json!({"General Section": {"Version": "1.0"}});
+        let v2 = json!({"generalSection": {"version": "v2.1"}});
+
+        assert_eq!(detect_version(&v1).unwrap(), SchemaVersion::V1);
+        assert_eq!(detect_version(&v2).unwrap(), SchemaVersion::V2);
+    }
+
+    #[test]
+    fn test_detect_version_rejects_mixed_documents() {
+        let mixed = json!({"General Section": {}, "methodSpecific": {}});
+
+        let err = detect_version(&mixed).unwrap_err().to_string();
+        assert!(err.contains("mixes v1 sections (General Section)"));
+        assert!(err.contains("v2 sections (methodSpecific)"));
+    }
+
+    #[test]
+    fn test_detect_version_rejects_conflicting_declared_version() {
+        let document = json!({"generalSection": {"version": "1.0"}});
+
+        let err = detect_version(&document).unwrap_err().to_string();
+        assert!(err.contains("uses v2 section names"));
+        assert!(err.contains("generalSection.version declares version '1.0'"));
+    }
+
+    #[test]
+    fn test_detect_version_rejects_unsupported_declared_version() {
+        let document = json!({"generalSection": {"version": "3.0"}});
+
+        let err = detect_version(&document).unwrap_err().to_string();
+        assert!(err.contains("unsupported version '3.0'"));
+    }
+
+    #[test]
+    fn test_detect_version_requires_known_sections() {
+        assert!(detect_version(&json!({"foo": {}})).is_err());
+        assert!(detect_version(&json!([])).is_err());
+    }
+
+    #[test]
+    fn test_major_version() {
+        assert_eq!(major_version("2.0"), Some(2));
+        assert_eq!(major_version("V1"), Some(1));
+        assert_eq!(major_version(" 10.2 "), Some(10));
+        assert_eq!(major_version("draft"), None);
+    }
+
+    #[test]
+    fn test_resolve_version_name_keeps_explicit_versions() {
+        let document = json!({"generalSection": {}});
+
+        assert_eq!(resolve_version_name("auto", &document).unwrap(), "v2");
+        assert_eq!(
+            resolve_version_name("v2-acme", &document).unwrap(),
+            "v2-acme"
+        );
+        assert_eq!(
+            VersionSelection::V1.resolve(&document).unwrap(),
+            SchemaVersion::V1
+        );
+    }
+}
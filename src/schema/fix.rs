@@ -0,0 +1,399 @@
+//! Safe repairs of mechanical validation failures.
+//!
+//! [`SchemaCache::fixes`] walks a document alongside its schema and proposes
+//! repairs whose result is unambiguous: numbers written as strings, bare
+//! numbers where `{value, unit}` is expected and the unit is known, known keys
+//! with the wrong case, and v1 style `Value`/`Unit` keys where v2 expects
+//! `value`/`unit`. The document itself is not changed.
+
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+use super::SchemaCache;
+use super::describe::{MAX_REFERENCE_DEPTH, Node};
+use crate::units::UnitRegistry;
+use crate::utils::encode_pointer_token;
+
+/// The kinds of repairs `fixes` proposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FixKind {
+    /// A known key written with the wrong case, e.g. `filename`.
+    KeyCase,
+    /// `Value`/`Unit` keys of FAMH v1 where `value`/`unit` are expected.
+    LegacyValueUnit,
+    /// A number written as a string, e.g. `"1024"`.
+    NumberFromString,
+    /// A bare number or a string such as `"5 kV"` where `{value, unit}` is
+    /// expected.
+    ValueWithUnit,
+}
+
+impl FixKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FixKind::KeyCase => "key-case",
+            FixKind::LegacyValueUnit => "legacy-value-unit",
+            FixKind::NumberFromString => "number-from-string",
+            FixKind::ValueWithUnit => "value-with-unit",
+        }
+    }
+}
+
+impl fmt::Display for FixKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// What a fix changes at its pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Rename the key to the given name.
+    RenameKey(String),
+    /// Replace the value.
+    ReplaceValue(Value),
+}
+
+impl Change {
+    /// The JSON text written by the change: the quoted key, or the value with
+    /// `value` before `unit`.
+    pub fn to_json(&self) -> String {
+        let value = match self {
+            Change::RenameKey(key) => return Value::String(key.clone()).to_string(),
+            Change::ReplaceValue(value) => value,
+        };
+        let member = |object: &Map<String, Value>, names: [&str; 2]| {
+            names
+                .into_iter()
+                .find_map(|name| Some(format!("{}: {}", Value::from(name), object.get(name)?)))
+        };
+        match value.as_object() {
+            Some(object) if object.len() == 2 => {
+                match (
+                    member(object, ["value", "Value"]),
+                    member(object, ["unit", "Unit"]),
+                ) {
+                    (Some(value), Some(unit)) => format!("{{ {value}, {unit} }}"),
+                    _ => value.to_string(),
+                }
+            }
+            _ => value.to_string(),
+        }
+    }
+}
+
+/// A single repair of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// JSON pointer of the key or value in the unrepaired document.
+    pub instance_path: String,
+    pub kind: FixKind,
+    pub change: Change,
+    pub message: String,
+}
+
+impl SchemaCache {
+    /// Repairs of `document`. Renamed keys are followed, so values below them
+    /// are repaired against the schema of the known key.
+    pub fn fixes(&self, document: &Value) -> Vec<Fix> {
+        let mut fixes = Vec::new();
+        let Some(top_level) = document.as_object() else {
+            return fixes;
+        };
+        for (key, value) in top_level {
+            let pointer = format!("/{}", encode_pointer_token(key));
+            let name = match self.version.section(key) {
+                Some(_) => key.as_str(),
+                None => match case_match(key, self.section_names(), top_level) {
+                    Some(name) => {
+                        fixes.push(rename(&pointer, key, name));
+                        name
+                    }
+                    None => continue,
+                },
+            };
+            if let Some(node) = self.section_node(name) {
+                self.collect_fixes(node, value, &pointer, 0, &mut fixes);
+            }
+        }
+        fixes
+    }
+
+    fn collect_fixes(
+        &self,
+        node: Node<'_>,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        fixes: &mut Vec<Fix>,
+    ) {
+        if depth >= MAX_REFERENCE_DEPTH {
+            return;
+        }
+        match value {
+            Value::Object(object) => {
+                let properties = self.properties(node);
+                for (key, value) in object {
+                    let pointer = format!("{}/{}", pointer, encode_pointer_token(key));
+                    let name = match properties.iter().find(|(name, _, _)| name == key) {
+                        Some((name, _, _)) => *name,
+                        None => match case_match(
+                            key,
+                            properties.iter().map(|(name, _, _)| *name),
+                            object,
+                        ) {
+                            Some(name) => {
+                                fixes.push(rename(&pointer, key, name));
+                                name
+                            }
+                            None => continue,
+                        },
+                    };
+                    if let Some((_, child, _)) = properties.iter().find(|(n, _, _)| *n == name) {
+                        self.collect_fixes(*child, value, &pointer, depth + 1, fixes);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let Some(item) = self.array_items(&self.reference_chain(node)) else {
+                    return;
+                };
+                for (index, value) in items.iter().enumerate() {
+                    let pointer = format!("{pointer}/{index}");
+                    self.collect_fixes(item, value, &pointer, depth + 1, fixes);
+                }
+            }
+            Value::Number(_) | Value::String(_) => {
+                if let Some(fix) = self
+                    .value_with_unit(node, value, pointer)
+                    .or_else(|| self.number_from_string(node, value, pointer))
+                {
+                    fixes.push(fix);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Declared types of `node`, e.g. `["number", "null"]`.
+    fn declared_types<'a>(&'a self, node: Node<'a>) -> Vec<&'a str> {
+        match self
+            .reference_chain(node)
+            .iter()
+            .find_map(|node| node.schema.get("type"))
+        {
+            Some(Value::String(name)) => vec![name.as_str()],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `value` as a number, if `node` expects a number but not a string.
+    fn number_from_string(&self, node: Node<'_>, value: &Value, pointer: &str) -> Option<Fix> {
+        let text = value.as_str()?;
+        let number = self.parse_number(node, text)?;
+        Some(Fix {
+            instance_path: pointer.to_string(),
+            kind: FixKind::NumberFromString,
+            message: format!("Convert the string \"{text}\" to the number {number}"),
+            change: Change::ReplaceValue(Value::Number(number)),
+        })
+    }
+
+    fn parse_number(&self, node: Node<'_>, text: &str) -> Option<Number> {
+        let types = self.declared_types(node);
+        if types.contains(&"string") {
+            return None;
+        }
+        let number: Number = text.trim().parse().ok()?;
+        if types.contains(&"number") || (types.contains(&"integer") && !number.is_f64()) {
+            Some(number)
+        } else {
+            None
+        }
+    }
+
+    /// `value` wrapped into `{value, unit}`, if `node` expects that object and
+    /// the unit is certain: a bare number of a field with a single allowed
+    /// unit, or a string such as `"5 kV"` naming an allowed or known unit.
+    fn value_with_unit(&self, node: Node<'_>, value: &Value, pointer: &str) -> Option<Fix> {
+        let properties = self.properties(node);
+        let property = |names: [&str; 2]| {
+            properties
+                .iter()
+                .find(|(name, _, _)| names.contains(name))
+                .map(|(name, node, _)| (*name, *node))
+        };
+        let (value_key, value_node) = property(["value", "Value"])?;
+        let (unit_key, unit_node) = property(["unit", "Unit"])?;
+        let units = |key: &str| -> Option<Vec<&str>> {
+            let units = self
+                .reference_chain(unit_node)
+                .iter()
+                .find_map(|node| node.schema.get(key))?
+                .as_array()?;
+            Some(units.iter().filter_map(Value::as_str).collect())
+        };
+        let allowed = units("enum");
+        let known = |unit: &str| match &allowed {
+            Some(allowed) => allowed.contains(&unit),
+            None => {
+                units("examples").is_some_and(|examples| examples.contains(&unit))
+                    || UnitRegistry::new().unit_dimension(unit).is_some()
+            }
+        };
+
+        let (number, unit) = match value {
+            Value::Number(number) => match allowed.as_deref() {
+                Some([unit]) => (self.parse_number(value_node, &number.to_string())?, *unit),
+                _ => return None,
+            },
+            Value::String(text) => {
+                let text = text.trim();
+                let split = text.find(|c: char| c.is_whitespace() || c.is_alphabetic())?;
+                let unit = text[split..].trim();
+                if !known(unit) {
+                    return None;
+                }
+                (self.parse_number(value_node, &text[..split])?, unit)
+            }
+            _ => return None,
+        };
+        let mut wrapped = Map::new();
+        wrapped.insert(value_key.to_string(), Value::Number(number));
+        wrapped.insert(unit_key.to_string(), Value::String(unit.to_string()));
+        let change = Change::ReplaceValue(Value::Object(wrapped));
+        Some(Fix {
+            instance_path: pointer.to_string(),
+            kind: FixKind::ValueWithUnit,
+            message: format!("Write {value} as {}", change.to_json()),
+            change,
+        })
+    }
+}
+
+/// The candidate equal to `key` but for case, unless `object` already holds
+/// it.
+fn case_match<'a>(
+    key: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    object: &Map<String, Value>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.to_lowercase() == key.to_lowercase())
+        .filter(|candidate| !object.contains_key(*candidate))
+}
+
+fn rename(pointer: &str, from: &str, to: &str) -> Fix {
+    let kind = match from {
+        "Value" | "Unit" => FixKind::LegacyValueUnit,
+        _ => FixKind::KeyCase,
+    };
+    Fix {
+        instance_path: pointer.to_string(),
+        kind,
+        message: format!("Rename \"{from}\" to \"{to}\""),
+        change: Change::RenameKey(to.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summary(fixes: &[Fix]) -> Vec<(String, FixKind, Change)> {
+        fixes
+            .iter()
+            .map(|fix| (fix.instance_path.clone(), fix.kind, fix.change.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fixes_cover_the_catalogue() {
+        let schema = SchemaCache::fixture("v2");
+        let document = json!({
+            "GeneralSection": {
+                "fileName": "sample.tif",
+                "imageWidth": 1024,
+                "imageHeight": { "Value": "768", "Unit": "px" },
+                "pixelWidth": "4.5 nm",
+                "filesize": { "value": 10, "unit": "MB" }
+            },
+            "methodSpecific": {
+                "scanningElectronMicroscopy": {
+                    "acceleratingVoltage": 5,
+                    "workingDistance": "8.5 furlong"
+                }
+            }
+        });
+
+        let fixes = schema.fixes(&document);
+
+        assert_eq!(
+            summary(&fixes),
+            vec![
+                (
+                    "/GeneralSection".to_string(),
+                    FixKind::KeyCase,
+                    Change::RenameKey("generalSection".to_string())
+                ),
+                (
+                    "/GeneralSection/filesize".to_string(),
+                    FixKind::KeyCase,
+                    Change::RenameKey("fileSize".to_string())
+                ),
+                (
+                    "/GeneralSection/imageHeight/Unit".to_string(),
+                    FixKind::LegacyValueUnit,
+                    Change::RenameKey("unit".to_string())
+                ),
+                (
+                    "/GeneralSection/imageHeight/Value".to_string(),
+                    FixKind::LegacyValueUnit,
+                    Change::RenameKey("value".to_string())
+                ),
+                (
+                    "/GeneralSection/imageHeight/Value".to_string(),
+                    FixKind::NumberFromString,
+                    Change::ReplaceValue(json!(768))
+                ),
+                (
+                    "/GeneralSection/imageWidth".to_string(),
+                    FixKind::ValueWithUnit,
+                    Change::ReplaceValue(json!({ "value": 1024, "unit": "px" }))
+                ),
+                (
+                    "/GeneralSection/pixelWidth".to_string(),
+                    FixKind::ValueWithUnit,
+                    Change::ReplaceValue(json!({ "value": 4.5, "unit": "nm" }))
+                ),
+            ]
+        );
+        assert_eq!(
+            fixes[0].message,
+            "Rename \"GeneralSection\" to \"generalSection\""
+        );
+        assert_eq!(
+            fixes[5].message,
+            "Write 1024 as { \"value\": 1024, \"unit\": \"px\" }"
+        );
+    }
+
+    #[test]
+    fn test_valid_documents_need_no_fixes() {
+        for (version, path) in [
+            ("v1", "tests/fixtures/v1/minimal_example_sem.json"),
+            ("v2", "tests/fixtures/v2/complete_example_v2.json"),
+        ] {
+            let document: Value =
+                serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            assert_eq!(
+                SchemaCache::fixture(version).fixes(&document),
+                Vec::new(),
+                "{path}"
+            );
+        }
+    }
+}
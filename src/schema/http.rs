@@ -0,0 +1,169 @@
+//! HTTP client used to download schema fragments.
+//!
+//! Callers build one client from the [`HttpConfig`] of a run and pass it to
+//! every schema load, so all fragment downloads share its connections.
+//! Connection errors, timeouts and `5xx` responses are retried with
+//! exponential backoff; any other response is returned to the caller as is.
+
+use reqwest::{Certificate, Client, Proxy, RequestBuilder, Response};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Network settings for schema downloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Proxy for HTTPS requests. Without it the `HTTPS_PROXY` and `ALL_PROXY`
+    /// environment variables are honoured.
+    pub https_proxy: Option<String>,
+    /// PEM file with CA certificates to trust in addition to the system ones.
+    pub ca_bundle: Option<PathBuf>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    /// Number of retries after a connection error or a `5xx` response.
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further retry.
+    pub retry_backoff: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            https_proxy: None,
+            ca_bundle: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+        }
+    }
+}
+
+/// A configured client together with its retry policy.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    retries: u32,
+    retry_backoff: Duration,
+}
+
+impl HttpClient {
+    /// Build a client from `config`. Fails on an invalid proxy or CA bundle.
+    pub fn new(config: &HttpConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = Client::builder()
+            .user_agent(concat!("famdo/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout);
+
+        if let Some(proxy) = &config.https_proxy {
+            let proxy =
+                Proxy::https(proxy).map_err(|e| format!("Invalid HTTPS proxy '{proxy}': {e}"))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = &config.ca_bundle {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Failed to read CA bundle {}: {}", path.display(), e))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid CA bundle {}: {}", path.display(), e))?;
+            if certificates.is_empty() {
+                return Err(
+                    format!("CA bundle {} contains no certificates", path.display()).into(),
+                );
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to set up the HTTP client: {e}"))?;
+        Ok(Self {
+            client,
+            retries: config.retries,
+            retry_backoff: config.retry_backoff,
+        })
+    }
+
+    pub(super) fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send `request`, retrying connection errors, timeouts and `5xx`
+    /// responses. A `5xx` response to the last attempt is turned into an error.
+    pub(super) async fn send(&self, request: RequestBuilder) -> Result<Response, String> {
+        let mut delay = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let this_attempt = request.try_clone().ok_or("request cannot be retried")?;
+            let failure = match this_attempt.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Ok(response) => format!("server responded with {}", response.status()),
+                Err(e) if e.is_connect() || e.is_timeout() => describe_error(&e),
+                Err(e) => return Err(describe_error(&e)),
+            };
+
+            if attempt > self.retries {
+                return Err(match attempt {
+                    1 => failure,
+                    _ => format!("{failure} (gave up after {attempt} attempts)"),
+                });
+            }
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2);
+        }
+    }
+}
+
+/// `error` followed by its causes, which reqwest leaves out of its message,
+/// e.g. `error sending request: client error (Connect): dns error: ...`.
+fn describe_error(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_proxy_is_rejected() {
+        let config = HttpConfig {
+            https_proxy: Some("not a url".to_string()),
+            ..HttpConfig::default()
+        };
+
+        let err = HttpClient::new(&config).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid HTTPS proxy 'not a url'")
+        );
+    }
+
+    #[test]
+    fn test_ca_bundle_without_certificates_is_rejected() {
+        let bundle = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(bundle.path(), "no certificates here\n").unwrap();
+        let config = HttpConfig {
+            ca_bundle: Some(bundle.path().to_path_buf()),
+            ..HttpConfig::default()
+        };
+
+        let err = HttpClient::new(&config).unwrap_err();
+        assert!(
+            err.to_string().contains("contains no certificates"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Registry of the known FAMH schema versions.
+//!
+//! Versions are described by a JSON manifest listing, for every version, its
+//! root-level sections and the fragment files defining them. The manifest in
+//! `schemas/manifest.json` is compiled into the binary; additional versions,
+//! or replacements of the built-in ones, are read from `--schema-manifest` and
+//! from a `manifest.json` inside `--schema-dir`.
+//!
+//! ```json
+//! {
+//!   "versions": [
+//!     {
+//!       "name": "v2-acme",
+//!       "folder": "v2",
+//!       "ref": "acme/main",
+//!       "sections": [
+//!         { "name": "generalSection", "file": "generalSection.json", "label": "general" }
+//!       ],
+//!       "required": ["generalSection"]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `folder` defaults to the version name and `ref` to `master`. Without
+//! `required`, a section is required if its fragment lists the section in its
+//! own top-level `required` keyword.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+use super::{SchemaConfig, bundled};
+
+/// File name of a manifest inside a local schema directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Git ref used when neither the manifest nor the configuration names one.
+pub const DEFAULT_SCHEMA_REF: &str = "master";
+
+/// Manifest describing the built-in schema versions.
+const MANIFEST: &str = include_str!("../../schemas/manifest.json");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaManifest {
+    pub versions: Vec<VersionSpec>,
+}
+
+/// A single schema version as described by the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionSpec {
+    /// Name used to select the version, e.g. `v2`.
+    pub name: String,
+    /// Folder below `schema/` holding the fragments. Defaults to the name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// Branch, tag or commit to download the fragments from.
+    #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    pub sections: Vec<SectionSpec>,
+    /// Required root-level sections, overriding the fragments' `required`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<String>>,
+}
+
+/// A root-level section and the fragment file defining it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionSpec {
+    /// Key of the section in a FAMH document, e.g. `methodSpecific`.
+    pub name: String,
+    /// File name of the fragment, e.g. `methodSpecific.json`.
+    pub file: String,
+    /// Human readable name used in messages. Defaults to the section name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl VersionSpec {
+    pub fn folder(&self) -> &str {
+        self.folder.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn git_ref(&self) -> &str {
+        self.git_ref.as_deref().unwrap_or(DEFAULT_SCHEMA_REF)
+    }
+
+    pub fn section(&self, name: &str) -> Option<&SectionSpec> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Required root-level sections given the loaded `fragments`, which must
+    /// be in the order of [`VersionSpec::sections`].
+    pub fn required_sections(&self, fragments: &[Value]) -> Vec<String> {
+        if let Some(required) = &self.required {
+            return required.clone();
+        }
+
+        self.sections
+            .iter()
+            .zip(fragments)
+            .filter(|(section, fragment)| {
+                fragment
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .is_some_and(|required| {
+                        required
+                            .iter()
+                            .any(|name| name.as_str() == Some(section.name.as_str()))
+                    })
+            })
+            .map(|(section, _)| section.name.clone())
+            .collect()
+    }
+
+    /// Whether every fragment of this version ships with the binary.
+    pub(super) fn is_bundled(&self) -> bool {
+        self.sections
+            .iter()
+            .all(|section| self.bundled_text(section).is_some())
+    }
+
+    /// Bundled copy of a fragment, if this version ships with the binary.
+    pub(super) fn bundled_text(&self, section: &SectionSpec) -> Option<&'static str> {
+        bundled::fragment_text(self.folder(), &section.file)
+    }
+}
+
+impl SectionSpec {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether `name` is the section name, file name or label, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        [self.name.as_str(), self.file.as_str(), self.label()]
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(name))
+    }
+}
+
+/// All schema versions known to `famdo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaRegistry {
+    versions: Vec<VersionSpec>,
+}
+
+impl SchemaRegistry {
+    /// Registry of the versions compiled into the binary.
+    pub fn built_in() -> Self {
+        let manifest: SchemaManifest =
+            serde_json::from_str(MANIFEST).expect("built-in schema manifest must be valid");
+        Self {
+            versions: manifest.versions,
+        }
+    }
+
+    /// Registry of the built-in versions extended by the manifests referenced
+    /// from `config`. Later manifests replace versions of the same name.
+    pub fn load(config: &SchemaConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut registry = Self::built_in();
+        if let Some(dir) = &config.dir {
+            let path = dir.join(MANIFEST_FILE_NAME);
+            if path.is_file() {
+                registry.merge(read_manifest(&path)?);
+            }
+        }
+        if let Some(path) = &config.manifest {
+            registry.merge(read_manifest(path)?);
+        }
+        Ok(registry)
+    }
+
+    pub fn merge(&mut self, manifest: SchemaManifest) {
+        for version in manifest.versions {
+            match self.versions.iter_mut().find(|v| v.name == version.name) {
+                Some(existing) => *existing = version,
+                None => self.versions.push(version),
+            }
+        }
+    }
+
+    pub fn versions(&self) -> &[VersionSpec] {
+        &self.versions
+    }
+
+    pub fn get(&self, name: &str) -> Result<&VersionSpec, Box<dyn std::error::Error>> {
+        self.versions
+            .iter()
+            .find(|version| version.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown schema version '{}'. Known versions: {}",
+                    name,
+                    self.names().join(", ")
+                )
+                .into()
+            })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.versions
+            .iter()
+            .map(|version| version.name.as_str())
+            .collect()
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<SchemaManifest, Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read schema manifest {}: {}", path.display(), e))?;
+    let manifest: SchemaManifest = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid schema manifest {}: {}", path.display(), e))?;
+
+    for version in &manifest.versions {
+        if version.sections.is_empty() {
+            return Err(format!(
+                "Schema manifest {}: version '{}' has no sections",
+                path.display(),
+                version.name
+            )
+            .into());
+        }
+        if let Some(unknown) = version
+            .required
+            .iter()
+            .flatten()
+            .find(|required| version.section(required).is_none())
+        {
+            return Err(format!(
+                "Schema manifest {}: version '{}' requires unknown section '{}'",
+                path.display(),
+                version.name,
+                unknown
+            )
+            .into());
+        }
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_built_in_registry_lists_v1_and_v2() {
+        let registry = SchemaRegistry::built_in();
+        assert_eq!(registry.names(), vec!["v1", "v2"]);
+
+        let v2 = registry.get("V2").unwrap();
+        assert_eq!(v2.folder(), "v2");
+        assert_eq!(v2.git_ref(), "master");
+        let customer = v2.section("customerSpecific").unwrap();
+        assert_eq!(customer.file, "customerSection.json");
+        assert_eq!(customer.label(), "customer");
+    }
+
+    #[test]
+    fn test_unknown_version_lists_known_versions() {
+        let err = SchemaRegistry::built_in().get("v3").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown schema version 'v3'. Known versions: v1, v2"
+        );
+    }
+
+    #[test]
+    fn test_required_sections_come_from_fragments() {
+        let mut v2 = SchemaRegistry::built_in().get("v2").unwrap().clone();
+        v2.required = None;
+        let fragments: Vec<Value> = v2
+            .sections
+            .iter()
+            .map(|section| json!({ "required": [section.name.clone()] }))
+            .take(2)
+            .chain(std::iter::repeat_n(json!({}), v2.sections.len() - 2))
+            .collect();
+
+        assert_eq!(
+            v2.required_sections(&fragments),
+            vec!["generalSection", "customerSpecific"]
+        );
+    }
+
+    #[test]
+    fn test_manifest_required_overrides_fragments() {
+        let mut version = SchemaRegistry::built_in().get("v2").unwrap().clone();
+        version.required = Some(vec!["toolSpecific".to_string()]);
+        let fragments = vec![json!({ "required": ["generalSection"] }); version.sections.len()];
+
+        assert_eq!(version.required_sections(&fragments), vec!["toolSpecific"]);
+    }
+
+    #[test]
+    fn test_load_merges_manifest_from_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("team.json");
+        std::fs::write(
+            &path,
+            r#"{"versions": [
+                {"name": "v2", "sections": [{"name": "generalSection", "file": "general.json"}]},
+                {"name": "v2-acme", "folder": "v2", "ref": "acme",
+                 "sections": [{"name": "generalSection", "file": "generalSection.json"}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let config = SchemaConfig {
+            manifest: Some(path),
+            ..SchemaConfig::default()
+        };
+        let registry = SchemaRegistry::load(&config).unwrap();
+
+        assert_eq!(registry.names(), vec!["v1", "v2", "v2-acme"]);
+        assert_eq!(registry.get("v2").unwrap().sections.len(), 1);
+        let acme = registry.get("v2-acme").unwrap();
+        assert_eq!(acme.folder(), "v2");
+        assert_eq!(acme.git_ref(), "acme");
+    }
+
+    #[test]
+    fn test_load_rejects_required_section_missing_from_manifest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(MANIFEST_FILE_NAME),
+            r#"{"versions": [{"name": "v3", "required": ["history"],
+                "sections": [{"name": "general", "file": "general.json"}]}]}"#,
+        )
+        .unwrap();
+
+        let config = SchemaConfig {
+            dir: Some(dir.path().to_path_buf()),
+            ..SchemaConfig::default()
+        };
+        let err = SchemaRegistry::load(&config).unwrap_err();
+        assert!(err.to_string().contains("unknown section 'history'"));
+    }
+}
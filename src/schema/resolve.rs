@@ -0,0 +1,477 @@
+//! Offline resolution of `$ref`s between schema fragments.
+//!
+//! Every fragment of a version is registered under the URL it is downloaded
+//! from, and under its `$id` if it declares one. Relative references such as
+//! `toolSpecific.json#/$defs/unit` and absolute references into the schema
+//! repository therefore resolve against the loaded copies, wherever those
+//! came from. References to any other document fail instead of being fetched.
+
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::{ReferencingError, Retrieve, Uri, ValidationError};
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+use super::SectionSpec;
+use crate::utils::encode_pointer_token;
+
+/// The fragments of a version, keyed by their URIs.
+#[derive(Debug, Clone)]
+pub(super) struct FragmentResources {
+    fragments: Arc<Vec<Fragment>>,
+}
+
+#[derive(Debug)]
+struct Fragment {
+    uri: String,
+    id: Option<String>,
+    file: String,
+    label: String,
+    schema: Value,
+}
+
+impl FragmentResources {
+    /// Register `fragments`, one per entry of `sections`, below `base_uri`.
+    pub(super) fn new(base_uri: &str, sections: &[SectionSpec], fragments: &[Value]) -> Self {
+        let fragments = sections
+            .iter()
+            .zip(fragments)
+            .map(|(section, schema)| Fragment {
+                uri: format!("{}{}", base_uri, encode_uri_component(&section.file)),
+                id: schema
+                    .get("$id")
+                    .and_then(Value::as_str)
+                    .map(|id| strip_fragment(id).to_string()),
+                file: section.file.clone(),
+                label: section.label().to_string(),
+                schema: schema.clone(),
+            })
+            .collect();
+        Self {
+            fragments: Arc::new(fragments),
+        }
+    }
+
+    /// Compile a validator for the root-level section `name` defined by the
+    /// fragment of `file`.
+    pub(super) fn section_validator(
+        &self,
+        file: &str,
+        name: &str,
+    ) -> Result<jsonschema::Validator, Box<dyn std::error::Error>> {
+        let fragment = self
+            .fragments
+            .iter()
+            .find(|fragment| fragment.file == file)
+            .ok_or_else(|| format!("No schema fragment {file} is loaded"))?;
+        let pointer = section_pointer(&fragment.schema, name)
+            .ok_or_else(|| format!("Could not find schema definition for section '{name}'"))?;
+
+        // Referencing the section instead of compiling it on its own keeps
+        // `#/$defs/...` and sibling-file references anchored at the fragment.
+        let entry_point = json!({
+            "$ref": format!("{}#{}", fragment.uri, encode_uri_component(&pointer))
+        });
+        jsonschema::options()
+            .with_retriever(self.clone())
+            .build(&entry_point)
+            .map_err(|err| self.describe_build_error(err).into())
+    }
+
+    /// Compile a schema that is not one of the fragments, resolving relative
+    /// `$ref`s such as `toolSpecific.json#/$defs/unit` against `base_uri`.
+    pub(super) fn validator(
+        &self,
+        base_uri: &str,
+        schema: &Value,
+    ) -> Result<jsonschema::Validator, Box<dyn std::error::Error>> {
+        jsonschema::options()
+            .with_base_uri(base_uri.to_string())
+            .with_retriever(self.clone())
+            .build(schema)
+            .map_err(|err| self.describe_build_error(err).into())
+    }
+
+    fn lookup(&self, uri: &str) -> Option<&Fragment> {
+        let uri = strip_fragment(uri);
+        self.fragments
+            .iter()
+            .find(|fragment| fragment.uri == uri || fragment.id.as_deref() == Some(uri))
+            .or_else(|| {
+                // Fragments with an `$id` resolve their relative references
+                // against it, which may name another host or ref than the one
+                // the fragments were loaded from. Match those by file name.
+                let file = percent_decode(uri.rsplit('/').next().unwrap_or(uri));
+                self.fragments.iter().find(|fragment| fragment.file == file)
+            })
+    }
+
+    /// Turn a reference error into a message naming the fragment and the
+    /// location of the `$ref` that could not be resolved.
+    fn describe_build_error(&self, err: ValidationError<'_>) -> String {
+        let ValidationErrorKind::Referencing(error) = &err.kind else {
+            return format!("Invalid schema: {err}");
+        };
+
+        let (target, reason) = match error {
+            ReferencingError::Unretrievable { uri, .. } => (
+                RefTarget::Document(uri.as_str()),
+                format!("{uri} is not one of the loaded schema fragments"),
+            ),
+            ReferencingError::PointerToNowhere { pointer } => (
+                RefTarget::Pointer(pointer.as_str()),
+                format!("{pointer} does not exist"),
+            ),
+            ReferencingError::NoSuchAnchor { anchor }
+            | ReferencingError::InvalidAnchor { anchor } => (
+                RefTarget::Pointer(anchor.as_str()),
+                format!("anchor '{anchor}' does not exist"),
+            ),
+            other => (RefTarget::Unknown, other.to_string()),
+        };
+
+        match self.find_reference(&target) {
+            Some((fragment, pointer, reference)) => format!(
+                "Unresolvable $ref '{}' at {} in the {} schema fragment ({}): {}",
+                reference, pointer, fragment.label, fragment.file, reason
+            ),
+            None => format!("Unresolvable $ref in the schema fragments: {reason}"),
+        }
+    }
+
+    /// The fragment, JSON pointer and value of the first `$ref` pointing to
+    /// `target`.
+    fn find_reference(&self, target: &RefTarget<'_>) -> Option<(&Fragment, String, String)> {
+        self.fragments.iter().find_map(|fragment| {
+            let mut references = Vec::new();
+            collect_references(&fragment.schema, String::new(), &mut references);
+            references
+                .into_iter()
+                .find(|(_, reference)| points_to(fragment, reference, target))
+                .map(|(pointer, reference)| (fragment, pointer, reference))
+        })
+    }
+}
+
+impl Retrieve for FragmentResources {
+    fn retrieve(
+        &self,
+        uri: &Uri<String>,
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        self.lookup(uri.as_str())
+            .map(|fragment| fragment.schema.clone())
+            .ok_or_else(|| format!("no local copy of {}", uri.as_str()).into())
+    }
+}
+
+enum RefTarget<'a> {
+    /// A document that is not loaded.
+    Document(&'a str),
+    /// A pointer or anchor missing from a loaded document.
+    Pointer(&'a str),
+    Unknown,
+}
+
+/// Whether `reference`, found in `fragment`, points to `target`.
+fn points_to(fragment: &Fragment, reference: &str, target: &RefTarget<'_>) -> bool {
+    let (document, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+    match target {
+        RefTarget::Document(uri) => {
+            !document.is_empty()
+                && percent_decode(&resolve_reference(base_of(fragment), document))
+                    == percent_decode(strip_fragment(uri))
+        }
+        RefTarget::Pointer(missing) => percent_decode(pointer) == *missing,
+        RefTarget::Unknown => false,
+    }
+}
+
+/// Pointer to the schema of section `name` inside its fragment.
+pub(super) fn section_pointer(fragment: &Value, name: &str) -> Option<String> {
+    let token = encode_pointer_token(name);
+    [format!("/properties/{token}"), format!("/{token}")]
+        .into_iter()
+        .find(|pointer| fragment.pointer(pointer).is_some())
+}
+
+/// All `$ref` values below `schema` together with their JSON pointers.
+fn collect_references(schema: &Value, pointer: String, references: &mut Vec<(String, String)>) {
+    match schema {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let child = format!("{}/{}", pointer, encode_pointer_token(key));
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => {
+                        references.push((child, reference.clone()));
+                    }
+                    _ => collect_references(value, child, references),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_references(item, format!("{pointer}/{index}"), references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// URI that relative references inside `fragment` are resolved against.
+fn base_of(fragment: &Fragment) -> &str {
+    fragment.id.as_deref().unwrap_or(&fragment.uri)
+}
+
+/// Resolve a relative `reference` (without fragment) against `base`.
+fn resolve_reference(base: &str, reference: &str) -> String {
+    if reference.contains("://") || reference.starts_with("urn:") {
+        return reference.to_string();
+    }
+
+    let (origin, path) = match base.find("://") {
+        Some(scheme_end) => {
+            let path_start = base[scheme_end + 3..]
+                .find('/')
+                .map_or(base.len(), |i| scheme_end + 3 + i);
+            base.split_at(path_start)
+        }
+        None => ("", base),
+    };
+    let mut segments: Vec<&str> = if reference.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut segments: Vec<&str> = path.split('/').collect();
+        segments.pop();
+        segments
+    };
+    for segment in reference.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{}{}", origin, segments.join("/"))
+}
+
+/// Follow `reference`, found in `fragments[from]`, to the schema it points to
+/// and the index of the fragment containing it. Other fragments are matched
+/// by file name, as in [`FragmentResources`].
+pub(super) fn follow_reference<'a>(
+    sections: &[SectionSpec],
+    fragments: &'a [Value],
+    from: usize,
+    reference: &str,
+) -> Option<(usize, &'a Value)> {
+    let (document, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+    let index = if document.is_empty() {
+        from
+    } else {
+        let file = percent_decode(document.rsplit('/').next().unwrap_or(document));
+        sections.iter().position(|section| section.file == file)?
+    };
+    fragments
+        .get(index)?
+        .pointer(&percent_decode(pointer))
+        .map(|schema| (index, schema))
+}
+
+fn strip_fragment(uri: &str) -> &str {
+    uri.split_once('#').map_or(uri, |(document, _)| document)
+}
+
+/// Percent-encode everything but the characters allowed in a URI path or
+/// fragment, e.g. the spaces in `General Section.json`.
+pub(super) fn encode_uri_component(raw: &str) -> String {
+    let mut encoded = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(raw: &str) -> String {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut rest = raw.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%'
+            && let Some(hex) = tail.get(..2)
+            && let Ok(decoded) = u8::from_str_radix(&String::from_utf8_lossy(hex), 16)
+        {
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://example.org/schemas/main/schema/v2/";
+
+    fn sections(files: &[&str]) -> Vec<SectionSpec> {
+        files
+            .iter()
+            .map(|file| SectionSpec {
+                name: file.trim_end_matches(".json").to_string(),
+                file: file.to_string(),
+                label: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolves_internal_and_cross_fragment_refs() {
+        let general = json!({
+            "$defs": { "name": { "type": "string" } },
+            "properties": {
+                "general": {
+                    "type": "object",
+                    "properties": {
+                        "fileName": { "$ref": "#/$defs/name" },
+                        "unit": { "$ref": "tool.json#/$defs/unit" },
+                        "toolName": { "$ref": "https://example.org/schemas/main/schema/v2/tool.json#/$defs/name" }
+                    }
+                }
+            }
+        });
+        let tool = json!({
+            "$defs": {
+                "unit": { "enum": ["nm", "um"] },
+                "name": { "type": "string", "minLength": 1 }
+            },
+            "properties": { "tool": { "type": "object" } }
+        });
+        let resources = FragmentResources::new(
+            BASE,
+            &sections(&["general.json", "tool.json"]),
+            &[general, tool],
+        );
+
+        let validator = resources
+            .section_validator("general.json", "general")
+            .unwrap();
+
+        assert!(validator.is_valid(&json!({"fileName": "a.tif", "unit": "nm", "toolName": "SEM"})));
+        assert!(!validator.is_valid(&json!({"fileName": 1})));
+        assert!(!validator.is_valid(&json!({"unit": "mm"})));
+        assert!(!validator.is_valid(&json!({"toolName": ""})));
+    }
+
+    #[test]
+    fn test_resolves_refs_relative_to_declared_id() {
+        let general = json!({
+            "$id": "https://upstream.example/schema/v2/General%20Section.json",
+            "properties": {
+                "general": { "$ref": "Tool%20Section.json#/$defs/object" }
+            }
+        });
+        let tool = json!({ "$defs": { "object": { "type": "object" } } });
+        let resources = FragmentResources::new(
+            BASE,
+            &sections(&["General Section.json", "Tool Section.json"]),
+            &[general, tool],
+        );
+
+        let validator = resources
+            .section_validator("General Section.json", "general")
+            .unwrap();
+
+        assert!(validator.is_valid(&json!({})));
+        assert!(!validator.is_valid(&json!([])));
+    }
+
+    #[test]
+    fn test_unknown_document_names_fragment_and_pointer() {
+        let general = json!({
+            "properties": {
+                "general": {
+                    "properties": { "unit": { "$ref": "units.json#/$defs/length" } }
+                }
+            }
+        });
+        let resources = FragmentResources::new(BASE, &sections(&["general.json"]), &[general]);
+
+        let err = resources
+            .section_validator("general.json", "general")
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "Unresolvable $ref 'units.json#/$defs/length' at /properties/general/properties/unit/$ref \
+             in the general schema fragment (general.json): \
+             https://example.org/schemas/main/schema/v2/units.json is not one of the loaded schema fragments"
+        );
+    }
+
+    #[test]
+    fn test_missing_pointer_names_fragment_and_pointer() {
+        let general = json!({
+            "properties": { "general": { "$ref": "tool.json#/$defs/missing" } }
+        });
+        let tool = json!({ "$defs": {} });
+        let resources = FragmentResources::new(
+            BASE,
+            &sections(&["general.json", "tool.json"]),
+            &[general, tool],
+        );
+
+        let err = resources
+            .section_validator("general.json", "general")
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with(
+            "Unresolvable $ref 'tool.json#/$defs/missing' at /properties/general/$ref in the general schema fragment (general.json)"
+        ), "{err}");
+    }
+
+    #[test]
+    fn test_resolve_reference() {
+        assert_eq!(
+            resolve_reference("https://a.org/s/v2/x.json", "y.json"),
+            "https://a.org/s/v2/y.json"
+        );
+        assert_eq!(
+            resolve_reference("https://a.org/s/v2/x.json", "../common/y.json"),
+            "https://a.org/s/common/y.json"
+        );
+        assert_eq!(
+            resolve_reference("https://a.org/s/v2/x.json", "/y.json"),
+            "https://a.org/y.json"
+        );
+        assert_eq!(
+            resolve_reference("https://a.org/s/v2/x.json", "https://b.org/y.json"),
+            "https://b.org/y.json"
+        );
+    }
+
+    #[test]
+    fn test_encode_uri_component() {
+        assert_eq!(
+            encode_uri_component("General Section.json"),
+            "General%20Section.json"
+        );
+        assert_eq!(
+            encode_uri_component("/properties/Compressed Bits~1Pixel"),
+            "/properties/Compressed%20Bits~1Pixel"
+        );
+        assert_eq!(
+            percent_decode("General%20Section.json"),
+            "General Section.json"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Keys of a document that its schema does not define.
+//!
+//! The FAMH schemas allow additional properties, so a misspelt field such as
+//! `accelerationVoltage` is accepted silently. [`SchemaCache::unknown_fields`]
+//! compares every key with the `properties` of the schema at the same level
+//! and suggests the closest known key.
+
+use serde_json::Value;
+
+use super::SchemaCache;
+use super::describe::{MAX_REFERENCE_DEPTH, Node};
+use super::resolve::section_pointer;
+use crate::utils::{decode_pointer_token, encode_pointer_token};
+
+/// A key that is not defined by the schema at its level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    /// JSON pointer of the unknown key.
+    pub instance_path: String,
+    /// The closest known key at the same level, if one is close enough.
+    pub suggestion: Option<Suggestion>,
+}
+
+/// A known key suggested for an unknown one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub key: String,
+    /// The FAMH version whose naming the unknown key follows, when it only
+    /// differs from `key` in spelling convention, e.g. `Working Distance` (v1)
+    /// for `workingDistance` (v2).
+    pub naming: Option<&'static str>,
+}
+
+impl UnknownField {
+    /// The unknown key itself.
+    pub fn name(&self) -> String {
+        decode_pointer_token(self.instance_path.rsplit('/').next().unwrap_or_default())
+    }
+
+    /// Whether the key is a root-level section.
+    pub fn is_section(&self) -> bool {
+        self.instance_path.matches('/').count() == 1
+    }
+
+    /// `Unknown <kind> "<name>"`, followed by the suggestion if there is one.
+    pub fn describe(&self, kind: &str) -> String {
+        let name = self.name();
+        match &self.suggestion {
+            None => format!("Unknown {kind} \"{name}\""),
+            Some(Suggestion {
+                key,
+                naming: Some(naming),
+            }) => format!(
+                "Unknown {kind} \"{name}\" uses FAMH {naming} naming, did you mean \"{key}\"?"
+            ),
+            Some(Suggestion { key, naming: None }) => {
+                format!("Unknown {kind} \"{name}\", did you mean \"{key}\"?")
+            }
+        }
+    }
+}
+
+/// The candidate closest to `key`. Keys are compared case-insensitively and
+/// ignoring everything but letters and digits, so `Working Distance`,
+/// `working_distance` and `workingDistance` are the same key. Otherwise a
+/// candidate starting with `key` (or the other way round) or within an edit
+/// distance of a third of the key's length is suggested.
+pub fn closest_key<'a>(
+    key: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<Suggestion> {
+    let folded = fold(key);
+    if folded.is_empty() {
+        return None;
+    }
+    let limit = (folded.chars().count() / 3).max(1);
+
+    let mut best: Option<(usize, &str)> = None;
+    for candidate in candidates {
+        let candidate_folded = fold(candidate);
+        let distance = if candidate_folded == folded {
+            0
+        } else if folded.len() >= 4
+            && (candidate_folded.starts_with(&folded) || folded.starts_with(&candidate_folded))
+        {
+            1
+        } else {
+            strsim::damerau_levenshtein(&folded, &candidate_folded)
+        };
+        if distance <= limit && best.is_none_or(|(best, _)| distance < best) {
+            best = Some((distance, candidate));
+        }
+    }
+
+    let (distance, candidate) = best?;
+    let naming = match (key.contains(' '), candidate.contains(' ')) {
+        _ if distance > 0 || key == candidate => None,
+        (true, false) => Some("v1"),
+        (false, true) => Some("v2"),
+        _ => None,
+    };
+    Some(Suggestion {
+        key: candidate.to_string(),
+        naming,
+    })
+}
+
+fn fold(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl SchemaCache {
+    /// Keys of `document` that the schema does not define, root-level
+    /// sections included. Objects whose schema defines no `properties`, or
+    /// sets `additionalProperties` or `patternProperties`, may hold any key.
+    pub fn unknown_fields(&self, document: &Value) -> Vec<UnknownField> {
+        let mut unknown = Vec::new();
+        let Some(top_level) = document.as_object() else {
+            return unknown;
+        };
+        for (key, value) in top_level {
+            let pointer = format!("/{}", encode_pointer_token(key));
+            match self.section_node(key) {
+                Some(node) => self.collect_unknown(node, value, &pointer, 0, &mut unknown),
+                None if self.version.section(key).is_none() => unknown.push(UnknownField {
+                    instance_path: pointer,
+                    suggestion: closest_key(key, self.section_names()),
+                }),
+                None => {}
+            }
+        }
+        unknown
+    }
+
+    pub(super) fn section_node(&self, name: &str) -> Option<Node<'_>> {
+        let index = self
+            .version
+            .sections
+            .iter()
+            .position(|section| section.name == name)?;
+        let fragment = &self.fragments[index];
+        let schema = fragment.pointer(&section_pointer(fragment, name)?)?;
+        Some(Node {
+            fragment: index,
+            schema,
+        })
+    }
+
+    fn collect_unknown(
+        &self,
+        node: Node<'_>,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        unknown: &mut Vec<UnknownField>,
+    ) {
+        if depth >= MAX_REFERENCE_DEPTH {
+            return;
+        }
+        match value {
+            Value::Object(object) => {
+                let properties = self.properties(node);
+                let open = properties.is_empty()
+                    || self.reference_chain(node).iter().any(|node| {
+                        node.schema.get("additionalProperties").is_some()
+                            || node.schema.get("patternProperties").is_some()
+                    });
+                for (key, value) in object {
+                    let pointer = format!("{}/{}", pointer, encode_pointer_token(key));
+                    match properties.iter().find(|(name, _, _)| name == key) {
+                        Some((_, child, _)) => {
+                            self.collect_unknown(*child, value, &pointer, depth + 1, unknown)
+                        }
+                        None if !open => unknown.push(UnknownField {
+                            instance_path: pointer,
+                            suggestion: closest_key(
+                                key,
+                                properties.iter().map(|(name, _, _)| *name),
+                            ),
+                        }),
+                        None => {}
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let Some(item) = self.array_items(&self.reference_chain(node)) else {
+                    return;
+                };
+                for (index, value) in items.iter().enumerate() {
+                    let pointer = format!("{pointer}/{index}");
+                    self.collect_unknown(item, value, &pointer, depth + 1, unknown);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn suggestion(key: &str, candidates: &[&str]) -> Option<(String, Option<&'static str>)> {
+        closest_key(key, candidates.iter().copied())
+            .map(|suggestion| (suggestion.key, suggestion.naming))
+    }
+
+    #[test]
+    fn test_closest_key_folds_case_and_naming() {
+        let candidates = ["acceleratingVoltage", "workingDistance", "signalTypes"];
+        assert_eq!(
+            suggestion("accelerationVoltage", &candidates),
+            Some(("acceleratingVoltage".to_string(), None))
+        );
+        assert_eq!(
+            suggestion("Working Distance", &candidates),
+            Some(("workingDistance".to_string(), Some("v1")))
+        );
+        assert_eq!(
+            suggestion("Signal Type(s)", &candidates),
+            Some(("signalTypes".to_string(), Some("v1")))
+        );
+        assert_eq!(suggestion("detector", &candidates), None);
+
+        assert_eq!(
+            suggestion("Working distance", &["Working Distance", "Magnification"]),
+            Some(("Working Distance".to_string(), None))
+        );
+        assert_eq!(
+            suggestion("workingDistance", &["Working Distance"]),
+            Some(("Working Distance".to_string(), Some("v2")))
+        );
+        assert_eq!(
+            suggestion("General", &["generalSection", "methodSpecific"]),
+            Some(("generalSection".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_found_at_every_level() {
+        let schema = SchemaCache::fixture("v2");
+        let mut document: Value = serde_json::from_str(
+            &std::fs::read_to_string("tests/fixtures/v2/complete_example_v2.json").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(schema.unknown_fields(&document), Vec::new());
+
+        document["General"] = json!({});
+        document["generalSection"]["Pixel Width"] = json!({ "value": 1, "unit": "nm" });
+        document["methodSpecific"]["scanningElectronMicroscopy"]["accelerationVoltage"] =
+            json!({ "value": 5, "unit": "kV" });
+
+        let unknown = schema.unknown_fields(&document);
+        let messages: Vec<String> = unknown
+            .iter()
+            .map(|field| format!("{}: {}", field.instance_path, field.describe("field")))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "/General: Unknown field \"General\", did you mean \"generalSection\"?",
+                "/generalSection/Pixel Width: Unknown field \"Pixel Width\" uses FAMH v1 naming, did you mean \"pixelWidth\"?",
+                "/methodSpecific/scanningElectronMicroscopy/accelerationVoltage: Unknown field \"accelerationVoltage\", did you mean \"acceleratingVoltage\"?",
+            ]
+        );
+        assert!(unknown[0].is_section());
+        assert!(!unknown[1].is_section());
+    }
+}
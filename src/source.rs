@@ -0,0 +1,565 @@
+//! Source locations of JSON values.
+//!
+//! `serde_json::Value` does not remember where a value was written. A
+//! [`SourceMap`] scans the document text once and maps the JSON pointer of
+//! every value to its span, so problems reported by pointer can also be shown
+//! by line and column, with an excerpt of the offending line.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_json::error::Category;
+
+use crate::utils::{display_path, encode_pointer_token, read_input};
+
+/// A 1-based line and column. Columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A source line with the offending part underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub location: SourceLocation,
+    /// The text of the line, without the line break.
+    pub line: String,
+    /// Number of characters underlined, starting at `location.column`.
+    pub width: usize,
+}
+
+impl Snippet {
+    /// Render the snippet in the style of compiler diagnostics:
+    ///
+    /// ```text
+    ///   --> header.json:3:17
+    ///    |
+    ///  3 |     "fileName": "",
+    ///    |                 ^^
+    /// ```
+    pub fn render(&self, path: &str) -> String {
+        let number = self.location.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Keep tabs, so the underline lines up with the source line.
+        let indent: String = self
+            .line
+            .chars()
+            .take(self.location.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let mut out = String::new();
+        let _ = writeln!(out, "{gutter}--> {}:{}", path, self.location);
+        let _ = writeln!(out, "{gutter} |");
+        let _ = writeln!(out, "{number} | {}", self.line);
+        let _ = writeln!(out, "{gutter} | {indent}{}", "^".repeat(self.width.max(1)));
+        out
+    }
+}
+
+/// Where a value was written: its key, if it is an object member, and the
+/// value itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    key: Option<Range<usize>>,
+    value: Range<usize>,
+}
+
+/// The text of a JSON document and the spans of its values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    text: String,
+    /// Line number of the first line of `text`, for documents that are part
+    /// of a larger input such as an NDJSON stream.
+    first_line: usize,
+    line_starts: Vec<usize>,
+    entries: HashMap<String, Entry>,
+}
+
+impl SourceMap {
+    /// Map the values of `text`. Values after a syntax error are not mapped.
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        let mut scanner = Scanner {
+            text: &text,
+            pos: 0,
+            entries: HashMap::new(),
+        };
+        scanner.value(String::new(), None);
+        let entries = scanner.entries;
+        Self {
+            text,
+            first_line: 1,
+            line_starts,
+            entries,
+        }
+    }
+
+    /// Number lines from `line` on, so that locations are lines of the input
+    /// the text was taken from.
+    pub fn with_first_line(mut self, line: usize) -> Self {
+        self.first_line = line.max(1);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Deserialize the document. Errors name `path`, the line and the column,
+    /// followed by a snippet of the line. Values of the wrong type are
+    /// underlined as a whole, like values reported by the schema.
+    pub fn deserialize<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        serde_json::from_str(&self.text).map_err(|e| {
+            let Some(snippet) = self.value_snippet_at(&e) else {
+                let mut message = self.located_error(path, &e);
+                if let Some(snippet) = self.snippet_at(SourceLocation {
+                    line: e.line() + self.first_line - 1,
+                    column: e.column(),
+                }) {
+                    message.push('\n');
+                    message.push_str(snippet.render(path).trim_end());
+                }
+                return message;
+            };
+            format!(
+                "{}:{}: {}\n{}",
+                path,
+                snippet.location,
+                error_message(&e),
+                snippet.render(path).trim_end()
+            )
+        })
+    }
+
+    /// Snippet of the innermost value containing the position of a data
+    /// error, e.g. a string where a number is expected.
+    fn value_snippet_at(&self, err: &serde_json::Error) -> Option<Snippet> {
+        if err.classify() != Category::Data || err.line() == 0 || err.column() == 0 {
+            return None;
+        }
+        // serde_json counts bytes and reports the position after the last
+        // byte it read, i.e. the end of the offending value.
+        let offset = self.line_starts.get(err.line() - 1)? + err.column() - 1;
+        let (pointer, _) = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.value.contains(&offset))
+            .min_by_key(|(_, entry)| entry.value.len())?;
+        self.snippet(pointer)
+    }
+
+    /// [`located_error`] for an error in the text, with lines counted from
+    /// the first line of the map.
+    pub fn located_error(&self, path: &str, err: &serde_json::Error) -> String {
+        located_error_from(path, err, self.first_line)
+    }
+
+    /// Location of the value at `pointer`, or of its closest mapped ancestor
+    /// if the value does not exist, e.g. a missing required property.
+    pub fn location(&self, pointer: &str) -> Option<SourceLocation> {
+        self.snippet(pointer).map(|snippet| snippet.location)
+    }
+
+    /// Snippet underlining the value at `pointer`, or its closest mapped
+    /// ancestor. Values spanning several lines underline their key instead.
+    pub fn snippet(&self, pointer: &str) -> Option<Snippet> {
+        let entry = self.entry(pointer)?;
+        let range = match &entry.key {
+            Some(key) if self.line_end(entry.value.start) < entry.value.end => key.clone(),
+            _ => entry.value.clone(),
+        };
+        Some(self.snippet_of(range))
+    }
+
+    /// Snippet underlining the key of the member at `pointer`, e.g. of an
+    /// unknown property. Falls back to [`SourceMap::snippet`].
+    pub fn key_snippet(&self, pointer: &str) -> Option<Snippet> {
+        match self
+            .entries
+            .get(pointer)
+            .and_then(|entry| entry.key.clone())
+        {
+            Some(key) => Some(self.snippet_of(key)),
+            None => self.snippet(pointer),
+        }
+    }
+
+    /// Byte range of the key of the member at `pointer`, quotes included.
+    pub fn key_range(&self, pointer: &str) -> Option<Range<usize>> {
+        self.entries.get(pointer)?.key.clone()
+    }
+
+    /// Byte range of the value at `pointer`.
+    pub fn value_range(&self, pointer: &str) -> Option<Range<usize>> {
+        Some(self.entries.get(pointer)?.value.clone())
+    }
+
+    /// Snippet underlining `range`, up to the end of its first line.
+    fn snippet_of(&self, range: Range<usize>) -> Snippet {
+        let end = range.end.min(self.line_end(range.start));
+        let location = self.location_of(range.start);
+        Snippet {
+            width: self.text[range.start..end].chars().count(),
+            line: self.line_text(location.line).to_string(),
+            location,
+        }
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .find(['\n', '\r'])
+            .map_or(self.text.len(), |end| offset + end)
+    }
+
+    /// Snippet underlining the character at `location`, e.g. of a parse error.
+    pub fn snippet_at(&self, location: SourceLocation) -> Option<Snippet> {
+        if location.line < self.first_line
+            || location.line - self.first_line >= self.line_starts.len()
+        {
+            return None;
+        }
+        let line = self.line_text(location.line);
+        Some(Snippet {
+            location: SourceLocation {
+                line: location.line,
+                column: location.column.clamp(1, line.chars().count() + 1),
+            },
+            line: line.to_string(),
+            width: 1,
+        })
+    }
+
+    fn entry(&self, pointer: &str) -> Option<&Entry> {
+        let mut pointer = pointer;
+        loop {
+            if let Some(entry) = self.entries.get(pointer) {
+                return Some(entry);
+            }
+            pointer = &pointer[..pointer.rfind('/')?];
+        }
+    }
+
+    fn location_of(&self, offset: usize) -> SourceLocation {
+        let index = self.line_starts.partition_point(|start| *start <= offset);
+        let start = self.line_starts[index - 1];
+        SourceLocation {
+            line: index + self.first_line - 1,
+            column: self.text[start..offset].chars().count() + 1,
+        }
+    }
+
+    fn line_text(&self, line: usize) -> &str {
+        let index = line - self.first_line;
+        let start = self.line_starts[index];
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        self.text[start..end].trim_end_matches('\r')
+    }
+}
+
+/// Read and parse the JSON document at `path`, or on standard input for `-`,
+/// keeping its source map.
+pub fn read_json(path: &str) -> Result<(Value, SourceMap), Box<dyn std::error::Error>> {
+    let source = SourceMap::new(read_input(path)?);
+    let document =
+        serde_json::from_str(source.text()).map_err(|e| located_error(display_path(path), &e))?;
+    Ok((document, source))
+}
+
+/// `path:line:column: message` for a JSON error, or `path: message` if the
+/// error has no location.
+pub fn located_error(path: &str, err: &serde_json::Error) -> String {
+    located_error_from(path, err, 1)
+}
+
+fn located_error_from(path: &str, err: &serde_json::Error, first_line: usize) -> String {
+    if err.line() == 0 {
+        return format!("{path}: {err}");
+    }
+    format!(
+        "{}:{}:{}: {}",
+        path,
+        err.line() + first_line - 1,
+        err.column(),
+        error_message(err)
+    )
+}
+
+/// The message of a JSON error without its ` at line L column C` suffix.
+fn error_message(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    let suffix = format!(" at line {} column {}", err.line(), err.column());
+    match message.strip_suffix(&suffix) {
+        Some(stripped) => stripped.to_string(),
+        None => message,
+    }
+}
+
+/// Records the spans of values while skipping over the document. Invalid
+/// input ends the scan; whatever was mapped until then is kept.
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+    entries: HashMap<String, Entry>,
+}
+
+impl Scanner<'_> {
+    fn value(&mut self, pointer: String, key: Option<Range<usize>>) -> Option<()> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek()? {
+            b'{' => self.object(&pointer)?,
+            b'[' => self.array(&pointer)?,
+            b'"' => {
+                self.string()?;
+            }
+            _ => self.scalar()?,
+        }
+        self.entries.insert(
+            pointer,
+            Entry {
+                key,
+                value: start..self.pos,
+            },
+        );
+        Some(())
+    }
+
+    fn object(&mut self, pointer: &str) -> Option<()> {
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Some(());
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            let name: String = serde_json::from_str(&self.text[key.clone()]).ok()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return None;
+            }
+            self.value(
+                format!("{}/{}", pointer, encode_pointer_token(&name)),
+                Some(key),
+            )?;
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Some(());
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &str) -> Option<()> {
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Some(());
+        }
+        for index in 0.. {
+            self.value(format!("{pointer}/{index}"), None)?;
+            self.skip_whitespace();
+            if self.eat(b']') {
+                break;
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    /// Skip a string, returning its span including the quotes.
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        if !self.eat(b'"') {
+            return None;
+        }
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(start..self.pos);
+                }
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Option<()> {
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let matched = self.peek() == Some(byte);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"{
+  "generalSection": {
+    "fileName": "",
+    "imageWidth": { "value": "wide", "unit": "px" },
+    "a/b": [1, 22]
+  }
+}
+"#;
+
+    #[test]
+    fn test_values_are_located_by_pointer() {
+        let source = SourceMap::new(DOCUMENT);
+
+        let snippet = source.snippet("/generalSection/imageWidth/value").unwrap();
+        assert_eq!(
+            snippet.location,
+            SourceLocation {
+                line: 4,
+                column: 30
+            }
+        );
+        assert_eq!(snippet.width, 6);
+        assert_eq!(
+            source.location("/generalSection/a~1b/1"),
+            Some(SourceLocation {
+                line: 5,
+                column: 16
+            })
+        );
+        // Missing values fall back to their parent.
+        assert_eq!(
+            source.location("/generalSection/fileName/missing"),
+            Some(SourceLocation {
+                line: 3,
+                column: 17
+            })
+        );
+        // Values spanning lines are located by their key.
+        let section = source.snippet("/generalSection").unwrap();
+        assert_eq!(section.location, SourceLocation { line: 2, column: 3 });
+        assert_eq!(section.width, "\"generalSection\"".len());
+        assert_eq!(
+            source.location(""),
+            Some(SourceLocation { line: 1, column: 1 })
+        );
+    }
+
+    #[test]
+    fn test_snippet_underlines_the_value() {
+        let source = SourceMap::new(DOCUMENT);
+
+        let rendered = source
+            .snippet("/generalSection/fileName")
+            .unwrap()
+            .render("h.json");
+
+        assert_eq!(
+            rendered,
+            concat!(
+                " --> h.json:3:17\n",
+                "  |\n",
+                "3 |     \"fileName\": \"\",\n",
+                "  |                 ^^\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_locations_count_from_the_first_line() {
+        let source = SourceMap::new(DOCUMENT).with_first_line(41);
+
+        let snippet = source.snippet("/generalSection/fileName").unwrap();
+        assert_eq!(
+            snippet.location,
+            SourceLocation {
+                line: 43,
+                column: 17
+            }
+        );
+        assert_eq!(snippet.line, "    \"fileName\": \"\",");
+        assert_eq!(
+            source.snippet_at(SourceLocation { line: 1, column: 1 }),
+            None
+        );
+
+        let source = SourceMap::new("{\"a\" 1}").with_first_line(7);
+        let err = serde_json::from_str::<serde_json::Value>(source.text()).unwrap_err();
+        assert_eq!(
+            source.located_error("s.ndjson", &err),
+            "s.ndjson:7:6: expected `:`"
+        );
+    }
+
+    #[test]
+    fn test_errors_are_located() {
+        let source = SourceMap::new("{\n  \"generalSection\": {\"bitDepth\": \"8\"}\n}");
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Header {
+            #[serde(rename = "generalSection")]
+            general: General,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct General {
+            #[serde(rename = "bitDepth")]
+            bit_depth: i64,
+        }
+
+        let err = source.deserialize::<Header>("h.json").unwrap_err();
+
+        assert_eq!(
+            err,
+            "h.json:2:34: invalid type: string \"8\", expected i64\n \
+             --> h.json:2:34\n  |\n\
+             2 |   \"generalSection\": {\"bitDepth\": \"8\"}\n  \
+             |                                  ^^^"
+        );
+
+        let err = serde_json::from_str::<serde_json::Value>("{\n  \"a\" 1\n}").unwrap_err();
+        assert_eq!(located_error("h.json", &err), "h.json:2:7: expected `:`");
+    }
+}
@@ -0,0 +1,334 @@
+//! Physical dimensions of FAMH `{value, unit}` fields.
+//!
+//! The schemas accept any string as a unit. [`UnitRegistry`] knows the
+//! dimension each field expects, e.g. a voltage for `acceleratingVoltage`, and
+//! the dimension of each unit, so `"mm"` for a voltage or an unknown unit can
+//! be reported. Field names of v1 and v2 are both registered.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::utils::encode_pointer_token;
+
+/// The physical dimension of a unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dimension {
+    Length,
+    Voltage,
+    Current,
+    Angle,
+    Time,
+}
+
+impl Dimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dimension::Length => "length",
+            Dimension::Voltage => "voltage",
+            Dimension::Current => "current",
+            Dimension::Angle => "angle",
+            Dimension::Time => "time",
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Units by dimension. Micro is accepted as micro sign, Greek mu and `u`.
+const UNITS: &[(Dimension, &[&str])] = &[
+    (
+        Dimension::Length,
+        &[
+            "m", "cm", "mm", "µm", "μm", "um", "nm", "pm", "Å", "angstrom",
+        ],
+    ),
+    (Dimension::Voltage, &["V", "mV", "kV", "MV"]),
+    (
+        Dimension::Current,
+        &["A", "mA", "µA", "μA", "uA", "nA", "pA", "fA"],
+    ),
+    (
+        Dimension::Angle,
+        &[
+            "deg", "degree", "degrees", "°", "rad", "mrad", "µrad", "μrad", "urad",
+        ],
+    ),
+    (
+        Dimension::Time,
+        &[
+            "s", "ms", "µs", "μs", "us", "ns", "min", "h", "sec", "second", "seconds",
+        ],
+    ),
+];
+
+/// Expected dimension of each field, by v2 and v1 name.
+const FIELDS: &[(Dimension, &[&str])] = &[
+    (
+        Dimension::Length,
+        &[
+            "pixelWidth",
+            "Pixel Width",
+            "pixelHeight",
+            "Pixel Height",
+            "workingDistance",
+            "Working Distance",
+            "stagePosition",
+        ],
+    ),
+    (
+        Dimension::Voltage,
+        &[
+            "acceleratingVoltage",
+            "Accelerating Voltage",
+            "deceleratingVoltage",
+            "Decelerating Voltage",
+        ],
+    ),
+    (
+        Dimension::Current,
+        &[
+            "probeCurrent",
+            "Probe Current",
+            "emissionCurrent",
+            "Emission Current",
+            "ionCurrent",
+        ],
+    ),
+    (
+        Dimension::Angle,
+        &[
+            "stageRotationRx",
+            "Stage Rotation Rx",
+            "stageRotationRy",
+            "Stage Rotation Ry",
+            "stageRotationRz",
+            "Stage Rotation Rz",
+            "fibTiltAngle",
+            "FIB Tilt Angle",
+            "correctedTiltAngle",
+            "Corrected Tilt Angle",
+            "scanRotation",
+            "Scan Rotation",
+            "Rotation Angle",
+        ],
+    ),
+    (Dimension::Time, &["exposureTime", "Exposure Time"]),
+];
+
+/// A `{value, unit}` field whose unit is unknown or of the wrong dimension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitViolation {
+    /// JSON pointer of the `unit` (v2) or `Unit` (v1) property.
+    pub instance_path: String,
+    pub expected: Dimension,
+    pub message: String,
+}
+
+/// Dimensions of units and of the fields that carry them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitRegistry {
+    units: BTreeMap<String, Dimension>,
+    fields: BTreeMap<String, Dimension>,
+}
+
+impl Default for UnitRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            units: BTreeMap::new(),
+            fields: BTreeMap::new(),
+        };
+        for (dimension, units) in UNITS {
+            for unit in *units {
+                registry.add_unit(unit, *dimension);
+            }
+        }
+        for (dimension, fields) in FIELDS {
+            for field in *fields {
+                registry.add_field(field, *dimension);
+            }
+        }
+        registry
+    }
+}
+
+impl UnitRegistry {
+    /// The built-in units and FAMH fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `unit` as a unit of `dimension`.
+    pub fn add_unit(&mut self, unit: &str, dimension: Dimension) {
+        self.units.insert(unit.to_string(), dimension);
+    }
+
+    /// Register the dimension expected by fields named `field`.
+    pub fn add_field(&mut self, field: &str, dimension: Dimension) {
+        self.fields.insert(field.to_string(), dimension);
+    }
+
+    /// Dimension of `unit`, or `None` for unknown units.
+    pub fn unit_dimension(&self, unit: &str) -> Option<Dimension> {
+        self.units.get(unit.trim()).copied()
+    }
+
+    /// Dimension expected by fields named `field`.
+    pub fn field_dimension(&self, field: &str) -> Option<Dimension> {
+        self.fields.get(field).copied()
+    }
+
+    /// Units of `dimension`, in registration order of the built-in units.
+    fn examples(&self, dimension: Dimension) -> Vec<&str> {
+        UNITS
+            .iter()
+            .filter(|(unit_dimension, _)| *unit_dimension == dimension)
+            .flat_map(|(_, units)| units.iter().copied())
+            .filter(|unit| unit.is_ascii())
+            .take(4)
+            .collect()
+    }
+
+    /// Check the unit of every registered field in `document`. `pointer` is
+    /// the JSON pointer of `document` itself, prefixed to the reported paths.
+    pub fn check(&self, document: &Value, pointer: &str) -> Vec<UnitViolation> {
+        let mut violations = Vec::new();
+        self.check_value(document, pointer, &mut violations);
+        violations
+    }
+
+    fn check_value(&self, value: &Value, pointer: &str, violations: &mut Vec<UnitViolation>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    let pointer = format!("{}/{}", pointer, encode_pointer_token(key));
+                    if let Some(expected) = self.field_dimension(key) {
+                        self.check_field(key, value, expected, &pointer, violations);
+                    }
+                    self.check_value(value, &pointer, violations);
+                }
+            }
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.check_value(item, &format!("{pointer}/{index}"), violations);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_field(
+        &self,
+        field: &str,
+        value: &Value,
+        expected: Dimension,
+        pointer: &str,
+        violations: &mut Vec<UnitViolation>,
+    ) {
+        // A missing unit or one of the wrong type is left to the schema.
+        let Some((key, unit)) = ["unit", "Unit"]
+            .iter()
+            .find_map(|key| Some((*key, value.get(key)?.as_str()?)))
+        else {
+            return;
+        };
+        let dimension = self.unit_dimension(unit);
+        if dimension == Some(expected) {
+            return;
+        }
+        let examples = self.examples(expected).join(", ");
+        let message = match dimension {
+            Some(actual) => format!(
+                "'{unit}' is a {actual} unit but {field} expects a {expected}, e.g. {examples}"
+            ),
+            None => format!("Unknown unit '{unit}', {field} expects a {expected}, e.g. {examples}"),
+        };
+        violations.push(UnitViolation {
+            instance_path: format!("{pointer}/{key}"),
+            expected,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_units_and_fields_have_dimensions() {
+        let registry = UnitRegistry::new();
+        assert_eq!(registry.unit_dimension("kV"), Some(Dimension::Voltage));
+        assert_eq!(registry.unit_dimension("µm"), Some(Dimension::Length));
+        assert_eq!(registry.unit_dimension("μm"), Some(Dimension::Length));
+        assert_eq!(registry.unit_dimension("A"), Some(Dimension::Current));
+        assert_eq!(registry.unit_dimension("furlong"), None);
+        assert_eq!(
+            registry.field_dimension("Probe Current"),
+            Some(Dimension::Current)
+        );
+        assert_eq!(registry.field_dimension("imageWidth"), None);
+    }
+
+    #[test]
+    fn test_check_reports_wrong_and_unknown_units() {
+        let registry = UnitRegistry::new();
+        let section = json!({
+            "scanningElectronMicroscopy": {
+                "acceleratingVoltage": { "value": 5, "unit": "mm" },
+                "workingDistance": { "value": 8.5, "unit": "mm" },
+                "probeCurrent": { "value": 100, "unit": "picoamp" },
+                "exposureTime": { "value": 2 }
+            }
+        });
+
+        let violations = registry.check(&section, "/methodSpecific");
+
+        assert_eq!(
+            violations,
+            vec![
+                UnitViolation {
+                    instance_path:
+                        "/methodSpecific/scanningElectronMicroscopy/acceleratingVoltage/unit"
+                            .to_string(),
+                    expected: Dimension::Voltage,
+                    message:
+                        "'mm' is a length unit but acceleratingVoltage expects a voltage, e.g. V, mV, kV, MV"
+                            .to_string(),
+                },
+                UnitViolation {
+                    instance_path: "/methodSpecific/scanningElectronMicroscopy/probeCurrent/unit"
+                        .to_string(),
+                    expected: Dimension::Current,
+                    message:
+                        "Unknown unit 'picoamp', probeCurrent expects a current, e.g. A, mA, uA, nA"
+                            .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_reads_v1_units() {
+        let registry = UnitRegistry::new();
+        let section = json!({
+            "Stage Rotation Rz": { "Value": 90, "Unit": "kV" },
+            "ROIs": [{ "Rotation Angle": { "Value": 15.0, "Unit": "deg" } }]
+        });
+
+        let violations = registry.check(&section, "/General Section");
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].instance_path,
+            "/General Section/Stage Rotation Rz/Unit"
+        );
+        assert_eq!(violations[0].expected, Dimension::Angle);
+    }
+}
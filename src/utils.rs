@@ -1,19 +1,49 @@
 // Utility functions
+use crate::source::located_error;
 use serde_json::Value;
 use std::fs;
 use std::io::{Error, ErrorKind, Write};
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tempfile::Builder;
 
+/// Path naming standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// How standard input is named in reports and messages.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Read the file at `path`, or standard input for [`STDIN_PATH`].
+pub fn read_input(path: &str) -> std::io::Result<String> {
+    if path == STDIN_PATH {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// `path` as shown in messages: [`STDIN_NAME`] for standard input.
+pub fn display_path(path: &str) -> &str {
+    if path == STDIN_PATH { STDIN_NAME } else { path }
+}
+
 pub fn load_json(path: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let json_text = std::fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&json_text)?)
+    let json_text = read_input(path)?;
+    Ok(serde_json::from_str(&json_text).map_err(|e| located_error(display_path(path), &e))?)
 }
 
-fn encode_pointer_token(token: &str) -> String {
+/// Escape a key as a JSON Pointer token: `~` as `~0` and `/` as `~1`.
+pub(crate) fn encode_pointer_token(token: &str) -> String {
     token.replace('~', "~0").replace('/', "~1")
 }
 
+/// The key a JSON Pointer token stands for, undoing [`encode_pointer_token`].
+pub(crate) fn decode_pointer_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
 pub fn field_to_json_pointer(field: &str) -> String {
     if field.starts_with('/') {
         return field.to_string();
@@ -62,6 +92,68 @@ pub fn write_bytes_atomically(
     }
 }
 
+/// Parse a duration such as `90s`, `30m`, `12h` or `7d`. A bare number is
+/// read as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{input}', expected e.g. 30m, 12h or 7d"))?;
+    let factor = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        other => return Err(format!("unknown duration unit '{other}', use s, m, h or d")),
+    };
+    number
+        .checked_mul(factor)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration '{input}' is too large"))
+}
+
+/// Number of threads used for parallel work unless configured otherwise.
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Apply `f` to every item on up to `jobs` threads. The results are returned
+/// in the order of `items`.
+pub fn parallel_map<T, R>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    let jobs = jobs.clamp(1, items.len().max(1));
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return done;
+                        };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread panicked"))
+            .collect()
+    });
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,6 +203,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pointer_tokens_round_trip() {
+        for key in ["Compressed Bits/Pixel", "value~raw", "a~1b", "~/"] {
+            assert_eq!(decode_pointer_token(&encode_pointer_token(key)), key);
+        }
+        assert_eq!(encode_pointer_token("~/"), "~0~1");
+    }
+
     #[test]
     fn test_field_to_json_pointer_preserves_explicit_json_pointer_input() {
         assert_eq!(
@@ -118,4 +218,32 @@ mod tests {
             "/General Section/Compressed Bits~1Pixel"
         );
     }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(
+            parse_duration("7d"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("3w").is_err());
+        assert!(parse_duration("-1h").is_err());
+    }
+
+    #[test]
+    fn test_parallel_map_keeps_input_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let squares = parallel_map(&items, 4, |n| n * n);
+        assert_eq!(squares, items.iter().map(|n| n * n).collect::<Vec<_>>());
+        assert!(parallel_map(&[] as &[usize], 4, |n| *n).is_empty());
+    }
 }
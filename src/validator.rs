@@ -0,0 +1,671 @@
+//! Reusable validation of FAMH documents.
+//!
+//! [`FamhValidator`] compiles the validators of all sections of a schema
+//! version once. It is `Send + Sync`, so a single instance can validate any
+//! number of documents, from any number of threads, without recompiling.
+//! Besides the schema, the units of `{value, unit}` fields are checked against
+//! a [`UnitRegistry`], and keys the schema does not define are reported with
+//! the closest known key. [`Profile`]s add stricter requirements on top.
+
+use famh_model::{v1, v2};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::profile::Profile;
+use crate::schema::{SchemaCache, SchemaVersion, UnknownField, VersionSpec};
+use crate::units::{UnitRegistry, UnitViolation};
+use crate::utils::{decode_pointer_token, encode_pointer_token, field_to_json_pointer};
+
+/// A typed FAMH header from `famh_model`.
+pub trait FamhHeader: Serialize {
+    const VERSION: SchemaVersion;
+}
+
+impl FamhHeader for v1::FaMetadataHeader {
+    const VERSION: SchemaVersion = SchemaVersion::V1;
+}
+
+impl FamhHeader for v2::FaMetadataHeader {
+    const VERSION: SchemaVersion = SchemaVersion::V2;
+}
+
+/// Compiled validators for every section of a schema version.
+pub struct FamhValidator {
+    version: VersionSpec,
+    required: Vec<String>,
+    sections: Vec<CompiledSection>,
+    units: UnitRegistry,
+    schema: SchemaCache,
+    profiles: Vec<CompiledProfile>,
+}
+
+struct CompiledSection {
+    name: String,
+    /// Location of the section schema, prefixed to the schema paths of errors.
+    location: String,
+    validator: jsonschema::Validator,
+}
+
+struct CompiledProfile {
+    name: String,
+    /// Overlay validators by section name.
+    overlays: Vec<(String, jsonschema::Validator)>,
+    required: Vec<String>,
+    /// Field as written in the profile, its JSON pointer and allowed values.
+    allowed: Vec<(String, String, Vec<Value>)>,
+}
+
+/// Outcome of validating a single document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationOutcome {
+    /// Root-level keys that are not sections of the schema version.
+    pub unknown_sections: Vec<String>,
+    /// Keys the schema does not define at their level, unknown sections
+    /// included, with the closest known key.
+    pub unknown_fields: Vec<UnknownField>,
+    /// Required sections missing from the document.
+    pub missing_sections: Vec<String>,
+    /// Sections present in the document, in manifest order.
+    pub sections: Vec<SectionOutcome>,
+    /// Unknown units and units of the wrong dimension, with the keyword
+    /// `unit`. Like unknown sections, they only make the document invalid in
+    /// `strict` mode.
+    pub unit_violations: Vec<SchemaViolation>,
+}
+
+/// Schema violations found in a single root-level section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionOutcome {
+    pub name: String,
+    pub errors: Vec<SchemaViolation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer into the document, starting with the section name.
+    pub instance_path: String,
+    /// The failing schema keyword, e.g. `type` or `required`, or `unit` for
+    /// units of the wrong dimension.
+    pub keyword: String,
+    /// Fragment file and JSON pointer of the failing keyword, e.g.
+    /// `generalSection.json#/properties/generalSection/properties/fileName/type`,
+    /// or `units#/<dimension>` for unit violations. Profile violations start
+    /// with the profile name instead, e.g. `automotive#/required/0`.
+    pub schema_path: String,
+    pub message: String,
+    /// Name of the profile that was violated, `None` for the schema itself.
+    pub profile: Option<String>,
+}
+
+impl FamhValidator {
+    /// Compile the validators of all sections of `schema_cache`.
+    pub fn new(schema_cache: &SchemaCache) -> Result<Self, Box<dyn std::error::Error>> {
+        let sections = schema_cache
+            .version()
+            .sections
+            .iter()
+            .map(|section| {
+                let validator = schema_cache.section_validator(&section.name)?;
+                let location = schema_cache
+                    .section_location(&section.name)
+                    .unwrap_or_else(|| format!("{}#", section.file));
+                Ok(CompiledSection {
+                    name: section.name.clone(),
+                    location,
+                    validator,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+        Ok(Self {
+            version: schema_cache.version().clone(),
+            required: schema_cache.required_sections().to_vec(),
+            sections,
+            units: UnitRegistry::new(),
+            schema: schema_cache.clone(),
+            profiles: Vec::new(),
+        })
+    }
+
+    /// Also check the requirements of `profile`. Fails if the profile is
+    /// written for another schema version, names a section this version does
+    /// not have or has an overlay that does not compile.
+    pub fn with_profile(mut self, profile: &Profile) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(version) = &profile.version
+            && !version.eq_ignore_ascii_case(&self.version.name)
+            && !version.eq_ignore_ascii_case(self.version.folder())
+        {
+            return Err(format!(
+                "Profile '{}' is written for schema {}, not {}",
+                profile.name, version, self.version.name
+            )
+            .into());
+        }
+
+        let overlays = profile
+            .sections
+            .iter()
+            .map(|(section, schema)| {
+                self.check_profile_section(profile, section)?;
+                let validator = self.schema.overlay_validator(schema).map_err(|e| {
+                    format!(
+                        "Invalid overlay for section '{}' in profile '{}': {}",
+                        section, profile.name, e
+                    )
+                })?;
+                Ok((section.clone(), validator))
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        let required = profile.required_pointers();
+        let allowed: Vec<(String, String, Vec<Value>)> = profile
+            .allowed
+            .iter()
+            .map(|(field, values)| (field.clone(), field_to_json_pointer(field), values.clone()))
+            .collect();
+        for pointer in required
+            .iter()
+            .chain(allowed.iter().map(|(_, pointer, _)| pointer))
+        {
+            self.check_profile_section(profile, &section_of(pointer))?;
+        }
+
+        self.profiles.push(CompiledProfile {
+            name: profile.name.clone(),
+            overlays,
+            required,
+            allowed,
+        });
+        Ok(self)
+    }
+
+    fn check_profile_section(
+        &self,
+        profile: &Profile,
+        section: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.version.section(section).is_none() {
+            return Err(format!(
+                "Profile '{}' refers to section '{}', which schema {} does not have",
+                profile.name, section, self.version.name
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Check units against `units` instead of the built-in registry.
+    pub fn with_units(mut self, units: UnitRegistry) -> Self {
+        self.units = units;
+        self
+    }
+
+    pub fn version(&self) -> &VersionSpec {
+        &self.version
+    }
+
+    /// Validate a FAMH document.
+    pub fn validate(
+        &self,
+        document: &Value,
+    ) -> Result<ValidationOutcome, Box<dyn std::error::Error>> {
+        let Some(top_level) = document.as_object() else {
+            return Err("Input JSON must be an object at top level".into());
+        };
+
+        let mut unknown_sections: Vec<String> = top_level
+            .keys()
+            .filter(|key| self.version.section(key).is_none())
+            .cloned()
+            .collect();
+        unknown_sections.sort_unstable();
+
+        let mut missing_sections: Vec<String> = self
+            .required
+            .iter()
+            .filter(|required| !top_level.contains_key(*required))
+            .cloned()
+            .collect();
+        missing_sections.sort_unstable();
+
+        let mut unit_violations = Vec::new();
+        let mut sections: Vec<SectionOutcome> = self
+            .sections
+            .iter()
+            .filter_map(|section| {
+                let section_data = top_level.get(&section.name)?;
+                let errors: Vec<SchemaViolation> = section
+                    .validator
+                    .iter_errors(section_data)
+                    .map(|err| section.violation(&err))
+                    .collect();
+                let pointer = format!("/{}", encode_pointer_token(&section.name));
+                unit_violations.extend(
+                    self.units
+                        .check(section_data, &pointer)
+                        .into_iter()
+                        .map(unit_violation),
+                );
+                Some(SectionOutcome {
+                    name: section.name.clone(),
+                    errors,
+                })
+            })
+            .collect();
+
+        let violations = self
+            .profiles
+            .iter()
+            .flat_map(|profile| profile.check(document));
+        for violation in violations {
+            let name = section_of(&violation.instance_path);
+            match sections.iter_mut().find(|section| section.name == name) {
+                Some(section) => section.errors.push(violation),
+                None => sections.push(SectionOutcome {
+                    name,
+                    errors: vec![violation],
+                }),
+            }
+        }
+        sections.sort_by_key(|section| {
+            self.version
+                .sections
+                .iter()
+                .position(|spec| spec.name == section.name)
+        });
+
+        Ok(ValidationOutcome {
+            unknown_sections,
+            unknown_fields: self.schema.unknown_fields(document),
+            missing_sections,
+            sections,
+            unit_violations,
+        })
+    }
+
+    /// Validate a typed header, e.g. a [`v2::FaMetadataHeader`].
+    pub fn validate_header<H: FamhHeader>(
+        &self,
+        header: &H,
+    ) -> Result<ValidationOutcome, Box<dyn std::error::Error>> {
+        let folder = self.version.folder();
+        if [SchemaVersion::V1, SchemaVersion::V2]
+            .iter()
+            .any(|version| version.as_str() == folder)
+            && folder != H::VERSION.as_str()
+        {
+            return Err(format!(
+                "Cannot validate a FAMH {} header against the {} schema",
+                H::VERSION,
+                self.version.name
+            )
+            .into());
+        }
+        self.validate(&famh_model::to_value(header)?)
+    }
+}
+
+impl CompiledSection {
+    fn violation(&self, err: &jsonschema::ValidationError<'_>) -> SchemaViolation {
+        // Validators are compiled from `{"$ref": "<fragment>#<section>"}`, so
+        // every schema path starts with that `$ref`.
+        let schema_path = err.schema_path.as_str();
+        let schema_path = schema_path.strip_prefix("/$ref").unwrap_or(schema_path);
+        let keyword = decode_pointer_token(schema_path.rsplit('/').next().unwrap_or_default());
+        SchemaViolation {
+            instance_path: format!("/{}{}", self.name, err.instance_path.as_str()),
+            keyword,
+            schema_path: format!("{}{}", self.location, schema_path),
+            message: err.to_string(),
+            profile: None,
+        }
+    }
+}
+
+impl CompiledProfile {
+    fn check(&self, document: &Value) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        for (section, validator) in &self.overlays {
+            let Some(section_data) = document.get(section) else {
+                continue;
+            };
+            violations.extend(validator.iter_errors(section_data).map(|err| {
+                let schema_path = err.schema_path.as_str();
+                SchemaViolation {
+                    instance_path: format!("/{}{}", section, err.instance_path.as_str()),
+                    keyword: schema_path
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    schema_path: format!("{}#/sections/{}{}", self.name, section, schema_path),
+                    message: err.to_string(),
+                    profile: Some(self.name.clone()),
+                }
+            }));
+        }
+
+        for (index, pointer) in self.required.iter().enumerate() {
+            if document.pointer(pointer).is_some() {
+                continue;
+            }
+            // Like the `required` keyword, report the missing field on the
+            // closest object that exists, or on its section.
+            let section = format!("/{}", encode_pointer_token(&section_of(pointer)));
+            let mut parent = pointer.as_str();
+            while let Some((ancestor, _)) = parent.rsplit_once('/')
+                && ancestor.len() >= section.len()
+            {
+                parent = ancestor;
+                if document.pointer(parent).is_some() {
+                    break;
+                }
+            }
+            let missing: Vec<String> = pointer[parent.len()..]
+                .trim_start_matches('/')
+                .split('/')
+                .map(decode_pointer_token)
+                .collect();
+            let field = if parent == pointer {
+                section_of(pointer)
+            } else {
+                missing.join(".")
+            };
+            violations.push(SchemaViolation {
+                instance_path: parent.to_string(),
+                keyword: "required".to_string(),
+                schema_path: format!("{}#/required/{}", self.name, index),
+                message: format!("\"{field}\" is a required property"),
+                profile: Some(self.name.clone()),
+            });
+        }
+
+        for (field, pointer, values) in &self.allowed {
+            let Some(value) = document.pointer(pointer) else {
+                continue;
+            };
+            if values.contains(value) {
+                continue;
+            }
+            violations.push(SchemaViolation {
+                instance_path: pointer.clone(),
+                keyword: "enum".to_string(),
+                schema_path: format!("{}#/allowed/{}", self.name, encode_pointer_token(field)),
+                message: format!("{} is not one of {}", value, Value::from(values.clone())),
+                profile: Some(self.name.clone()),
+            });
+        }
+        violations
+    }
+}
+
+/// Name of the root-level section a JSON pointer points into.
+fn section_of(pointer: &str) -> String {
+    decode_pointer_token(
+        pointer
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default(),
+    )
+}
+
+fn unit_violation(violation: UnitViolation) -> SchemaViolation {
+    SchemaViolation {
+        instance_path: violation.instance_path,
+        keyword: "unit".to_string(),
+        schema_path: format!("units#/{}", violation.expected),
+        message: violation.message,
+        profile: None,
+    }
+}
+
+impl ValidationOutcome {
+    /// Whether the document is valid. Unknown sections and unit violations
+    /// only make it invalid in `strict` mode.
+    pub fn is_valid(&self, strict: bool) -> bool {
+        self.missing_sections.is_empty()
+            && (!strict || self.unknown_sections.is_empty() && self.unit_violations.is_empty())
+            && self.sections.iter().all(SectionOutcome::is_valid)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.sections
+            .iter()
+            .map(|section| section.errors.len())
+            .sum()
+    }
+}
+
+impl SectionOutcome {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixture_validator(version: &str) -> FamhValidator {
+        FamhValidator::new(&SchemaCache::fixture(version)).unwrap()
+    }
+
+    fn fixture(path: &str) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_validator_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FamhValidator>();
+    }
+
+    #[test]
+    fn test_validates_many_documents_with_one_validator() {
+        let validator = fixture_validator("v2");
+        let valid = fixture("tests/fixtures/v2/complete_example_v2.json");
+        let invalid = fixture("tests/fixtures/v2/complete_example_multiple_failures_v2.json");
+
+        for _ in 0..3 {
+            assert!(validator.validate(&valid).unwrap().is_valid(false));
+            let outcome = validator.validate(&invalid).unwrap();
+            assert!(!outcome.is_valid(false));
+            assert!(outcome.error_count() > 1);
+        }
+    }
+
+    #[test]
+    fn test_validator_is_shared_across_threads() {
+        let validator = fixture_validator("v1");
+        let document = fixture("tests/fixtures/v1/minimal_example_optical.json");
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| validator.validate(&document).unwrap()))
+                .collect();
+            for handle in handles {
+                assert!(handle.join().unwrap().is_valid(false));
+            }
+        });
+    }
+
+    #[test]
+    fn test_validate_header_checks_typed_headers() {
+        let validator = fixture_validator("v2");
+        let header = v2::FaMetadataHeader::from_value(fixture(
+            "tests/fixtures/v2/minimal_example_optical.json",
+        ))
+        .unwrap();
+
+        assert!(validator.validate_header(&header).unwrap().is_valid(false));
+
+        let v1_header = v1::FaMetadataHeader::from_value(fixture(
+            "tests/fixtures/v1/minimal_example_optical.json",
+        ))
+        .unwrap();
+        let err = validator.validate_header(&v1_header).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot validate a FAMH v1 header against the v2 schema"
+        );
+    }
+
+    #[test]
+    fn test_violations_name_keyword_and_schema_path() {
+        let validator = fixture_validator("v2");
+        let outcome = validator
+            .validate(&json!({ "generalSection": { "fileName": "" } }))
+            .unwrap();
+
+        let violation = &outcome.sections[0].errors[0];
+        assert_eq!(violation.instance_path, "/generalSection/fileName");
+        assert_eq!(violation.keyword, "minLength");
+        assert_eq!(
+            violation.schema_path,
+            "generalSection.json#/properties/generalSection/properties/fileName/minLength"
+        );
+    }
+
+    #[test]
+    fn test_units_of_the_wrong_dimension_only_fail_strict_validation() {
+        let validator = fixture_validator("v1");
+        let mut document = fixture("tests/fixtures/v1/minimal_example_sem.json");
+        document["Method Specific"]["Scanning Electron Microscopy"]["Accelerating Voltage"]["Unit"] =
+            json!("mm");
+
+        let outcome = validator.validate(&document).unwrap();
+
+        assert_eq!(outcome.error_count(), 0);
+        assert!(outcome.is_valid(false));
+        assert!(!outcome.is_valid(true));
+        let errors = &outcome.unit_violations;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].instance_path,
+            "/Method Specific/Scanning Electron Microscopy/Accelerating Voltage/Unit"
+        );
+        assert_eq!(errors[0].keyword, "unit");
+        assert_eq!(errors[0].schema_path, "units#/voltage");
+    }
+
+    #[test]
+    fn test_outcome_reports_unknown_and_missing_sections() {
+        let validator = fixture_validator("v2");
+        let outcome = validator
+            .validate(&json!({ "toolSpecific": {}, "General": {} }))
+            .unwrap();
+
+        assert_eq!(outcome.unknown_sections, vec!["General"]);
+        assert_eq!(
+            outcome.unknown_fields[0].describe("section"),
+            "Unknown section \"General\", did you mean \"generalSection\"?"
+        );
+        assert!(
+            outcome
+                .missing_sections
+                .contains(&"generalSection".to_string())
+        );
+        assert!(!outcome.is_valid(false));
+    }
+
+    #[test]
+    fn test_profile_violations_name_the_profile() {
+        let profile = Profile::from_value(
+            "automotive",
+            json!({
+                "sections": {
+                    "customerSpecific": {
+                        "properties": {
+                            "orderId": { "pattern": "^FA-" },
+                            "sampleId": {
+                                "$ref": "customerSection.json#/properties/customerSpecific/properties/dieCoordinates"
+                            }
+                        }
+                    }
+                },
+                "required": ["customerSpecific.lotNumber", "toolSpecific"],
+                "allowed": { "customerSpecific.customFields.toolCalibrated": [true] }
+            }),
+        )
+        .unwrap();
+        let validator = fixture_validator("v2").with_profile(&profile).unwrap();
+        let mut document = fixture("tests/fixtures/v2/complete_example_v2.json");
+        let customer = &mut document["customerSpecific"];
+        customer["orderId"] = json!("REQ-1");
+        customer["customFields"] = json!({ "toolCalibrated": false });
+        customer.as_object_mut().unwrap().remove("lotNumber");
+
+        let outcome = validator.validate(&document).unwrap();
+
+        let errors: Vec<(&str, &str, &str)> = outcome
+            .sections
+            .iter()
+            .flat_map(|section| &section.errors)
+            .map(|error| {
+                assert_eq!(error.profile.as_deref(), Some("automotive"));
+                (
+                    error.instance_path.as_str(),
+                    error.keyword.as_str(),
+                    error.schema_path.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "/customerSpecific/orderId",
+                    "pattern",
+                    "automotive#/sections/customerSpecific/properties/orderId/pattern"
+                ),
+                (
+                    "/customerSpecific/sampleId",
+                    "type",
+                    "automotive#/sections/customerSpecific/properties/sampleId/$ref/type"
+                ),
+                ("/customerSpecific", "required", "automotive#/required/0"),
+                (
+                    "/customerSpecific/customFields/toolCalibrated",
+                    "enum",
+                    "automotive#/allowed/customerSpecific.customFields.toolCalibrated"
+                ),
+                ("/toolSpecific", "required", "automotive#/required/1"),
+            ]
+        );
+        let tool = outcome
+            .sections
+            .iter()
+            .find(|section| section.name == "toolSpecific")
+            .unwrap();
+        assert_eq!(
+            tool.errors[0].message,
+            "\"toolSpecific\" is a required property"
+        );
+        assert!(!outcome.is_valid(false));
+    }
+
+    #[test]
+    fn test_profiles_must_fit_the_schema_version() {
+        let profile = Profile::load("automotive", Some(std::path::Path::new("profiles"))).unwrap();
+        let err = fixture_validator("v1")
+            .with_profile(&profile)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Profile 'automotive' is written for schema v2, not v1"
+        );
+
+        let profile =
+            Profile::from_value("p", json!({ "required": ["General.fileName"] })).unwrap();
+        let err = fixture_validator("v2")
+            .with_profile(&profile)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Profile 'p' refers to section 'General', which schema v2 does not have"
+        );
+    }
+}
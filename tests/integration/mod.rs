@@ -1,2 +1,3 @@
+mod validate_batch;
 mod validate_v1;
 mod validate_v2;
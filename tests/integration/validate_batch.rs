@@ -0,0 +1,128 @@
+//! Integration tests for batch validation of FAMH files
+
+use famdo::commands::validate::{BatchOptions, validate_files};
+use famdo::inputs::{InputOptions, collect_inputs};
+use famdo::report::{BatchSummary, FileResult};
+use famdo::schema::SchemaConfig;
+use std::io::Write;
+
+fn local_schema() -> SchemaConfig {
+    SchemaConfig {
+        dir: Some("tests/schemas".into()),
+        ..SchemaConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_validates_fixture_directories_recursively() {
+    let options = InputOptions {
+        recursive: true,
+        ..InputOptions::default()
+    };
+    let mut paths = collect_inputs(&["tests/fixtures".to_string()], &options).unwrap();
+    paths.push("tests/fixtures/missing.json".into());
+
+    let options = BatchOptions {
+        no_cache: true,
+        jobs: 4,
+        ..BatchOptions::default()
+    };
+    let batch = validate_files(&paths, "auto", &local_schema(), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        batch.summary(),
+        BatchSummary {
+            valid: 9,
+            invalid: 1,
+            errored: 1
+        }
+    );
+    let versions: Vec<&str> = batch
+        .files
+        .iter()
+        .filter_map(|file| match file {
+            FileResult::Validated(report) => Some(report.version.as_str()),
+            FileResult::Failed { .. } => None,
+        })
+        .collect();
+    assert_eq!(
+        versions.iter().filter(|version| **version == "v1").count(),
+        5
+    );
+    assert_eq!(
+        batch.files.last().unwrap().path(),
+        "tests/fixtures/missing.json"
+    );
+    assert_eq!(
+        batch.most_frequent_failures(1)[0].0,
+        "/dataEvaluation/pointsOfInterest/*/coordinates/value"
+    );
+}
+
+#[tokio::test]
+async fn test_validates_each_record_of_an_ndjson_stream() {
+    let record = |path: &str| {
+        let text = std::fs::read_to_string(path).unwrap();
+        let document: serde_json::Value = serde_json::from_str(&text).unwrap();
+        document.to_string()
+    };
+    let mut stream = tempfile::NamedTempFile::new().unwrap();
+    writeln!(
+        stream,
+        "{}",
+        record("tests/fixtures/v2/minimal_example_optical.json")
+    )
+    .unwrap();
+    writeln!(stream).unwrap();
+    writeln!(
+        stream,
+        "{}",
+        record("tests/fixtures/v1/minimal_example_fib.json")
+    )
+    .unwrap();
+    writeln!(stream, r#"{{"generalSection": {{"fileName": ""}}}}"#).unwrap();
+    writeln!(stream, "{{not json").unwrap();
+
+    let paths = vec![stream.path().to_path_buf()];
+    let options = BatchOptions {
+        no_cache: true,
+        ndjson: true,
+        jobs: 2,
+        ..BatchOptions::default()
+    };
+    let batch = validate_files(&paths, "auto", &local_schema(), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        batch.summary(),
+        BatchSummary {
+            valid: 2,
+            invalid: 1,
+            errored: 1
+        }
+    );
+    let path = stream.path().to_string_lossy();
+    let FileResult::Validated(invalid) = &batch.files[2] else {
+        panic!("record 4 was not validated");
+    };
+    assert_eq!(invalid.record, Some(4));
+    assert_eq!(invalid.name(), format!("{path}:4"));
+    let issue = invalid
+        .issues()
+        .into_iter()
+        .find(|issue| issue.instance_path == "/generalSection/fileName")
+        .unwrap();
+    assert_eq!(issue.location.unwrap().line, 4);
+    let FileResult::Failed {
+        path: failed,
+        error,
+    } = &batch.files[3]
+    else {
+        panic!("record 5 was validated");
+    };
+    assert_eq!(failed, &format!("{path}:5"));
+    assert!(error.starts_with(&format!("{path}:5:2: ")), "{error}");
+}
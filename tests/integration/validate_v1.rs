@@ -1,55 +1,114 @@
 //! Integration tests for validation of v1 FAMH files
 
 use famdo::commands::validate::validate_json;
-use famdo::schema::SchemaVersion;
+use famdo::schema::{SchemaConfig, SchemaVersion};
 
 #[tokio::test]
+#[ignore = "downloads the upstream schema"]
 async fn test_valid_minimal_example_optical() {
     let famh_path = "tests/fixtures/v1/minimal_example_optical.json";
-    let result = validate_json(&famh_path, SchemaVersion::V1, true, false).await;
+    let result = validate_json(
+        famh_path,
+        SchemaVersion::V1,
+        &SchemaConfig::default(),
+        true,
+        false,
+    )
+    .await;
 
     assert!(
         result.is_ok(),
         "Should successfully validate optical example"
     );
-    assert!(result.unwrap(), "Optical example should be valid v1");
+    assert!(
+        result.unwrap().is_valid(),
+        "Optical example should be valid v1"
+    );
 }
 
 #[tokio::test]
+#[ignore = "downloads the upstream schema"]
 async fn test_valid_minimal_example_fib() {
     let famh_path = "tests/fixtures/v1/minimal_example_fib.json";
-    let result = validate_json(&famh_path, SchemaVersion::V1, true, false).await;
+    let result = validate_json(
+        famh_path,
+        SchemaVersion::V1,
+        &SchemaConfig::default(),
+        true,
+        false,
+    )
+    .await;
 
     assert!(result.is_ok(), "Should successfully validate FIB example");
-    assert!(result.unwrap(), "FIB example should be valid v1");
+    assert!(result.unwrap().is_valid(), "FIB example should be valid v1");
 }
 
 #[tokio::test]
+#[ignore = "downloads the upstream schema"]
 async fn test_valid_minimal_example_sem() {
     let famh_path = "tests/fixtures/v1/minimal_example_sem.json";
-    let result = validate_json(&famh_path, SchemaVersion::V1, true, false).await;
+    let result = validate_json(
+        famh_path,
+        SchemaVersion::V1,
+        &SchemaConfig::default(),
+        true,
+        false,
+    )
+    .await;
 
     assert!(result.is_ok(), "Should successfully validate SEM example");
-    assert!(result.unwrap(), "SEM example should be valid v1");
+    assert!(result.unwrap().is_valid(), "SEM example should be valid v1");
 }
 
 #[tokio::test]
+#[ignore = "downloads the upstream schema"]
 async fn test_valid_rectangle_example_sem() {
     let famh_path = "tests/fixtures/v1/rectangle_example_sem.json";
-    let result = validate_json(&famh_path, SchemaVersion::V1, true, false).await;
+    let result = validate_json(
+        famh_path,
+        SchemaVersion::V1,
+        &SchemaConfig::default(),
+        true,
+        false,
+    )
+    .await;
 
     assert!(
         result.is_ok(),
         "Should successfully validate rectangle example"
     );
-    assert!(result.unwrap(), "Rectangle example should be valid v1");
+    assert!(
+        result.unwrap().is_valid(),
+        "Rectangle example should be valid v1"
+    );
 }
 
 #[tokio::test]
+#[ignore = "downloads the upstream schema"]
 async fn test_valid_rectangle_example_with_multiple_failures() {
     let famh_path = "tests/fixtures/v1/rectangle_example_sem_multiple_failures.json";
-    let result = validate_json(&famh_path, SchemaVersion::V1, true, false).await;
+    let result = validate_json(
+        famh_path,
+        SchemaVersion::V1,
+        &SchemaConfig::default(),
+        true,
+        false,
+    )
+    .await;
 
     assert!(result.is_ok(), "Should successfully process the file");
-    assert!(result.unwrap(), "Rectangle example should be valid v1");
+    assert!(
+        result.unwrap().is_valid(),
+        "Rectangle example should be valid v1"
+    );
+}
+
+#[tokio::test]
+#[ignore = "downloads the upstream schema"]
+async fn test_auto_detects_v1_version() {
+    let famh_path = "tests/fixtures/v1/minimal_example_sem.json";
+    let result = validate_json(famh_path, "auto", &SchemaConfig::default(), true, false).await;
+
+    assert!(result.is_ok(), "Should detect the v1 version automatically");
+    assert!(result.unwrap().is_valid(), "SEM example should be valid v1");
 }